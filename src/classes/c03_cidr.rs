@@ -0,0 +1,445 @@
+/// This module is an exercise on enums and pattern-matching, built on
+/// `IpAddrKind` and `IpAddr` from `c03_enums`.
+/// A CIDR block (Classless Inter-Domain Routing) is an address plus a prefix length:
+///         192.168.0.0/24      the first 24 bits are the network, the other 8 the hosts
+///         fe80::/10           the first 10 bits are fixed, the other 118 are free
+/// With it we compute network/broadcast addresses, host counts, membership,
+/// and we split and merge blocks.
+/// See
+///         https://en.wikipedia.org/wiki/Classless_Inter-Domain_Routing
+///
+/// IPv4 and IPv6 do the same arithmetic on different widths (32 and 128 bits),
+/// with a few differences (IPv6 has no broadcast address).
+/// We keep every address as a u128 internally, and we `match` on the kind
+/// whenever the two families differ.
+
+use crate::classes::c03_enums::{IpAddr, IpAddrKind, IpParseError};
+use std::fmt;
+use std::str::FromStr;
+
+/// A network: the address has all its host bits set to 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+// the number of bits of an address of this kind
+fn width(kind: IpAddrKind) -> u8 {
+    match kind {
+        IpAddrKind::V4 => 32,
+        IpAddrKind::V6 => 128,
+    }
+}
+
+fn to_bits(addr: &IpAddr) -> u128 {
+    match *addr {
+        IpAddr::V4(a, b, c, d) => u32::from_be_bytes([a, b, c, d]) as u128,
+        IpAddr::V6(segments) => segments.iter().fold(0u128, |acc, s| (acc << 16) | *s as u128),
+    }
+}
+
+fn from_bits(kind: IpAddrKind, bits: u128) -> IpAddr {
+    match kind {
+        IpAddrKind::V4 => {
+            let [a, b, c, d] = (bits as u32).to_be_bytes();
+            IpAddr::V4(a, b, c, d)
+        }
+        IpAddrKind::V6 => {
+            let mut segments = [0u16; 8];
+            for (i, s) in segments.iter_mut().enumerate() {
+                *s = (bits >> (16 * (7 - i))) as u16;
+            }
+            IpAddr::V6(segments)
+        }
+    }
+}
+
+// the mask of the host bits: the last `width - prefix` bits set
+fn host_mask(kind: IpAddrKind, prefix: u8) -> u128 {
+    // shifting a u128 by 128 overflows, so the empty prefix of IPv6 is a special case
+    match width(kind) - prefix {
+        128 => u128::MAX,
+        free => (1u128 << free) - 1,
+    }
+}
+
+// the mask of the network bits: the first `prefix` bits set
+fn mask(kind: IpAddrKind, prefix: u8) -> u128 {
+    host_mask(kind, 0) & !host_mask(kind, prefix)
+}
+
+/// Why a string is not a CIDR block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CidrParseError {
+    /// no `/` in the string
+    MissingPrefix,
+    /// the part before the `/`
+    Address(IpParseError),
+    /// the part after the `/` is not a number
+    InvalidPrefix(String),
+    /// e.g. `/33` for IPv4
+    PrefixTooLong { prefix: u8, max: u8 },
+    /// e.g. `192.168.0.1/24`: did you mean `192.168.0.0/24`?
+    HostBitsSet { network: IpAddr, prefix: u8 },
+}
+
+impl fmt::Display for CidrParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CidrParseError::MissingPrefix => write!(f, "missing `/prefix`"),
+            CidrParseError::Address(_) => write!(f, "invalid network address"),
+            CidrParseError::InvalidPrefix(p) => write!(f, "invalid prefix length {:?}", p),
+            CidrParseError::PrefixTooLong { prefix, max } => write!(f, "prefix /{} is longer than {} bits", prefix, max),
+            CidrParseError::HostBitsSet { network, prefix } => write!(f, "host bits are set, did you mean {}/{}?", network, prefix),
+        }
+    }
+}
+
+impl std::error::Error for CidrParseError {
+    // the address error is the CAUSE of ours: `source` lets callers walk the chain
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CidrParseError::Address(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Cidr {
+    /// Builds a block from any address inside it, clearing the host bits.
+    /// Returns `None` if the prefix is too long for the address.
+    pub fn new(addr: IpAddr, prefix: u8) -> Option<Cidr> {
+        let kind = addr.kind();
+        if prefix > width(kind) {
+            return None;
+        }
+        let network = from_bits(kind, to_bits(&addr) & mask(kind, prefix));
+        Some(Cidr { network, prefix })
+    }
+
+    pub fn kind(&self) -> IpAddrKind {
+        self.network.kind()
+    }
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+    pub fn network(&self) -> IpAddr {
+        self.network
+    }
+
+    // the numeric range of the block, both ends included
+    fn range(&self) -> (u128, u128) {
+        let first = to_bits(&self.network);
+        (first, first | host_mask(self.kind(), self.prefix))
+    }
+
+    /// The last address of the block
+    pub fn last(&self) -> IpAddr {
+        from_bits(self.kind(), self.range().1)
+    }
+
+    /// Only IPv4 has broadcast addresses
+    pub fn broadcast(&self) -> Option<IpAddr> {
+        match self.kind() {
+            IpAddrKind::V4 => Some(self.last()),
+            IpAddrKind::V6 => None,
+        }
+    }
+
+    /// Number of addresses that can be given to hosts.
+    /// In IPv4 the network and broadcast addresses are excluded, except for
+    /// /31 (point-to-point links, RFC 3021) and /32 (a single host).
+    /// In IPv6 every address counts; a /0 has 2^128 of them, one more than u128::MAX,
+    /// so it saturates.
+    pub fn host_count(&self) -> u128 {
+        let free_bits = width(self.kind()) - self.prefix;
+        match (self.kind(), free_bits) {
+            (IpAddrKind::V4, 0) => 1,
+            (IpAddrKind::V4, 1) => 2,
+            (IpAddrKind::V4, n) => (1u128 << n) - 2,
+            (IpAddrKind::V6, 128) => u128::MAX,
+            (IpAddrKind::V6, n) => 1u128 << n,
+        }
+    }
+
+    pub fn contains(&self, addr: &IpAddr) -> bool {
+        // an IPv4 address is never inside an IPv6 block, and vice versa
+        if addr.kind() != self.kind() {
+            return false;
+        }
+        let (first, last) = self.range();
+        (first..=last).contains(&to_bits(addr))
+    }
+
+    /// Whether `other` is entirely inside `self`
+    pub fn contains_cidr(&self, other: &Cidr) -> bool {
+        other.kind() == self.kind() && other.prefix >= self.prefix && self.contains(&other.network)
+    }
+
+    /// Iterates over the usable host addresses, as counted by `host_count`
+    pub fn hosts(&self) -> Hosts {
+        let (mut first, mut last) = self.range();
+        if self.kind() == IpAddrKind::V4 && self.prefix < 31 {
+            first += 1;
+            last -= 1;
+        }
+        Hosts { kind: self.kind(), next: Some(first), last }
+    }
+
+    /// The two halves of the block, or `None` for a single address
+    pub fn split(&self) -> Option<(Cidr, Cidr)> {
+        if self.prefix == width(self.kind()) {
+            return None;
+        }
+        let prefix = self.prefix + 1;
+        let (first, _) = self.range();
+        let second = first | (1u128 << (width(self.kind()) - prefix));
+        Some((
+            Cidr { network: self.network, prefix },
+            Cidr { network: from_bits(self.kind(), second), prefix },
+        ))
+    }
+
+    /// All the subnets of length `prefix` inside this block, in order
+    pub fn subnets(&self, prefix: u8) -> Option<Subnets> {
+        if prefix < self.prefix || prefix > width(self.kind()) {
+            return None;
+        }
+        let (first, last) = self.range();
+        Some(Subnets { kind: self.kind(), prefix, next: Some(first), last })
+    }
+
+    /// The block one bit shorter that contains this one
+    pub fn supernet(&self) -> Option<Cidr> {
+        match self.prefix {
+            0 => None,
+            p => Cidr::new(self.network, p - 1),
+        }
+    }
+
+    // a numeric key to sort blocks: IPv4 first, then by address, then the biggest block first
+    fn sort_key(&self) -> (u8, u128, u8) {
+        (width(self.kind()), to_bits(&self.network), self.prefix)
+    }
+
+    /// Merges a list of blocks into the smallest equivalent list:
+    /// blocks inside other blocks disappear, and sibling blocks are merged
+    /// into their supernet, e.g. 10.0.0.0/25 + 10.0.0.128/25 = 10.0.0.0/24
+    pub fn merge(blocks: &[Cidr]) -> Vec<Cidr> {
+        let mut sorted = blocks.to_vec();
+        sorted.sort_by_key(|c| c.sort_key());
+        let mut result: Vec<Cidr> = vec![];
+        for block in sorted {
+            // sorted by address, so only the last block kept can contain this one
+            if let Some(prev) = result.last() {
+                if prev.contains_cidr(&block) {
+                    continue;
+                }
+            }
+            result.push(block);
+            // merging two siblings may produce a block that is the sibling of the previous one:
+            // keep merging at the end of the list, like a stack
+            while result.len() >= 2 {
+                let b = result[result.len() - 1];
+                let a = result[result.len() - 2];
+                match a.supernet() {
+                    Some(parent) if a.prefix == b.prefix && parent.network == a.network && parent.contains_cidr(&b) => {
+                        result.pop();
+                        result.pop();
+                        result.push(parent);
+                    }
+                    _ => break,
+                }
+            }
+        }
+        result
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = CidrParseError;
+
+    fn from_str(s: &str) -> Result<Cidr, CidrParseError> {
+        let (addr, prefix) = s.split_once('/').ok_or(CidrParseError::MissingPrefix)?;
+        let addr: IpAddr = addr.parse().map_err(CidrParseError::Address)?;
+        let prefix: u8 = match prefix.parse() {
+            Ok(p) if !prefix.starts_with('+') => p,
+            _ => return Err(CidrParseError::InvalidPrefix(prefix.to_string())),
+        };
+        let max = width(addr.kind());
+        if prefix > max {
+            return Err(CidrParseError::PrefixTooLong { prefix, max });
+        }
+        let cidr = Cidr::new(addr, prefix).unwrap();
+        if cidr.network != addr {
+            return Err(CidrParseError::HostBitsSet { network: cidr.network, prefix });
+        }
+        Ok(cidr)
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+/// Iterator returned by `Cidr::hosts`
+pub struct Hosts {
+    kind: IpAddrKind,
+    // `None` once finished: `last + 1` may not exist (think of 255.255.255.255)
+    next: Option<u128>,
+    last: u128,
+}
+
+impl Iterator for Hosts {
+    type Item = IpAddr;
+    fn next(&mut self) -> Option<IpAddr> {
+        let current = self.next.filter(|n| *n <= self.last)?;
+        self.next = current.checked_add(1);
+        Some(from_bits(self.kind, current))
+    }
+}
+
+/// Iterator returned by `Cidr::subnets`
+pub struct Subnets {
+    kind: IpAddrKind,
+    prefix: u8,
+    next: Option<u128>,
+    last: u128,
+}
+
+impl Iterator for Subnets {
+    type Item = Cidr;
+    fn next(&mut self) -> Option<Cidr> {
+        let current = self.next.filter(|n| *n <= self.last)?;
+        let step = match width(self.kind) - self.prefix {
+            128 => None,
+            free => Some(1u128 << free),
+        };
+        self.next = step.and_then(|s| current.checked_add(s));
+        Some(Cidr { network: from_bits(self.kind, current), prefix: self.prefix })
+    }
+}
+
+pub fn cidrexample() {
+    let lan: Cidr = "192.168.0.0/24".parse().unwrap();
+    println!("{}: network {}, broadcast {:?}, {} hosts", lan, lan.network(), lan.broadcast(), lan.host_count());
+    println!("first hosts: {:?}", lan.hosts().take(3).map(|h| h.to_string()).collect::<Vec<_>>());
+    let (a, b) = lan.split().unwrap();
+    println!("halves: {} and {}", a, b);
+    println!("/26 subnets: {:?}", lan.subnets(26).unwrap().map(|c| c.to_string()).collect::<Vec<_>>());
+    let link: Cidr = "fe80::/10".parse().unwrap();
+    println!("{}: last {}, contains fe80::1? {}", link, link.last(), link.contains(&"fe80::1".parse().unwrap()));
+    let merged = Cidr::merge(&[a, b, "10.0.0.0/8".parse().unwrap(), "10.1.0.0/16".parse().unwrap()]);
+    println!("merged: {:?}", merged.iter().map(|c| c.to_string()).collect::<Vec<_>>());
+    for bad in ["192.168.0.1/24", "10.0.0.0/33", "10.0.0/8", "10.0.0.0"] {
+        println!("{} -> {}", bad, bad.parse::<Cidr>().unwrap_err());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cidr(s: &str) -> Cidr {
+        s.parse().unwrap()
+    }
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn v4_network_broadcast_and_hosts() {
+        let c = cidr("192.168.0.0/24");
+        assert_eq!(c.network(), ip("192.168.0.0"));
+        assert_eq!(c.broadcast(), Some(ip("192.168.0.255")));
+        assert_eq!(c.host_count(), 254);
+        let hosts: Vec<IpAddr> = c.hosts().collect();
+        assert_eq!(hosts.len(), 254);
+        assert_eq!(hosts[0], ip("192.168.0.1"));
+        assert_eq!(hosts[253], ip("192.168.0.254"));
+        assert_eq!(cidr("10.0.0.0/31").hosts().count(), 2);
+        assert_eq!(cidr("10.0.0.7/32").hosts().collect::<Vec<_>>(), vec![ip("10.0.0.7")]);
+        assert_eq!(cidr("0.0.0.0/0").host_count(), (1u128 << 32) - 2);
+    }
+
+    #[test]
+    fn v6_has_no_broadcast() {
+        let c = cidr("fe80::/10");
+        assert_eq!(c.broadcast(), None);
+        assert_eq!(c.last(), ip("febf:ffff:ffff:ffff:ffff:ffff:ffff:ffff"));
+        assert_eq!(c.host_count(), 1u128 << 118);
+        assert_eq!(cidr("::/0").host_count(), u128::MAX);
+        assert_eq!(cidr("2001:db8::/126").hosts().count(), 4);
+    }
+
+    #[test]
+    fn iteration_stops_at_the_end_of_the_address_space() {
+        assert_eq!(cidr("255.255.255.254/31").hosts().count(), 2);
+        assert_eq!(cidr("ffff:ffff:ffff:ffff:ffff:ffff:ffff:fffc/126").hosts().count(), 4);
+        assert_eq!(cidr("255.255.255.0/24").subnets(26).unwrap().count(), 4);
+    }
+
+    #[test]
+    fn membership() {
+        let c = cidr("172.16.0.0/12");
+        assert!(c.contains(&ip("172.31.255.255")));
+        assert!(!c.contains(&ip("172.32.0.0")));
+        assert!(!c.contains(&ip("::ffff:172.16.0.1")));
+        assert!(cidr("2001:db8::/32").contains(&ip("2001:db8:1::1")));
+        assert!(c.contains_cidr(&cidr("172.20.0.0/16")));
+        assert!(!cidr("172.20.0.0/16").contains_cidr(&c));
+    }
+
+    #[test]
+    fn split_and_subnets() {
+        let (a, b) = cidr("10.0.0.0/8").split().unwrap();
+        assert_eq!((a, b), (cidr("10.0.0.0/9"), cidr("10.128.0.0/9")));
+        assert_eq!(cidr("10.0.0.1/32").split(), None);
+        let subnets: Vec<String> = cidr("2001:db8::/32").subnets(34).unwrap().map(|c| c.to_string()).collect();
+        assert_eq!(subnets, vec!["2001:db8::/34", "2001:db8:4000::/34", "2001:db8:8000::/34", "2001:db8:c000::/34"]);
+        assert!(cidr("10.0.0.0/8").subnets(7).is_none());
+        assert!(cidr("10.0.0.0/8").subnets(33).is_none());
+    }
+
+    #[test]
+    fn merge_aggregates_siblings_and_drops_contained_blocks() {
+        let merged = Cidr::merge(&[
+            cidr("10.0.1.0/24"),
+            cidr("10.0.0.0/24"),
+            cidr("10.0.2.0/23"),
+            cidr("10.0.2.128/25"),
+            cidr("192.168.0.0/24"),
+            cidr("fe80::/11"),
+            cidr("fea0::/11"),
+        ]);
+        assert_eq!(merged, vec![cidr("10.0.0.0/22"), cidr("192.168.0.0/24"), cidr("fe80::/10")]);
+        // two blocks of the same size that are adjacent but NOT siblings stay apart
+        assert_eq!(Cidr::merge(&[cidr("10.0.1.0/24"), cidr("10.0.2.0/24")]).len(), 2);
+        assert_eq!(Cidr::merge(&[]), vec![]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        use std::error::Error;
+        assert_eq!("10.0.0.0".parse::<Cidr>(), Err(CidrParseError::MissingPrefix));
+        assert_eq!("10.0.0.0/x".parse::<Cidr>(), Err(CidrParseError::InvalidPrefix(String::from("x"))));
+        assert_eq!("10.0.0.0/+8".parse::<Cidr>(), Err(CidrParseError::InvalidPrefix(String::from("+8"))));
+        assert_eq!("10.0.0.0/33".parse::<Cidr>(), Err(CidrParseError::PrefixTooLong { prefix: 33, max: 32 }));
+        assert_eq!("::/129".parse::<Cidr>(), Err(CidrParseError::PrefixTooLong { prefix: 129, max: 128 }));
+        assert_eq!("192.168.0.1/24".parse::<Cidr>(),
+                   Err(CidrParseError::HostBitsSet { network: ip("192.168.0.0"), prefix: 24 }));
+        let err = "10.0.0/8".parse::<Cidr>().unwrap_err();
+        assert_eq!(err, CidrParseError::Address(IpParseError::V4PartCount(3)));
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for s in ["0.0.0.0/0", "192.168.0.0/16", "10.1.2.3/32", "::/0", "fe80::/10", "2001:db8::/32"] {
+            assert_eq!(cidr(s).to_string(), s);
+        }
+    }
+}
@@ -12,16 +12,27 @@
 // such as F#, OCaml, and Haskell.
 
 // the `IpAddrKind` enum defines 2 different values: `V4` and `V6`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum IpAddrKind {
     V4,
     V6,
 }
-// the `IpAddr` enum defines 3 values:
-//   V4 has 4 i32 fields, V6 has a String field and V0 has none
-enum IpAddr {
-    V4(i32,i32,i32,i32),
-    V6(String),
-    V0(),
+// the `IpAddr` enum defines 2 values, each carrying data:
+//   V4 has 4 u8 fields, one per octet: `u8` is exactly the range 0..=255 of an octet
+//   V6 has an array of 8 u16 fields, one per 16-bit segment
+// QUIZ: why not `i32` for the octets?
+
+
+
+
+
+//
+// with i32, IpAddr::V4(300, -1, 0, 0) would be a valid value of our type.
+// Picking the right types makes wrong values impossible to build: the compiler checks them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IpAddr {
+    V4(u8,u8,u8,u8),
+    V6([u16; 8]),
 }
 // the first enum is accessible from other modules, and both `V4` and `V6` are public types
 
@@ -35,9 +46,10 @@ pub fn enum_usage(){
     // here we say that `_four` is a value of a certain variant (V4)
     let _six = IpAddrKind::V6;
 
-    let loopback = IpAddr::V6(String::from("::1"));
+    let loopback = IpAddr::V6([0, 0, 0, 0, 0, 0, 0, 1]);
     let home = IpAddr::V4(127, 0, 0, 1);
     // we can access these fields with pattern-matching, which we describe in a second
+    // (and at the end of this file, `IpAddr` grows into a type we can parse and print)
 }
 
 
@@ -65,6 +77,7 @@ enum Option<T> {
     Some(T),
 }
  */
+// `c03_optionresult` re-implements it, together with its methods `map`, `and_then`, ...
 
 /// This function showcases Rust Options and how to use them
 /// See
@@ -111,7 +124,7 @@ pub fn option(){
 ///     https://doc.rust-lang.org/book/ch18-00-patterns.html?highlight=pattern%20ma#patterns-and-matching
 pub fn patternmatching(){
     let home = IpAddr::V4(127, 0, 0, 1);
-    let loopback = IpAddr::V6(String::from("::1"));
+    let loopback = IpAddr::V6([0, 0, 0, 0, 0, 0, 0, 1]);
 
     // match allows you to compare some value against a series of pattens
     // and execute code based on which pattern matches.
@@ -122,13 +135,14 @@ pub fn patternmatching(){
 
     // QUIZ: is this ok?
     // match home {
-    //     IpAddr::V4(a, b, c, d) => println!("Is V4"),
+    //     IpAddr::V4(127, b, c, d) => println!("Is V4 loopback"),
     //     IpAddr::V6(a) => println!("Is V6")
     // };
     // Y / N
 
 
-    // DNC: error[E0004]: non-exhaustive patterns: `V0` not covered
+    // DNC: error[E0004]: non-exhaustive patterns: `IpAddr::V4(0_u8..=126_u8, _, _, _)` and `IpAddr::V4(128_u8..=u8::MAX, _, _, _)` not covered
+    // notice how precise the compiler is: it knows the range of a u8
     match home {
         // matches any V4 whose first field is 127
         IpAddr::V4(127, b, c, d) => println!("Is V4 loopback"),
@@ -136,12 +150,11 @@ pub fn patternmatching(){
         IpAddr::V4(a, b, c, d) => println!("Is V4"),
         // matches any V6
         IpAddr::V6(a) => println!("Is V6"),
-        // the " _ " matches anything
-        _ => println!(" errror")
     };
     // pattern-matching can return values, so it can be used to set variables
     let _variable = match loopback {
         IpAddr::V4(127, b, c, d) => Some(loopback),
+        // the " _ " matches anything
         _ => None
     };
     // Q : what is the type of `_variable` ?
@@ -205,6 +218,7 @@ pub fn errors() {
         Err(E),
     }
      */
+    // its methods are re-implemented in `c03_optionresult`
     use std::fs::File;
 
     // erase file and comment line to see panic
@@ -261,6 +275,8 @@ pub fn errors() {
     //      Therefore, returning Result is a good default choice when you’re defining a function that might fail.
     // Panic:
     //      you deny users of your code the option to recover
+    // `c03_errors` turns this guide into tested examples, and shows how to build error types
+    // that keep their causes instead of `String`s.
 }
 
 
@@ -316,6 +332,7 @@ use std::fs::File;
 use std::io::{Read, Write};
 use std::io::prelude::*;
 
+// A worked solution, with the bugs of this first attempt fixed, is in `c03_files`
 pub fn readfilecontent () -> Result<(),String>{
 
     // create a new file X -> deal with the Result
@@ -339,6 +356,7 @@ pub fn readfilecontent () -> Result<(),String>{
 // write out calculateS
 // use chars iterator
 // use eq_ignore_ascii_case
+// (`c03_textstats` generalises it to any character, case folding and n-grams)
 fn calculateS(string : &String) -> i32{
     let mut count =0;
     for x in string.chars(){
@@ -348,3 +366,392 @@ fn calculateS(string : &String) -> i32{
     }
     return count;
 }
+
+
+/* ==== A usable IpAddr ====
+   ========================= */
+// We end the lesson by turning the `IpAddr` enum from the top of this file into
+// a real type: one that can be parsed from a string, printed back, classified,
+// and converted to and from the standard library's `std::net::IpAddr`.
+// Almost every method below is a `match` on the two variants.
+// See
+//      https://datatracker.ietf.org/doc/html/rfc5952     (how to print IPv6 addresses)
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Why a string is not an IP address.
+/// Positions are byte offsets in the parsed string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IpParseError {
+    /// the string is empty
+    Empty,
+    /// a character that cannot appear there
+    UnexpectedChar(char, usize),
+    /// nothing between two separators, e.g. `1..2.3`
+    EmptyPart(usize),
+    /// an IPv4 address needs exactly 4 octets
+    V4PartCount(usize),
+    /// an octet above 255
+    OctetOutOfRange(String),
+    /// an octet like `01`, which some tools read as octal
+    LeadingZero(String),
+    /// an IPv6 segment longer than 4 hex digits
+    SegmentTooLong(String),
+    /// `::` can only appear once
+    MultipleDoubleColon,
+    /// an IPv6 address needs 8 segments, or fewer than 8 with a `::`
+    V6SegmentCount(usize),
+}
+
+impl fmt::Display for IpParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IpParseError::Empty => write!(f, "empty address"),
+            IpParseError::UnexpectedChar(c, pos) => write!(f, "unexpected character {:?} at position {}", c, pos),
+            IpParseError::EmptyPart(pos) => write!(f, "missing number at position {}", pos),
+            IpParseError::V4PartCount(n) => write!(f, "an IPv4 address has 4 octets, found {}", n),
+            IpParseError::OctetOutOfRange(o) => write!(f, "octet {} is larger than 255", o),
+            IpParseError::LeadingZero(o) => write!(f, "octet {} has a leading zero", o),
+            IpParseError::SegmentTooLong(s) => write!(f, "segment {} has more than 4 hex digits", s),
+            IpParseError::MultipleDoubleColon => write!(f, "`::` can appear only once"),
+            IpParseError::V6SegmentCount(n) => write!(f, "an IPv6 address has 8 segments, found {}", n),
+        }
+    }
+}
+
+impl std::error::Error for IpParseError {}
+
+// parses "a.b.c.d" starting at byte `offset` of the original string
+fn parse_v4(s: &str, offset: usize) -> Result<[u8; 4], IpParseError> {
+    let parts: Vec<&str> = s.split('.').collect();
+    if parts.len() != 4 {
+        return Err(IpParseError::V4PartCount(parts.len()));
+    }
+    let mut octets = [0u8; 4];
+    let mut pos = offset;
+    for (i, part) in parts.iter().enumerate() {
+        if part.is_empty() {
+            return Err(IpParseError::EmptyPart(pos));
+        }
+        if let Some((j, c)) = part.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+            return Err(IpParseError::UnexpectedChar(c, pos + j));
+        }
+        if part.len() > 1 && part.starts_with('0') {
+            return Err(IpParseError::LeadingZero(part.to_string()));
+        }
+        // a very long string of digits does not even fit a u32: still out of range
+        octets[i] = match part.parse::<u32>() {
+            Ok(n) if n <= 255 => n as u8,
+            _ => return Err(IpParseError::OctetOutOfRange(part.to_string())),
+        };
+        pos += part.len() + 1;
+    }
+    Ok(octets)
+}
+
+// parses the colon-separated segments of one side of a `::`
+// the last segment of the whole address may be an embedded IPv4, e.g. `::ffff:1.2.3.4`
+fn parse_v6_segments(s: &str, offset: usize, may_end_with_v4: bool) -> Result<Vec<u16>, IpParseError> {
+    let mut segments = vec![];
+    if s.is_empty() {
+        return Ok(segments);
+    }
+    let parts: Vec<&str> = s.split(':').collect();
+    let mut pos = offset;
+    for (i, part) in parts.iter().enumerate() {
+        if may_end_with_v4 && i == parts.len() - 1 && part.contains('.') {
+            let [a, b, c, d] = parse_v4(part, pos)?;
+            segments.push(u16::from_be_bytes([a, b]));
+            segments.push(u16::from_be_bytes([c, d]));
+            continue;
+        }
+        if part.is_empty() {
+            return Err(IpParseError::EmptyPart(pos));
+        }
+        if let Some((j, c)) = part.char_indices().find(|(_, c)| !c.is_ascii_hexdigit()) {
+            return Err(IpParseError::UnexpectedChar(c, pos + j));
+        }
+        if part.len() > 4 {
+            return Err(IpParseError::SegmentTooLong(part.to_string()));
+        }
+        segments.push(u16::from_str_radix(part, 16).unwrap());
+        pos += part.len() + 1;
+    }
+    Ok(segments)
+}
+
+fn parse_v6(s: &str) -> Result<[u16; 8], IpParseError> {
+    let mut result = [0u16; 8];
+    match s.find("::") {
+        None => {
+            let segments = parse_v6_segments(s, 0, true)?;
+            if segments.len() != 8 {
+                return Err(IpParseError::V6SegmentCount(segments.len()));
+            }
+            result.copy_from_slice(&segments);
+        }
+        Some(at) => {
+            let (head, tail) = (&s[..at], &s[at + 2..]);
+            if tail.contains("::") {
+                return Err(IpParseError::MultipleDoubleColon);
+            }
+            let head = parse_v6_segments(head, 0, false)?;
+            let tail = parse_v6_segments(tail, at + 2, true)?;
+            // `::` stands for AT LEAST one zero segment
+            if head.len() + tail.len() > 7 {
+                return Err(IpParseError::V6SegmentCount(head.len() + tail.len()));
+            }
+            result[..head.len()].copy_from_slice(&head);
+            result[8 - tail.len()..].copy_from_slice(&tail);
+        }
+    }
+    Ok(result)
+}
+
+// Implementing `FromStr` is what makes `"127.0.0.1".parse::<IpAddr>()` work
+impl FromStr for IpAddr {
+    type Err = IpParseError;
+
+    fn from_str(s: &str) -> Result<IpAddr, IpParseError> {
+        if s.is_empty() {
+            return Err(IpParseError::Empty);
+        }
+        // a colon can only be in an IPv6 address
+        if s.contains(':') {
+            Ok(IpAddr::V6(parse_v6(s)?))
+        } else {
+            let [a, b, c, d] = parse_v4(s, 0)?;
+            Ok(IpAddr::V4(a, b, c, d))
+        }
+    }
+}
+
+// Printing follows RFC 5952, like the standard library:
+//  - segments in lowercase hex, without leading zeros
+//  - the LONGEST run of two or more zero segments becomes `::` (the first one, on ties)
+//  - IPv4-mapped addresses (::ffff:a.b.c.d) end in dotted notation
+impl fmt::Display for IpAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IpAddr::V4(a, b, c, d) => write!(f, "{}.{}.{}.{}", a, b, c, d),
+            IpAddr::V6([0, 0, 0, 0, 0, 0xffff, hi, lo]) => {
+                let [a, b] = hi.to_be_bytes();
+                let [c, d] = lo.to_be_bytes();
+                write!(f, "::ffff:{}.{}.{}.{}", a, b, c, d)
+            }
+            IpAddr::V6(segments) => {
+                // find the longest run of zeros: (start, length)
+                let mut best = (0, 0);
+                let mut i = 0;
+                while i < 8 {
+                    if segments[i] == 0 {
+                        let start = i;
+                        while i < 8 && segments[i] == 0 {
+                            i += 1;
+                        }
+                        if i - start > best.1 {
+                            best = (start, i - start);
+                        }
+                    } else {
+                        i += 1;
+                    }
+                }
+                let hex = |part: &[u16]| part.iter().map(|s| format!("{:x}", s)).collect::<Vec<_>>().join(":");
+                match best {
+                    (start, len) if len >= 2 => {
+                        write!(f, "{}::{}", hex(&segments[..start]), hex(&segments[start + len..]))
+                    }
+                    _ => write!(f, "{}", hex(segments)),
+                }
+            }
+        }
+    }
+}
+
+impl IpAddr {
+    pub fn kind(&self) -> IpAddrKind {
+        match self {
+            IpAddr::V4(..) => IpAddrKind::V4,
+            IpAddr::V6(_) => IpAddrKind::V6,
+        }
+    }
+
+    /// 127.0.0.0/8 and ::1
+    pub fn is_loopback(&self) -> bool {
+        match self {
+            IpAddr::V4(127, _, _, _) => true,
+            IpAddr::V6([0, 0, 0, 0, 0, 0, 0, 1]) => true,
+            _ => false,
+        }
+    }
+
+    /// 10.0.0.0/8, 172.16.0.0/12, 192.168.0.0/16 and the IPv6 unique local fc00::/7
+    pub fn is_private(&self) -> bool {
+        match *self {
+            IpAddr::V4(10, _, _, _) => true,
+            // a range pattern: 16, 17, ..., 31
+            IpAddr::V4(172, 16..=31, _, _) => true,
+            IpAddr::V4(192, 168, _, _) => true,
+            // a guard: patterns cannot look at bits, so we test them after the match
+            IpAddr::V6([first, ..]) if first & 0xfe00 == 0xfc00 => true,
+            _ => false,
+        }
+    }
+
+    /// 224.0.0.0/4 and ff00::/8
+    pub fn is_multicast(&self) -> bool {
+        match *self {
+            IpAddr::V4(224..=239, _, _, _) => true,
+            IpAddr::V6([first, ..]) => first >> 8 == 0xff,
+            _ => false,
+        }
+    }
+
+    /// 0.0.0.0 and ::
+    pub fn is_unspecified(&self) -> bool {
+        matches!(self, IpAddr::V4(0, 0, 0, 0) | IpAddr::V6([0, 0, 0, 0, 0, 0, 0, 0]))
+    }
+}
+
+// Conversions with the standard library type.
+// `From` in one direction gives us `Into` in the other for free:
+//      let ours: IpAddr = std_addr.into();
+impl From<std::net::IpAddr> for IpAddr {
+    fn from(addr: std::net::IpAddr) -> IpAddr {
+        match addr {
+            std::net::IpAddr::V4(v4) => {
+                let [a, b, c, d] = v4.octets();
+                IpAddr::V4(a, b, c, d)
+            }
+            std::net::IpAddr::V6(v6) => IpAddr::V6(v6.segments()),
+        }
+    }
+}
+
+impl From<IpAddr> for std::net::IpAddr {
+    fn from(addr: IpAddr) -> std::net::IpAddr {
+        match addr {
+            IpAddr::V4(a, b, c, d) => std::net::IpAddr::V4(std::net::Ipv4Addr::new(a, b, c, d)),
+            IpAddr::V6(s) => std::net::IpAddr::V6(std::net::Ipv6Addr::from(s)),
+        }
+    }
+}
+
+pub fn ipaddr_usage() {
+    for s in ["192.168.1.10", "127.0.0.1", "2001:db8:0:0:1:0:0:1", "ff02::1", "::ffff:10.0.0.1",
+              "256.0.0.1", "1.2.3", "01.2.3.4", "1::2::3", "12345::", "fe80::g"] {
+        match s.parse::<IpAddr>() {
+            Ok(ip) => println!("{:<22} -> {:<20} {:?} loopback: {}, private: {}, multicast: {}",
+                               s, ip, ip.kind(), ip.is_loopback(), ip.is_private(), ip.is_multicast()),
+            Err(e) => println!("{:<22} -> error: {}", s, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod ipaddr_tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn parses_and_prints_v4() {
+        let ip: IpAddr = "192.168.0.1".parse().unwrap();
+        assert_eq!(ip, IpAddr::V4(192, 168, 0, 1));
+        assert_eq!(ip.to_string(), "192.168.0.1");
+        assert_eq!(ip.kind(), IpAddrKind::V4);
+    }
+
+    #[test]
+    fn compresses_the_longest_zero_run() {
+        let cases = [
+            ("2001:db8:0:0:1:0:0:1", "2001:db8::1:0:0:1"),
+            ("2001:0db8:0000:0000:0000:0000:0002:0001", "2001:db8::2:1"),
+            ("0:0:0:0:0:0:0:1", "::1"),
+            ("::", "::"),
+            ("1:0:2:0:3:0:4:0", "1:0:2:0:3:0:4:0"),
+            ("fe80::", "fe80::"),
+            ("::ffff:1.2.3.4", "::ffff:1.2.3.4"),
+            ("1:2:3:4:5:6:7::", "1:2:3:4:5:6:7:0"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(input.parse::<IpAddr>().unwrap().to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn reports_detailed_errors() {
+        let cases = [
+            ("", IpParseError::Empty),
+            ("1.2.3", IpParseError::V4PartCount(3)),
+            ("1..2.3", IpParseError::EmptyPart(2)),
+            ("1.2.x.4", IpParseError::UnexpectedChar('x', 4)),
+            ("256.1.1.1", IpParseError::OctetOutOfRange(String::from("256"))),
+            ("99999999999.1.1.1", IpParseError::OctetOutOfRange(String::from("99999999999"))),
+            ("01.1.1.1", IpParseError::LeadingZero(String::from("01"))),
+            ("1::2::3", IpParseError::MultipleDoubleColon),
+            ("12345::", IpParseError::SegmentTooLong(String::from("12345"))),
+            ("fe80::g", IpParseError::UnexpectedChar('g', 6)),
+            ("1:2:3:4:5:6:7", IpParseError::V6SegmentCount(7)),
+            ("1:2:3:4::5:6:7:8", IpParseError::V6SegmentCount(8)),
+            ("1:2:::3", IpParseError::EmptyPart(5)),
+            ("::1.2.3.256", IpParseError::OctetOutOfRange(String::from("256"))),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(input.parse::<IpAddr>(), Err(expected), "{}", input);
+        }
+    }
+
+    #[test]
+    fn classifies_addresses() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        assert!(ip("127.1.2.3").is_loopback());
+        assert!(ip("::1").is_loopback());
+        assert!(!ip("::2").is_loopback());
+        assert!(ip("10.0.0.1").is_private());
+        assert!(ip("172.31.255.255").is_private());
+        assert!(!ip("172.32.0.0").is_private());
+        assert!(ip("192.168.5.5").is_private());
+        assert!(ip("fd12::1").is_private());
+        assert!(!ip("fe80::1").is_private());
+        assert!(ip("224.0.0.1").is_multicast());
+        assert!(!ip("240.0.0.1").is_multicast());
+        assert!(ip("ff02::1").is_multicast());
+        assert!(ip("0.0.0.0").is_unspecified());
+        assert!(ip("::").is_unspecified());
+    }
+
+    // the standard library is our oracle: random addresses must parse, print
+    // and classify exactly like `std::net::IpAddr`
+    #[test]
+    fn agrees_with_std_on_random_addresses() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..2000 {
+            let std_ip: std::net::IpAddr = if rng.gen() {
+                std::net::Ipv4Addr::from(rng.gen::<[u8; 4]>()).into()
+            } else {
+                // many zero segments, to exercise the `::` compression
+                let segments: [u16; 8] = std::array::from_fn(|_| if rng.gen_bool(0.5) { 0 } else { rng.gen() });
+                std::net::Ipv6Addr::from(segments).into()
+            };
+            let ours = IpAddr::from(std_ip);
+            assert_eq!(ours.to_string(), std_ip.to_string());
+            assert_eq!(std_ip.to_string().parse::<IpAddr>(), Ok(ours));
+            assert_eq!(std::net::IpAddr::from(ours), std_ip);
+            assert_eq!(ours.is_loopback(), std_ip.is_loopback());
+            assert_eq!(ours.is_multicast(), std_ip.is_multicast());
+            assert_eq!(ours.is_unspecified(), std_ip.is_unspecified());
+            if let std::net::IpAddr::V4(v4) = std_ip {
+                assert_eq!(ours.is_private(), v4.is_private());
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_what_std_rejects() {
+        for s in ["1.2.3.4.5", "1.2.3.-4", " 1.2.3.4", "1:2:3:4:5:6:7:8:9", ":1:2", "1:2:", "::1.2.3", "::1.2.3.4:5", "1.2.3.4::"] {
+            assert!(s.parse::<std::net::IpAddr>().is_err(), "std accepts {}", s);
+            assert!(s.parse::<IpAddr>().is_err(), "we accept {}", s);
+        }
+    }
+}
@@ -0,0 +1,367 @@
+/// This module continues `c03_enums::errors`.
+/// There, errors are `String`s or are `unwrap()`ped; that is fine for a first program,
+/// but the caller cannot tell the errors apart, and the CAUSE of an error is lost.
+/// Here we see how larger Rust programs organise errors:
+///     - one error enum per subsystem, implementing `std::error::Error`
+///       (we already have `FileError` in `c03_files` and `CidrParseError` in `c03_cidr`)
+///     - `source()`, which links an error to the one that caused it, forming a chain
+///     - `context`, to add "what we were doing" on top of an error
+///     - `Report`, which prints the whole chain
+///     - when to panic and when to return a Result
+/// The `anyhow` and `thiserror` crates do all of this for real programs:
+/// here we write it by hand to see that there is no magic.
+/// See
+///         https://doc.rust-lang.org/std/error/trait.Error.html
+///         https://doc.rust-lang.org/book/ch09-03-to-panic-or-not-to-panic.html
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::num::ParseIntError;
+use std::path::{Path, PathBuf};
+
+use crate::classes::c03_cidr::Cidr;
+use crate::classes::c03_files::FileError;
+
+// the usual shorthand for "any error": a trait object.
+// `Send + Sync` so it can cross threads, `'static` so it can be returned by `source()`
+pub type BoxError = Box<dyn Error + Send + Sync + 'static>;
+
+/* ===== A subsystem error ==
+   ========================== */
+// Our example subsystem reads a configuration file like
+//      # comment
+//      network = 10.0.0.0/8
+//      port = 8080
+// It can fail reading the file, on a malformed line, on a missing key, or on a bad value.
+#[derive(Debug)]
+pub struct Config {
+    pub network: Cidr,
+    pub port: u16,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read { path: PathBuf, source: FileError },
+    Syntax { line: usize, text: String },
+    Missing(&'static str),
+    // the value failed to parse: the parse error is kept as the source, whatever its type
+    Value { line: usize, key: String, source: BoxError },
+}
+
+// Display describes THIS error only, not its causes: those are reached through `source`.
+// If each level also printed its cause, a report would print the same message many times.
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Read { path, .. } => write!(f, "could not read {}", path.display()),
+            ConfigError::Syntax { line, text } => write!(f, "line {}: expected `key = value`, found {:?}", line, text),
+            ConfigError::Missing(key) => write!(f, "missing key `{}`", key),
+            ConfigError::Value { line, key, .. } => write!(f, "line {}: invalid value for `{}`", line, key),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Read { source, .. } => Some(source),
+            // `&**source`: from &Box<dyn Error + Send + Sync> to &dyn Error
+            ConfigError::Value { source, .. } => Some(&**source),
+            ConfigError::Syntax { .. } | ConfigError::Missing(_) => None,
+        }
+    }
+}
+
+pub fn parse_config(text: &str) -> Result<Config, ConfigError> {
+    let mut values: HashMap<&str, (usize, &str)> = HashMap::new();
+    for (i, raw) in text.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once('=') {
+            Some((key, value)) => {
+                values.insert(key.trim(), (i + 1, value.trim()));
+            }
+            None => return Err(ConfigError::Syntax { line: i + 1, text: raw.to_string() }),
+        }
+    }
+    // a generic helper: works for every type that can be parsed, as long as
+    // its error can be boxed
+    fn get<T>(values: &HashMap<&str, (usize, &str)>, key: &'static str) -> Result<T, ConfigError>
+    where
+        T: std::str::FromStr,
+        T::Err: Error + Send + Sync + 'static,
+    {
+        let (line, value) = values.get(key).ok_or(ConfigError::Missing(key))?;
+        value.parse().map_err(|e: T::Err| ConfigError::Value { line: *line, key: key.to_string(), source: Box::new(e) })
+    }
+    Ok(Config { network: get(&values, "network")?, port: get(&values, "port")? })
+}
+
+pub fn load_config(path: &Path) -> Result<Config, ConfigError> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| ConfigError::Read { path: path.to_path_buf(), source: FileError::from(e) })?;
+    parse_config(&text)
+}
+
+/* ======== Context =========
+   ========================== */
+// A subsystem knows WHAT failed, but not WHY we were doing it:
+// `load_config` does not know it was called "while starting the server".
+// `context` wraps any error in a new one, which carries a message and the old error as source.
+#[derive(Debug)]
+pub struct ContextError {
+    context: String,
+    source: BoxError,
+}
+
+impl ContextError {
+    pub fn context(&self) -> &str {
+        &self.context
+    }
+}
+
+impl fmt::Display for ContextError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.context)
+    }
+}
+
+impl Error for ContextError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+/// Adds a context message to the error of a Result, or turns a None into an error.
+/// A trait, so that we can add methods to `Result` and `Option`, which are not ours
+/// (an "extension trait")
+pub trait Context<T> {
+    fn context<C: Into<String>>(self, context: C) -> Result<T, ContextError>;
+    // computes the message only on error: useful when it is built with `format!`
+    fn with_context<C: Into<String>, F: FnOnce() -> C>(self, f: F) -> Result<T, ContextError>;
+}
+
+impl<T, E: Error + Send + Sync + 'static> Context<T> for Result<T, E> {
+    fn context<C: Into<String>>(self, context: C) -> Result<T, ContextError> {
+        self.map_err(|e| ContextError { context: context.into(), source: Box::new(e) })
+    }
+    fn with_context<C: Into<String>, F: FnOnce() -> C>(self, f: F) -> Result<T, ContextError> {
+        self.map_err(|e| ContextError { context: f().into(), source: Box::new(e) })
+    }
+}
+
+// a None has no error to wrap: we use an error that just says "none"
+#[derive(Debug)]
+pub struct NoneError;
+
+impl fmt::Display for NoneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "value was None")
+    }
+}
+
+impl Error for NoneError {}
+
+impl<T> Context<T> for Option<T> {
+    fn context<C: Into<String>>(self, context: C) -> Result<T, ContextError> {
+        self.ok_or(NoneError).context(context)
+    }
+    fn with_context<C: Into<String>, F: FnOnce() -> C>(self, f: F) -> Result<T, ContextError> {
+        self.ok_or(NoneError).with_context(f)
+    }
+}
+
+/* ======== Report ==========
+   ========================== */
+/// Iterates over an error and all its causes, following `source()`
+pub fn chain<'a>(e: &'a (dyn Error + 'static)) -> impl Iterator<Item = &'a (dyn Error + 'static)> {
+    // `successors` keeps calling the closure on the last item until it returns None.
+    // The closure gets a `&&'a dyn Error`: without the annotation, the compiler ties the
+    // returned source to the outer, short-lived reference instead of to `'a`
+    std::iter::successors(Some(e), |e: &&'a (dyn Error + 'static)| (*e).source())
+}
+
+/// Prints an error with its causal chain:
+///     Error: starting the server
+///
+///     Caused by:
+///         0: line 2: invalid value for `network`
+///         1: host bits set ...
+pub struct Report<'a>(pub &'a (dyn Error + 'static));
+
+impl fmt::Display for Report<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Error: {}", self.0)?;
+        let mut causes = chain(self.0).skip(1).peekable();
+        if causes.peek().is_some() {
+            write!(f, "\n\nCaused by:")?;
+            for (i, cause) in causes.enumerate() {
+                write!(f, "\n    {}: {}", i, cause)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/* === panic! or Result? ====
+   ========================== */
+// The rule from the book: return a Result when failure is EXPECTED, e.g. it depends
+// on input, files, network; panic when failure means a BUG in the program,
+// i.e., a broken promise between the caller and the callee.
+// Each function below is an example, and each has a test showing the behaviour.
+
+// 1. user input can be anything: Result, and the caller decides what to do
+pub fn parse_port(input: &str) -> Result<u16, ParseIntError> {
+    input.trim().parse()
+}
+
+// 2. the caller PROMISES `whole > 0` (it is in the docs): breaking the promise is a bug, so panic.
+//    Returning a Result would force every caller to handle an error that never happens.
+/// Percentage of `part` over `whole`.
+/// # Panics
+/// If `whole` is 0.
+pub fn percent(part: u32, whole: u32) -> f64 {
+    assert!(whole > 0, "percent: whole must be positive");
+    part as f64 * 100.0 / whole as f64
+}
+
+// 3. we KNOW it cannot fail, but the compiler does not: `expect` with the reason.
+//    If it ever panics, the message tells us which assumption was wrong.
+pub fn localhost() -> Cidr {
+    "127.0.0.0/8".parse().expect("hardcoded CIDR is valid")
+}
+
+// 4. absence is normal, not an error: Option, not Result and not panic
+pub fn find_key<'a>(config_text: &'a str, key: &str) -> Option<&'a str> {
+    config_text.lines().filter_map(|l| l.split_once('=')).find(|(k, _)| k.trim() == key).map(|(_, v)| v.trim())
+}
+
+// 5. tests, examples and prototypes: `unwrap` is fine, a panic IS the right report there
+
+// Putting it together: the top of a program, where errors are finally shown to the user
+fn start_server(path: &Path) -> Result<Config, ContextError> {
+    let config = load_config(path).context("loading the configuration")?;
+    Ok(config)
+}
+
+pub fn errorsexample() {
+    let path = std::env::temp_dir().join("ap-class-server.conf");
+    let configs = [
+        "network = 10.0.0.0/8\nport = 8080\n",
+        "network = 10.0.0.1/8\nport = 8080\n",
+        "network = 10.0.0.0/8\nport = 80800\n",
+        "network = 10.0.0.0/8\n",
+        "network: 10.0.0.0/8\n",
+    ];
+    for text in configs {
+        std::fs::write(&path, text).unwrap();
+        match start_server(&path).context("starting the server") {
+            Ok(config) => println!("started with {:?}\n", config),
+            Err(e) => println!("{}\n", Report(&e)),
+        }
+    }
+    std::fs::remove_file(&path).unwrap();
+    if let Err(e) = start_server(Path::new("/does/not/exist.conf")) {
+        println!("{}", Report(&e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(e: &(dyn Error + 'static)) -> Vec<String> {
+        chain(e).map(|e| e.to_string()).collect()
+    }
+
+    #[test]
+    fn valid_config() {
+        let config = parse_config("# server\nnetwork = 10.0.0.0/8\n\nport = 8080\n").unwrap();
+        assert_eq!(config.port, 8080);
+        assert_eq!(config.network.to_string(), "10.0.0.0/8");
+    }
+
+    #[test]
+    fn each_config_error_is_distinct() {
+        assert!(matches!(parse_config("network 10.0.0.0/8"), Err(ConfigError::Syntax { line: 1, .. })));
+        assert!(matches!(parse_config("network = 10.0.0.0/8"), Err(ConfigError::Missing("port"))));
+        assert!(matches!(parse_config("port = x\nnetwork = 10.0.0.0/8"), Err(ConfigError::Value { line: 1, .. })));
+        let missing = load_config(Path::new("/does/not/exist.conf")).unwrap_err();
+        assert!(matches!(missing, ConfigError::Read { source: FileError::NotFound, .. }));
+    }
+
+    #[test]
+    fn source_chain_goes_down_to_the_root_cause() {
+        let e = parse_config("network = 300.0.0.0/8\nport = 1").unwrap_err().to_string();
+        assert_eq!(e, "line 1: invalid value for `network`");
+        let e = Err::<(), _>(parse_config("network = 300.0.0.0/8\nport = 1").unwrap_err())
+            .context("starting")
+            .unwrap_err();
+        let chain = messages(&e);
+        assert_eq!(chain.len(), 4, "{:?}", chain);
+        assert_eq!(chain[0], "starting");
+        assert_eq!(chain[1], "line 1: invalid value for `network`");
+        // the root cause is the IpParseError inside the CidrParseError
+        assert!(e.source().unwrap().source().unwrap().source().unwrap().source().is_none());
+    }
+
+    #[test]
+    fn contexts_nest() {
+        let r: Result<u16, ContextError> = parse_port("http").context("reading port").context("starting");
+        let e = r.unwrap_err();
+        assert_eq!(e.context(), "starting");
+        assert_eq!(messages(&e), vec!["starting", "reading port", "invalid digit found in string"]);
+    }
+
+    #[test]
+    fn with_context_is_lazy() {
+        let mut calls = 0;
+        let ok: Result<u16, _> = parse_port("80").with_context(|| {
+            calls += 1;
+            "never built"
+        });
+        assert_eq!(ok.unwrap(), 80);
+        assert_eq!(calls, 0);
+    }
+
+    #[test]
+    fn option_context() {
+        let e = find_key("a = 1", "b").context("looking for b").unwrap_err();
+        assert_eq!(messages(&e), vec!["looking for b", "value was None"]);
+        assert_eq!(find_key("a = 1", "a").context("looking for a").unwrap(), "1");
+    }
+
+    #[test]
+    fn report_format() {
+        let e = parse_port("-1").context("reading port").unwrap_err();
+        assert_eq!(Report(&e).to_string(), "Error: reading port\n\nCaused by:\n    0: invalid digit found in string");
+        // no chain, no "Caused by"
+        assert_eq!(Report(&NoneError).to_string(), "Error: value was None");
+    }
+
+    // the decision guide, as tests
+    #[test]
+    fn guide_input_errors_are_results() {
+        assert_eq!(parse_port(" 8080 "), Ok(8080));
+        assert!(parse_port("80800").is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "whole must be positive")]
+    fn guide_broken_promises_panic() {
+        percent(1, 0);
+    }
+
+    #[test]
+    fn guide_known_good_values_use_expect() {
+        assert_eq!(localhost().to_string(), "127.0.0.0/8");
+    }
+
+    #[test]
+    fn guide_absence_is_an_option() {
+        assert_eq!(find_key("port = 1", "network"), None);
+    }
+}
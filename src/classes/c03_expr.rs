@@ -0,0 +1,664 @@
+/// This module puts together the enum and match lessons of `c03_enums` in one small project:
+/// a calculator for arithmetic expressions with variables, like
+///         let x = 3 in x * (x + 1) - y
+/// An expression is an algebraic data type (see `primers/FP.md`): a tree whose nodes are the
+/// variants of `Expr`, and every function on it is a recursive `match`, one arm per variant.
+/// The pipeline is the one of every interpreter and compiler:
+///     text --tokenize--> tokens --parse--> Expr --simplify--> Expr --eval--> i64
+///                                            \--Display--> text
+/// See
+///         https://doc.rust-lang.org/book/ch18-03-pattern-syntax.html
+///         https://en.wikipedia.org/wiki/Recursive_descent_parser
+
+use std::collections::HashMap;
+use std::fmt;
+
+/* ======== The ADT =========
+   ========================== */
+// QUIZ: why `Box<Expr>` and not `Expr`?
+
+
+
+
+
+//
+// A recursive type must have a finite size: `Add(Expr, Expr)` would contain itself.
+// DNC: error[E0072]: recursive type `Expr` has infinite size
+// A Box is a pointer, of known size. See `recursivetypes` in `c11_heap`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Num(i64),
+    Var(String),
+    Add(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Neg(Box<Expr>),
+    // let name = value in body
+    Let(String, Box<Expr>, Box<Expr>),
+}
+
+// short constructors, so that trees can be written by hand without `Box::new` everywhere
+pub fn num(n: i64) -> Expr {
+    Expr::Num(n)
+}
+pub fn var(name: &str) -> Expr {
+    Expr::Var(name.to_string())
+}
+pub fn add(l: Expr, r: Expr) -> Expr {
+    Expr::Add(Box::new(l), Box::new(r))
+}
+pub fn mul(l: Expr, r: Expr) -> Expr {
+    Expr::Mul(Box::new(l), Box::new(r))
+}
+pub fn neg(e: Expr) -> Expr {
+    Expr::Neg(Box::new(e))
+}
+pub fn let_in(name: &str, value: Expr, body: Expr) -> Expr {
+    Expr::Let(name.to_string(), Box::new(value), Box::new(body))
+}
+
+/* ======== Tokenizer =======
+   ========================== */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Num(i64),
+    Ident(String),
+    Plus,
+    Minus,
+    Star,
+    LParen,
+    RParen,
+    Equals,
+    Let,
+    In,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    UnexpectedChar(char, usize),
+    NumberTooLarge(String),
+    // which token, and its index in the token list
+    UnexpectedToken(Token, usize),
+    UnexpectedEnd,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::UnexpectedChar(c, at) => write!(f, "unexpected character {:?} at {}", c, at),
+            ParseError::NumberTooLarge(s) => write!(f, "number {} does not fit in an i64", s),
+            ParseError::UnexpectedToken(t, at) => write!(f, "unexpected {:?} (token {})", t, at),
+            ParseError::UnexpectedEnd => write!(f, "unexpected end of input"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+pub fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    // `peekable` lets us look at the next char without consuming it,
+    // which we need to know where a number or a name ends
+    let mut chars = input.char_indices().peekable();
+    while let Some(&(i, c)) = chars.peek() {
+        // a match on a char: literal patterns, `|`, and guards
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '+' | '-' | '*' | '(' | ')' | '=' => {
+                chars.next();
+                tokens.push(match c {
+                    '+' => Token::Plus,
+                    '-' => Token::Minus,
+                    '*' => Token::Star,
+                    '(' => Token::LParen,
+                    ')' => Token::RParen,
+                    _ => Token::Equals,
+                });
+            }
+            '0'..='9' => {
+                let mut digits = String::new();
+                while let Some(&(_, d)) = chars.peek() {
+                    if !d.is_ascii_digit() {
+                        break;
+                    }
+                    digits.push(d);
+                    chars.next();
+                }
+                let n = digits.parse().map_err(|_| ParseError::NumberTooLarge(digits.clone()))?;
+                tokens.push(Token::Num(n));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut name = String::new();
+                while let Some(&(_, d)) = chars.peek() {
+                    if !(d.is_alphanumeric() || d == '_') {
+                        break;
+                    }
+                    name.push(d);
+                    chars.next();
+                }
+                // keywords are names with a special meaning: a match on a &str
+                tokens.push(match name.as_str() {
+                    "let" => Token::Let,
+                    "in" => Token::In,
+                    _ => Token::Ident(name),
+                });
+            }
+            other => return Err(ParseError::UnexpectedChar(other, i)),
+        }
+    }
+    Ok(tokens)
+}
+
+/* ========= Parser =========
+   ========================== */
+// Recursive descent: one function per rule of the grammar, lowest precedence first
+//      expr    := 'let' IDENT '=' expr 'in' expr | sum
+//      sum     := product (('+' | '-') product)*
+//      product := unary ('*' unary)*
+//      unary   := '-' unary | atom
+//      atom    := NUM | IDENT | '(' expr ')'
+// `a - b` has no variant of its own: it is parsed as `a + (-b)`.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Token, ParseError> {
+        let t = self.tokens.get(self.pos).cloned().ok_or(ParseError::UnexpectedEnd)?;
+        self.pos += 1;
+        Ok(t)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ParseError> {
+        let at = self.pos;
+        match self.next()? {
+            t if t == expected => Ok(()),
+            t => Err(ParseError::UnexpectedToken(t, at)),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        if self.peek() == Some(&Token::Let) {
+            self.next()?;
+            let at = self.pos;
+            let name = match self.next()? {
+                Token::Ident(name) => name,
+                t => return Err(ParseError::UnexpectedToken(t, at)),
+            };
+            self.expect(Token::Equals)?;
+            let value = self.expr()?;
+            self.expect(Token::In)?;
+            let body = self.expr()?;
+            return Ok(Expr::Let(name, Box::new(value), Box::new(body)));
+        }
+        self.sum()
+    }
+
+    fn sum(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.product()?;
+        // a loop, not recursion, so that `1 + 2 + 3` is `(1 + 2) + 3`: left associative
+        loop {
+            match self.peek() {
+                Some(Token::Plus) => {
+                    self.next()?;
+                    left = add(left, self.product()?);
+                }
+                Some(Token::Minus) => {
+                    self.next()?;
+                    left = add(left, neg(self.product()?));
+                }
+                _ => return Ok(left),
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.unary()?;
+        while self.peek() == Some(&Token::Star) {
+            self.next()?;
+            left = mul(left, self.unary()?);
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.peek() == Some(&Token::Minus) {
+            self.next()?;
+            return Ok(neg(self.unary()?));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr, ParseError> {
+        let at = self.pos;
+        match self.next()? {
+            Token::Num(n) => Ok(Expr::Num(n)),
+            Token::Ident(name) => Ok(Expr::Var(name)),
+            Token::LParen => {
+                let e = self.expr()?;
+                self.expect(Token::RParen)?;
+                Ok(e)
+            }
+            t => Err(ParseError::UnexpectedToken(t, at)),
+        }
+    }
+}
+
+pub fn parse(input: &str) -> Result<Expr, ParseError> {
+    let mut parser = Parser { tokens: tokenize(input)?, pos: 0 };
+    let e = parser.expr()?;
+    // everything must be consumed: `1 2` is not an expression
+    match parser.peek() {
+        None => Ok(e),
+        Some(t) => Err(ParseError::UnexpectedToken(t.clone(), parser.pos)),
+    }
+}
+
+/* ======== Evaluator =======
+   ========================== */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvalError {
+    UnboundVariable(String),
+    Overflow,
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvalError::UnboundVariable(name) => write!(f, "variable `{}` is not defined", name),
+            EvalError::Overflow => write!(f, "arithmetic overflow"),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+impl Expr {
+    /// Evaluates the expression; free variables are looked up in `env`
+    pub fn eval(&self, env: &HashMap<String, i64>) -> Result<i64, EvalError> {
+        self.eval_in(env, &mut Vec::new())
+    }
+
+    // `scopes` holds the variables bound by the enclosing `let`s, innermost last:
+    // searching from the end gives shadowing, as in `let x = 1 in let x = 2 in x`
+    fn eval_in<'a>(&'a self, env: &HashMap<String, i64>, scopes: &mut Vec<(&'a str, i64)>) -> Result<i64, EvalError> {
+        match self {
+            Expr::Num(n) => Ok(*n),
+            Expr::Var(name) => scopes
+                .iter()
+                .rev()
+                .find(|(bound, _)| bound == name)
+                .map(|(_, v)| *v)
+                .or_else(|| env.get(name).copied())
+                .ok_or_else(|| EvalError::UnboundVariable(name.clone())),
+            // `checked_*` return None on overflow, instead of panicking in debug and wrapping in release
+            Expr::Add(l, r) => l.eval_in(env, scopes)?.checked_add(r.eval_in(env, scopes)?).ok_or(EvalError::Overflow),
+            Expr::Mul(l, r) => l.eval_in(env, scopes)?.checked_mul(r.eval_in(env, scopes)?).ok_or(EvalError::Overflow),
+            Expr::Neg(e) => e.eval_in(env, scopes)?.checked_neg().ok_or(EvalError::Overflow),
+            Expr::Let(name, value, body) => {
+                let v = value.eval_in(env, scopes)?;
+                scopes.push((name, v));
+                let result = body.eval_in(env, scopes);
+                // popped even on error, so that `scopes` is always left as we found it
+                scopes.pop();
+                result
+            }
+        }
+    }
+
+    /// Is `name` used in the expression, outside a `let` that rebinds it?
+    pub fn has_free(&self, name: &str) -> bool {
+        match self {
+            Expr::Num(_) => false,
+            Expr::Var(v) => v == name,
+            Expr::Add(l, r) | Expr::Mul(l, r) => l.has_free(name) || r.has_free(name),
+            Expr::Neg(e) => e.has_free(name),
+            // the value is outside the scope of the binding, the body is inside
+            Expr::Let(bound, value, body) => value.has_free(name) || (bound != name && body.has_free(name)),
+        }
+    }
+}
+
+/* ======== Simplifier =======
+   =========================== */
+// Algebraic rules, written as patterns: this is where matching shines.
+// Note the nested patterns (`Neg(Neg(e))` looks two levels deep), the guards (`if a == b`),
+// and the or-patterns (`(Num(0), e) | (e, Num(0))`).
+// Patterns cannot look through a Box, so we match on `&**l`: `&Box<Expr>` -> `Expr` -> `&Expr`.
+impl Expr {
+    pub fn simplify(&self) -> Expr {
+        // bottom-up: first the children, then this node
+        let e = match self {
+            Expr::Num(_) | Expr::Var(_) => self.clone(),
+            Expr::Add(l, r) => add(l.simplify(), r.simplify()),
+            Expr::Mul(l, r) => mul(l.simplify(), r.simplify()),
+            Expr::Neg(e) => neg(e.simplify()),
+            Expr::Let(name, value, body) => let_in(name, value.simplify(), body.simplify()),
+        };
+        match e.rewrite() {
+            // a rule applied: the result may match another rule
+            Some(rewritten) => rewritten.simplify(),
+            None => e,
+        }
+    }
+
+    // one step; None if no rule applies
+    fn rewrite(&self) -> Option<Expr> {
+        use Expr::*;
+        match self {
+            // constant folding, unless it overflows: then we keep the expression, and eval reports it
+            Add(l, r) => match (&**l, &**r) {
+                (Num(a), Num(b)) => a.checked_add(*b).map(Num),
+                (Num(0), e) | (e, Num(0)) => Some(e.clone()),
+                // x + -x
+                // NOTE: like `0 * y` below, this also removes errors: `y - y` is 0 even if `y` is unbound,
+                // and `(9223372036854775807 + x) - (9223372036854775807 + x)` is 0 even though it overflows
+                (a, Neg(b)) | (Neg(b), a) if a == &**b => Some(Num(0)),
+                // x + x
+                // (this one keeps errors: `2 * x` fails exactly when `x + x` does)
+                (a, b) if a == b => Some(mul(num(2), a.clone())),
+                _ => None,
+            },
+            Mul(l, r) => match (&**l, &**r) {
+                (Num(a), Num(b)) => a.checked_mul(*b).map(Num),
+                // NOTE: this also removes errors, `0 * y` is 0 even if `y` is unbound
+                (Num(0), _) | (_, Num(0)) => Some(num(0)),
+                (Num(1), e) | (e, Num(1)) => Some(e.clone()),
+                (Num(-1), e) | (e, Num(-1)) => Some(neg(e.clone())),
+                // constants to the left, so that `x * 2 + x * 3` and `2 * x + 3 * x` look the same
+                (e, Num(n)) => Some(mul(num(*n), e.clone())),
+                _ => None,
+            },
+            Neg(e) => match &**e {
+                Num(n) => n.checked_neg().map(Num),
+                Neg(inner) => Some((**inner).clone()),
+                _ => None,
+            },
+            Let(name, value, body) => match (&**value, &**body) {
+                // the binding is never used
+                // NOTE: like `0 * y` above, this also removes errors:
+                // `let x = y in 1` is 1 even if `y` is unbound, and `value` is never evaluated
+                (_, b) if !b.has_free(name) => Some(b.clone()),
+                // let x = e in x
+                (v, Var(x)) if x == name => Some(v.clone()),
+                // a constant can be substituted
+                (Num(n), b) => Some(b.substitute(name, *n)),
+                _ => None,
+            },
+            Num(_) | Var(_) => None,
+        }
+    }
+
+    // replaces the free occurrences of `name` with `value`
+    fn substitute(&self, name: &str, value: i64) -> Expr {
+        match self {
+            Expr::Var(v) if v == name => num(value),
+            Expr::Num(_) | Expr::Var(_) => self.clone(),
+            Expr::Add(l, r) => add(l.substitute(name, value), r.substitute(name, value)),
+            Expr::Mul(l, r) => mul(l.substitute(name, value), r.substitute(name, value)),
+            Expr::Neg(e) => neg(e.substitute(name, value)),
+            // shadowed: the inner `let` hides our variable in its body
+            Expr::Let(bound, v, body) if bound == name => let_in(bound, v.substitute(name, value), (**body).clone()),
+            Expr::Let(bound, v, body) => let_in(bound, v.substitute(name, value), body.substitute(name, value)),
+        }
+    }
+}
+
+/* ====== Pretty printer ======
+   ============================ */
+// Parentheses only where needed. Each node knows its precedence; a child is wrapped
+// in parentheses if its precedence is lower than what its position requires.
+// The output parses back to the same tree, with one exception: negative literals.
+// `Num(-4)` prints as `-4`, which the parser reads as `Neg(Num(4))`: a different tree, with the same value.
+// (`Num(i64::MIN)` does not parse back at all: without its sign, the number is too large for an i64)
+const PREC_LET: u8 = 0;
+const PREC_SUM: u8 = 1;
+const PREC_PRODUCT: u8 = 2;
+const PREC_UNARY: u8 = 3;
+
+impl Expr {
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Let(..) => PREC_LET,
+            Expr::Add(..) => PREC_SUM,
+            Expr::Mul(..) => PREC_PRODUCT,
+            // a negative literal prints with its minus sign, like a Neg
+            Expr::Neg(_) | Expr::Num(i64::MIN..=-1) => PREC_UNARY,
+            Expr::Num(_) | Expr::Var(_) => PREC_UNARY + 1,
+        }
+    }
+
+    fn fmt_at(&self, f: &mut fmt::Formatter, min: u8) -> fmt::Result {
+        if self.precedence() < min {
+            write!(f, "(")?;
+            self.fmt_at(f, PREC_LET)?;
+            return write!(f, ")");
+        }
+        match self {
+            Expr::Num(n) => write!(f, "{}", n),
+            Expr::Var(name) => write!(f, "{}", name),
+            // the right operand needs a HIGHER precedence: `a + (b + c)` keeps its parentheses
+            Expr::Add(l, r) => {
+                l.fmt_at(f, PREC_SUM)?;
+                match &**r {
+                    Expr::Neg(inner) => {
+                        write!(f, " - ")?;
+                        inner.fmt_at(f, PREC_PRODUCT)
+                    }
+                    _ => {
+                        write!(f, " + ")?;
+                        r.fmt_at(f, PREC_PRODUCT)
+                    }
+                }
+            }
+            Expr::Mul(l, r) => {
+                l.fmt_at(f, PREC_PRODUCT)?;
+                write!(f, " * ")?;
+                r.fmt_at(f, PREC_UNARY)
+            }
+            Expr::Neg(e) => {
+                write!(f, "-")?;
+                e.fmt_at(f, PREC_UNARY)
+            }
+            Expr::Let(name, value, body) => {
+                write!(f, "let {} = ", name)?;
+                value.fmt_at(f, PREC_LET)?;
+                write!(f, " in ")?;
+                body.fmt_at(f, PREC_LET)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_at(f, PREC_LET)
+    }
+}
+
+pub fn exprexample() {
+    let env: HashMap<String, i64> = [(String::from("y"), 10)].into_iter().collect();
+    let inputs = [
+        "1 + 2 * 3",
+        "(1 + 2) * 3",
+        "let x = 3 in x * (x + 1) - y",
+        "let x = 5 in let x = x * 2 in x",
+        "x * 0 + 1 * (y + 0) - -y",
+        "let z = 2 in y + y",
+        "9223372036854775807 + 1",
+        "1 + * 2",
+        "undefined * 2",
+    ];
+    for input in inputs {
+        match parse(input) {
+            Ok(e) => {
+                let simplified = e.simplify();
+                let value = match e.eval(&env) {
+                    Ok(v) => v.to_string(),
+                    Err(err) => format!("error: {}", err),
+                };
+                println!("{:<35} => {:<25} simplified: {:<20} value: {}", input, e.to_string(), simplified.to_string(), value);
+            }
+            Err(err) => println!("{:<35} => parse error: {}", input, err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn env() -> HashMap<String, i64> {
+        [("y".to_string(), 10), ("z".to_string(), -3)].into_iter().collect()
+    }
+
+    #[test]
+    fn tokens() {
+        assert_eq!(
+            tokenize("let x1 = 42 in -(x1*2)").unwrap(),
+            vec![
+                Token::Let,
+                Token::Ident("x1".into()),
+                Token::Equals,
+                Token::Num(42),
+                Token::In,
+                Token::Minus,
+                Token::LParen,
+                Token::Ident("x1".into()),
+                Token::Star,
+                Token::Num(2),
+                Token::RParen
+            ]
+        );
+        assert_eq!(tokenize("1 / 2"), Err(ParseError::UnexpectedChar('/', 2)));
+        assert!(matches!(tokenize("99999999999999999999"), Err(ParseError::NumberTooLarge(_))));
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        assert_eq!(parse("1 + 2 * 3").unwrap(), add(num(1), mul(num(2), num(3))));
+        assert_eq!(parse("1 - 2 - 3").unwrap(), add(add(num(1), neg(num(2))), neg(num(3))));
+        assert_eq!(parse("-x * 2").unwrap(), mul(neg(var("x")), num(2)));
+        assert_eq!(parse("let a = 1 in a + 1").unwrap(), let_in("a", num(1), add(var("a"), num(1))));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse("1 +"), Err(ParseError::UnexpectedEnd));
+        assert_eq!(parse("1 2"), Err(ParseError::UnexpectedToken(Token::Num(2), 1)));
+        assert_eq!(parse("(1"), Err(ParseError::UnexpectedEnd));
+        assert_eq!(parse("let 1 = 2 in 3"), Err(ParseError::UnexpectedToken(Token::Num(1), 1)));
+        assert_eq!(parse("let x = 2 x"), Err(ParseError::UnexpectedToken(Token::Ident("x".into()), 4)));
+    }
+
+    #[test]
+    fn evaluation() {
+        let eval = |s: &str| parse(s).unwrap().eval(&env());
+        assert_eq!(eval("let x = 3 in x * (x + 1) - y"), Ok(2));
+        assert_eq!(eval("let y = 1 in y"), Ok(1), "let shadows the environment");
+        assert_eq!(eval("let x = 1 in (let x = 2 in x) + x"), Ok(3), "the inner binding ends with its let");
+        assert_eq!(eval("z * z"), Ok(9));
+        assert_eq!(eval("w + 1"), Err(EvalError::UnboundVariable("w".into())));
+        assert_eq!(eval("9223372036854775807 + 1"), Err(EvalError::Overflow));
+        assert_eq!(eval("-(0 - 9223372036854775807 - 1)"), Err(EvalError::Overflow));
+    }
+
+    #[test]
+    fn simplification_rules() {
+        let s = |input: &str| parse(input).unwrap().simplify().to_string();
+        assert_eq!(s("1 + 2 * 3"), "7");
+        assert_eq!(s("x + 0"), "x");
+        assert_eq!(s("x * 1 + 0 * y"), "x");
+        assert_eq!(s("--x"), "x");
+        assert_eq!(s("x - x"), "0");
+        assert_eq!(s("x + x"), "2 * x");
+        assert_eq!(s("x * 3"), "3 * x");
+        assert_eq!(s("let a = 5 in y"), "y");
+        assert_eq!(s("let a = y in a"), "y");
+        assert_eq!(s("let a = 2 in a * x + a"), "2 * x + 2");
+        assert_eq!(s("let a = 2 in let a = x in a + a"), "let a = x in 2 * a");
+        assert_eq!(s("let a = y * x in a + a"), "let a = y * x in 2 * a");
+        // overflow is not folded away
+        assert_eq!(s("9223372036854775807 + 1"), "9223372036854775807 + 1");
+    }
+
+    #[test]
+    fn simplification_can_remove_errors() {
+        let both = |input: &str| {
+            let e = parse(input).unwrap();
+            (e.eval(&env()), e.simplify().eval(&env()))
+        };
+        // the rules that throw a subexpression away also throw its error away
+        assert_eq!(both("w - w"), (Err(EvalError::UnboundVariable("w".into())), Ok(0)));
+        let big = "(9223372036854775807 + y) - (9223372036854775807 + y)";
+        assert_eq!(both(big), (Err(EvalError::Overflow), Ok(0)));
+        assert_eq!(both("0 * w"), (Err(EvalError::UnboundVariable("w".into())), Ok(0)));
+        assert_eq!(both("let a = w in 1"), (Err(EvalError::UnboundVariable("w".into())), Ok(1)));
+        // the others keep it
+        let unbound = Err(EvalError::UnboundVariable("w".into()));
+        assert_eq!(both("w + w"), (unbound.clone(), unbound));
+        let big = "(9223372036854775807 + y) + (9223372036854775807 + y)";
+        assert_eq!(both(big), (Err(EvalError::Overflow), Err(EvalError::Overflow)));
+    }
+
+    #[test]
+    fn printing_uses_minimal_parentheses() {
+        let p = |input: &str| parse(input).unwrap().to_string();
+        assert_eq!(p("((1 + 2)) + 3"), "1 + 2 + 3");
+        assert_eq!(p("1 + (2 + 3)"), "1 + (2 + 3)");
+        assert_eq!(p("(1 + 2) * -(3)"), "(1 + 2) * -3");
+        assert_eq!(p("1 - (2 - 3)"), "1 - (2 - 3)");
+        assert_eq!(p("(let x = 1 in x) * 2"), "(let x = 1 in x) * 2");
+        assert_eq!(p("1 + (let x = 1 in x)"), "1 + (let x = 1 in x)");
+        assert_eq!(parse("1 + let x = 1 in x"), Err(ParseError::UnexpectedToken(Token::Let, 2)));
+        assert_eq!(num(-4).to_string(), "-4");
+        assert_eq!(mul(num(2), num(-4)).to_string(), "2 * -4");
+    }
+
+    // random trees: printing then parsing gives the same tree back,
+    // and simplifying never changes the value
+    fn random_expr(rng: &mut impl Rng, depth: u32) -> Expr {
+        let names = ["x", "y", "z"];
+        let leaf = depth == 0 || rng.gen_bool(0.3);
+        match if leaf { rng.gen_range(0..2) } else { rng.gen_range(2..6) } {
+            0 => num(rng.gen_range(0..5)),
+            1 => var(names[rng.gen_range(0..names.len())]),
+            2 => add(random_expr(rng, depth - 1), random_expr(rng, depth - 1)),
+            3 => mul(random_expr(rng, depth - 1), random_expr(rng, depth - 1)),
+            4 => neg(random_expr(rng, depth - 1)),
+            _ => let_in(names[rng.gen_range(0..2)], random_expr(rng, depth - 1), random_expr(rng, depth - 1)),
+        }
+    }
+
+    #[test]
+    fn print_parse_roundtrip() {
+        // `random_expr` makes no negative literals: with them, the tree would change
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let e = random_expr(&mut rng, 5);
+            assert_eq!(parse(&e.to_string()).as_ref(), Ok(&e), "{}", e);
+        }
+        // a negative literal comes back as a negation, with the same value
+        let e = mul(num(-4), var("x"));
+        assert_eq!(parse(&e.to_string()), Ok(mul(neg(num(4)), var("x"))));
+        let env: HashMap<String, i64> = [("x".to_string(), 5)].into_iter().collect();
+        assert_eq!(parse(&e.to_string()).unwrap().eval(&env), e.eval(&env));
+    }
+
+    #[test]
+    fn simplify_preserves_value() {
+        let mut rng = rand::thread_rng();
+        let env: HashMap<String, i64> = [("x".to_string(), 7), ("y".to_string(), -2), ("z".to_string(), 3)].into_iter().collect();
+        for _ in 0..500 {
+            let e = random_expr(&mut rng, 5);
+            assert_eq!(e.simplify().eval(&env), e.eval(&env), "{} simplified to {}", e, e.simplify());
+        }
+    }
+}
@@ -0,0 +1,287 @@
+/// This module is the finished version of the `readfilecontent` exercise at the end of `c03_enums`.
+/// That function has a few problems worth looking at:
+///     - it opens `foo.txt` with `File::open`, which is READ-ONLY, and then writes to it
+///     - it ignores the `Result`s of `write_all` and `read_to_string`
+///     - it turns every error into the same `"could not open"` string
+/// Here we fix them with:
+///     - `OpenOptions`, to say exactly how a file is opened
+///     - a typed error enum, so callers can tell the failures apart
+///     - `From<io::Error>`, so that `?` converts errors for us
+/// and we use it as the `analyze` subcommand of our executable:
+///         cargo run -- analyze foo.txt
+///         cargo run -- analyze foo.txt --append "some more text"
+/// See
+///         https://doc.rust-lang.org/std/fs/struct.OpenOptions.html
+///         https://doc.rust-lang.org/book/ch09-02-recoverable-errors-with-result.html#propagating-errors
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/* ======= The error =======
+   ========================= */
+// One variant per failure the caller may want to handle differently;
+// everything else goes in `Io`, keeping the original error inside.
+#[derive(Debug)]
+pub enum FileError {
+    NotFound,
+    PermissionDenied,
+    // the bytes read are not text: we say where the first bad byte is
+    InvalidUtf8 { valid_up_to: usize },
+    Io(io::Error),
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FileError::NotFound => write!(f, "file not found"),
+            FileError::PermissionDenied => write!(f, "permission denied"),
+            FileError::InvalidUtf8 { valid_up_to } => {
+                write!(f, "file is not valid UTF-8 (first invalid byte at offset {})", valid_up_to)
+            }
+            FileError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for FileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FileError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// this is what makes `?` work on `io::Result`s inside functions returning `Result<_, FileError>`:
+// `?` calls `From::from` on the error before returning it
+impl From<io::Error> for FileError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::NotFound => FileError::NotFound,
+            io::ErrorKind::PermissionDenied => FileError::PermissionDenied,
+            _ => FileError::Io(e),
+        }
+    }
+}
+
+impl From<std::string::FromUtf8Error> for FileError {
+    fn from(e: std::string::FromUtf8Error) -> Self {
+        FileError::InvalidUtf8 { valid_up_to: e.utf8_error().valid_up_to() }
+    }
+}
+
+/* ======= The report ======
+   ========================= */
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FileStats {
+    pub lines: usize,
+    pub words: usize,
+    pub bytes: usize,
+    // a BTreeMap, not a HashMap, so that the characters are printed in order
+    pub chars: BTreeMap<char, usize>,
+}
+
+impl FileStats {
+    pub fn from_text(text: &str) -> FileStats {
+        let mut chars = BTreeMap::new();
+        for c in text.chars() {
+            *chars.entry(c).or_insert(0) += 1;
+        }
+        FileStats {
+            lines: text.lines().count(),
+            words: text.split_whitespace().count(),
+            bytes: text.len(),
+            chars,
+        }
+    }
+
+    pub fn occurrences(&self, c: char) -> usize {
+        self.chars.get(&c).copied().unwrap_or(0)
+    }
+
+    // what `calculateS` in `c03_enums` computes, for any ASCII letter
+    pub fn occurrences_ignore_case(&self, c: char) -> usize {
+        self.chars.iter().filter(|(k, _)| k.eq_ignore_ascii_case(&c)).map(|(_, n)| n).sum()
+    }
+}
+
+impl fmt::Display for FileStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "lines: {}, words: {}, bytes: {}", self.lines, self.words, self.bytes)?;
+        for (c, n) in &self.chars {
+            // `{:?}` so that '\n' and ' ' are visible
+            writeln!(f, "  {:?}: {}", c, n)?;
+        }
+        Ok(())
+    }
+}
+
+/* ====== The functions =====
+   ========================== */
+// Each step returns a Result, and each `?` either unwraps it or returns the error early,
+// converted into a FileError. Compare with the `match` in `readfilecontent`.
+
+/// Reads the whole file as text
+pub fn analyze(path: &Path) -> Result<FileStats, FileError> {
+    let mut f = File::open(path)?;
+    read_stats(&mut f)
+}
+
+/// Appends `text` to the file, creating it if missing, then reads the whole file
+pub fn append_and_analyze(path: &Path, text: &str) -> Result<FileStats, FileError> {
+    // `File::open` is read-only and `File::create` truncates: neither is what we want.
+    // `append(true)` implies writing, and every write goes at the end of the file,
+    // `read(true)` lets us read it back through the same handle.
+    let mut f = OpenOptions::new().read(true).append(true).create(true).open(path)?;
+    f.write_all(text.as_bytes())?;
+    // after writing, the cursor is at the end: reading from there gives nothing
+    f.seek(SeekFrom::Start(0))?;
+    read_stats(&mut f)
+}
+
+fn read_stats(f: &mut File) -> Result<FileStats, FileError> {
+    // `read_to_string` would fail on bad UTF-8 too, but with an `InvalidData` io::Error
+    // that does not say where: reading bytes first gives us the position
+    let mut bytes = Vec::new();
+    f.read_to_end(&mut bytes)?;
+    let text = String::from_utf8(bytes)?;
+    Ok(FileStats::from_text(&text))
+}
+
+/// The `analyze` subcommand: `args` are the arguments after the subcommand name
+pub fn analyzecommand(args: &[String]) -> Result<FileStats, String> {
+    let result = match args {
+        [path] => analyze(Path::new(path)),
+        [path, flag, text] if flag == "--append" => append_and_analyze(Path::new(path), text),
+        _ => return Err(String::from("usage: analyze <file> [--append <text>]")),
+    };
+    // only here, at the very top, the typed error becomes a message for the user
+    result.map_err(|e| format!("{}: {}", args[0], e))
+}
+
+pub fn filesexample() {
+    let path = std::env::temp_dir().join("ap-class-foo.txt");
+    match append_and_analyze(&path, "adv prog sssss\n") {
+        Ok(stats) => {
+            print!("{}", stats);
+            println!("s or S: {}", stats.occurrences_ignore_case('s'));
+        }
+        Err(e) => println!("Error: {}", e),
+    }
+    // the errors can be told apart
+    match analyze(Path::new("this/does/not/exist.txt")) {
+        Err(FileError::NotFound) => println!("not there, as expected"),
+        other => println!("unexpected: {:?}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // a fresh directory per test: tests run in parallel and must not share files
+    fn tempdir() -> PathBuf {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "ap-class-files-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn counts_lines_words_bytes_chars() {
+        let dir = tempdir();
+        let path = dir.join("a.txt");
+        std::fs::write(&path, "adv prog\nsssSS è\n").unwrap();
+        let stats = analyze(&path).unwrap();
+        assert_eq!(stats.lines, 2);
+        assert_eq!(stats.words, 4);
+        // 'è' is two bytes in UTF-8
+        assert_eq!(stats.bytes, 18);
+        assert_eq!(stats.occurrences('s'), 3);
+        assert_eq!(stats.occurrences_ignore_case('s'), 5);
+        assert_eq!(stats.occurrences('è'), 1);
+        assert_eq!(stats.occurrences('\n'), 2);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn append_creates_then_appends() {
+        let dir = tempdir();
+        let path = dir.join("new.txt");
+        assert_eq!(append_and_analyze(&path, "one\n").unwrap().lines, 1);
+        let stats = append_and_analyze(&path, "two three\n").unwrap();
+        assert_eq!((stats.lines, stats.words), (2, 3));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\ntwo three\n");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_file_is_not_found() {
+        let dir = tempdir();
+        assert!(matches!(analyze(&dir.join("missing.txt")), Err(FileError::NotFound)));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_utf8_reports_the_offset() {
+        let dir = tempdir();
+        let path = dir.join("bin.dat");
+        std::fs::write(&path, b"ok\xff\xfe").unwrap();
+        assert!(matches!(analyze(&path), Err(FileError::InvalidUtf8 { valid_up_to: 2 })));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn other_failures_are_io() {
+        // a directory can be opened, but not read
+        let dir = tempdir();
+        let err = analyze(&dir).unwrap_err();
+        assert!(matches!(err, FileError::Io(_)), "{:?}", err);
+        assert!(std::error::Error::source(&err).is_some());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unreadable_file_is_permission_denied() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempdir();
+        let path = dir.join("secret.txt");
+        std::fs::write(&path, "hidden").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o000)).unwrap();
+        // root ignores file permissions: nothing to test there
+        if File::open(&path).is_err() {
+            assert!(matches!(analyze(&path), Err(FileError::PermissionDenied)));
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn io_error_kinds_are_mapped() {
+        let e = |kind| FileError::from(io::Error::from(kind));
+        assert!(matches!(e(io::ErrorKind::NotFound), FileError::NotFound));
+        assert!(matches!(e(io::ErrorKind::PermissionDenied), FileError::PermissionDenied));
+        assert!(matches!(e(io::ErrorKind::UnexpectedEof), FileError::Io(_)));
+    }
+
+    #[test]
+    fn command_arguments() {
+        let dir = tempdir();
+        let path = dir.join("cmd.txt").to_string_lossy().into_owned();
+        let args = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert!(analyzecommand(&args(&[])).is_err());
+        assert_eq!(analyzecommand(&args(&[&path])).unwrap_err(), format!("{}: file not found", path));
+        assert_eq!(analyzecommand(&args(&[&path, "--append", "a b"])).unwrap().words, 2);
+        assert_eq!(analyzecommand(&args(&[&path])).unwrap().bytes, 3);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,506 @@
+/// This module re-implements `Option` and `Result`, as seen in `c03_enums`.
+/// There we only looked at their definitions:
+///         enum Option<T> { None, Some(T) }
+///         enum Result<T, E> { Ok(T), Err(E) }
+/// Here we give our own copies the methods of the standard library ones,
+/// to show that there is no magic: every combinator is a small `match`.
+/// See
+///         https://doc.rust-lang.org/std/option/enum.Option.html
+///         https://doc.rust-lang.org/std/result/enum.Result.html
+///
+/// NOTE: in this file `Option` and `Result` are OUR enums, they shadow the ones of the prelude.
+/// When we need the standard ones, we write their full path `std::option::Option`.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Option<T> {
+    None,
+    Some(T),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Result<T, E> {
+    Ok(T),
+    Err(E),
+}
+
+// the prelude makes `Some`, `None`, `Ok` and `Err` usable without the enum name:
+// we do the same for our variants
+use self::Option::{None, Some};
+use self::Result::{Err, Ok};
+
+/* ======= Option ==========
+   ========================= */
+impl<T> Option<T> {
+    pub fn is_some(&self) -> bool {
+        matches!(self, Some(_))
+    }
+    pub fn is_none(&self) -> bool {
+        !self.is_some()
+    }
+
+    // `&Option<T>` to `Option<&T>`: look inside without moving the content out.
+    // Most methods take `self` by value, so `as_ref` is how we use them on a borrowed Option
+    pub fn as_ref(&self) -> Option<&T> {
+        match self {
+            Some(x) => Some(x),
+            None => None,
+        }
+    }
+    pub fn as_mut(&mut self) -> Option<&mut T> {
+        match self {
+            Some(x) => Some(x),
+            None => None,
+        }
+    }
+
+    pub fn unwrap(self) -> T {
+        match self {
+            Some(x) => x,
+            None => panic!("called `Option::unwrap()` on a `None` value"),
+        }
+    }
+    pub fn expect(self, msg: &str) -> T {
+        match self {
+            Some(x) => x,
+            None => panic!("{}", msg),
+        }
+    }
+    // the default is computed even when not needed ...
+    pub fn unwrap_or(self, default: T) -> T {
+        match self {
+            Some(x) => x,
+            None => default,
+        }
+    }
+    // ... here only when needed: the `_else` variants take a closure
+    pub fn unwrap_or_else<F: FnOnce() -> T>(self, f: F) -> T {
+        match self {
+            Some(x) => x,
+            None => f(),
+        }
+    }
+
+    // transform the content, if any
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Option<U> {
+        match self {
+            Some(x) => Some(f(x)),
+            None => None,
+        }
+    }
+    // like map, but `f` can fail too: no Option<Option<U>>, the two Nones are merged
+    pub fn and_then<U, F: FnOnce(T) -> Option<U>>(self, f: F) -> Option<U> {
+        match self {
+            Some(x) => f(x),
+            None => None,
+        }
+    }
+    pub fn or(self, other: Option<T>) -> Option<T> {
+        match self {
+            Some(x) => Some(x),
+            None => other,
+        }
+    }
+    pub fn or_else<F: FnOnce() -> Option<T>>(self, f: F) -> Option<T> {
+        match self {
+            Some(x) => Some(x),
+            None => f(),
+        }
+    }
+    pub fn filter<P: FnOnce(&T) -> bool>(self, predicate: P) -> Option<T> {
+        match self {
+            // a guard in the pattern
+            Some(x) if predicate(&x) => Some(x),
+            _ => None,
+        }
+    }
+
+    // from "maybe nothing" to "maybe an error": we must say WHICH error
+    pub fn ok_or<E>(self, err: E) -> Result<T, E> {
+        match self {
+            Some(x) => Ok(x),
+            None => Err(err),
+        }
+    }
+    pub fn ok_or_else<E, F: FnOnce() -> E>(self, f: F) -> Result<T, E> {
+        match self {
+            Some(x) => Ok(x),
+            None => Err(f()),
+        }
+    }
+
+    // both or nothing: matching on a tuple, as in `c03_enums::patternmatching`
+    pub fn zip<U>(self, other: Option<U>) -> Option<(T, U)> {
+        match (self, other) {
+            (Some(a), Some(b)) => Some((a, b)),
+            _ => None,
+        }
+    }
+
+    // moves the content out, leaving a None in its place (needs `&mut self`)
+    pub fn take(&mut self) -> Option<T> {
+        std::mem::replace(self, None)
+    }
+
+    // an Option is a collection of zero or one elements
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { inner: self.as_ref() }
+    }
+}
+
+// some methods only exist for some instantiations of T:
+// `flatten` only makes sense if the content is an Option itself
+impl<T> Option<Option<T>> {
+    pub fn flatten(self) -> Option<T> {
+        self.and_then(|inner| inner)
+    }
+}
+
+// Option<Result<..>> <-> Result<Option<..>>: swap the two layers
+impl<T, E> Option<Result<T, E>> {
+    pub fn transpose(self) -> Result<Option<T>, E> {
+        match self {
+            None => Ok(None),
+            Some(Ok(x)) => Ok(Some(x)),
+            Some(Err(e)) => Err(e),
+        }
+    }
+}
+
+/// The iterator of `Option::iter` and `Result::iter`
+pub struct Iter<'a, T> {
+    inner: Option<&'a T>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    // QUIZ: why `std::option::Option` here?
+
+
+
+
+
+    //
+    // the `Iterator` trait is defined by the standard library with ITS Option:
+    // we must return that one, ours is just another enum that happens to have the same name
+    fn next(&mut self) -> std::option::Option<&'a T> {
+        self.inner.take().into()
+    }
+}
+
+/* ======= Result ==========
+   ========================= */
+impl<T, E> Result<T, E> {
+    pub fn is_ok(&self) -> bool {
+        matches!(self, Ok(_))
+    }
+    pub fn is_err(&self) -> bool {
+        !self.is_ok()
+    }
+
+    // forget the error, or forget the value
+    pub fn ok(self) -> Option<T> {
+        match self {
+            Ok(x) => Some(x),
+            Err(_) => None,
+        }
+    }
+    pub fn err(self) -> Option<E> {
+        match self {
+            Ok(_) => None,
+            Err(e) => Some(e),
+        }
+    }
+
+    pub fn as_ref(&self) -> Result<&T, &E> {
+        match self {
+            Ok(x) => Ok(x),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Result<U, E> {
+        match self {
+            Ok(x) => Ok(f(x)),
+            Err(e) => Err(e),
+        }
+    }
+    // the same, on the other side: typically to convert one error type into another
+    pub fn map_err<G, F: FnOnce(E) -> G>(self, f: F) -> Result<T, G> {
+        match self {
+            Ok(x) => Ok(x),
+            Err(e) => Err(f(e)),
+        }
+    }
+    pub fn and_then<U, F: FnOnce(T) -> Result<U, E>>(self, f: F) -> Result<U, E> {
+        match self {
+            Ok(x) => f(x),
+            Err(e) => Err(e),
+        }
+    }
+    // recover from an error, possibly with a different error type
+    pub fn or_else<G, F: FnOnce(E) -> Result<T, G>>(self, f: F) -> Result<T, G> {
+        match self {
+            Ok(x) => Ok(x),
+            Err(e) => f(e),
+        }
+    }
+
+    pub fn unwrap_or(self, default: T) -> T {
+        match self {
+            Ok(x) => x,
+            Err(_) => default,
+        }
+    }
+    // unlike Option, the closure receives the error
+    pub fn unwrap_or_else<F: FnOnce(E) -> T>(self, f: F) -> T {
+        match self {
+            Ok(x) => x,
+            Err(e) => f(e),
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { inner: self.as_ref().ok() }
+    }
+}
+
+// `unwrap` must print the error, so it needs E: Debug
+impl<T, E: std::fmt::Debug> Result<T, E> {
+    pub fn unwrap(self) -> T {
+        match self {
+            Ok(x) => x,
+            Err(e) => panic!("called `Result::unwrap()` on an `Err` value: {:?}", e),
+        }
+    }
+    pub fn expect(self, msg: &str) -> T {
+        match self {
+            Ok(x) => x,
+            Err(e) => panic!("{}: {:?}", msg, e),
+        }
+    }
+}
+
+impl<T, E> Result<Option<T>, E> {
+    pub fn transpose(self) -> Option<Result<T, E>> {
+        match self {
+            Ok(None) => None,
+            Ok(Some(x)) => Some(Ok(x)),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+impl<T, E> Result<Result<T, E>, E> {
+    pub fn flatten(self) -> Result<T, E> {
+        self.and_then(|inner| inner)
+    }
+}
+
+/* ===== `?` and std =======
+   ========================= */
+// The `?` operator works through a trait (`Try`) that is still unstable,
+// so our enums cannot support it directly.
+// What we can do is convert to and from the standard types, with `From`,
+// and then use `?` on those: `let x = ours.into_std()?;`
+impl<T> From<Option<T>> for std::option::Option<T> {
+    fn from(o: Option<T>) -> Self {
+        match o {
+            Some(x) => std::option::Option::Some(x),
+            None => std::option::Option::None,
+        }
+    }
+}
+impl<T> From<std::option::Option<T>> for Option<T> {
+    fn from(o: std::option::Option<T>) -> Self {
+        match o {
+            std::option::Option::Some(x) => Some(x),
+            std::option::Option::None => None,
+        }
+    }
+}
+impl<T, E> From<Result<T, E>> for std::result::Result<T, E> {
+    fn from(r: Result<T, E>) -> Self {
+        match r {
+            Ok(x) => std::result::Result::Ok(x),
+            Err(e) => std::result::Result::Err(e),
+        }
+    }
+}
+impl<T, E> From<std::result::Result<T, E>> for Result<T, E> {
+    fn from(r: std::result::Result<T, E>) -> Self {
+        match r {
+            std::result::Result::Ok(x) => Ok(x),
+            std::result::Result::Err(e) => Err(e),
+        }
+    }
+}
+
+// `into()` needs a type annotation to know the target; these read better
+impl<T> Option<T> {
+    pub fn into_std(self) -> std::option::Option<T> {
+        self.into()
+    }
+}
+impl<T, E> Result<T, E> {
+    pub fn into_std(self) -> std::result::Result<T, E> {
+        self.into()
+    }
+}
+
+// a function in "our" world
+fn parse_digit(c: char) -> Result<u32, String> {
+    c.to_digit(10).ok_or(format!("{:?} is not a digit", c)).into()
+}
+
+// a function in the std world, using `?` on our types after conversion
+fn sum_of_digits(s: &str) -> std::result::Result<u32, String> {
+    let mut sum = 0;
+    for c in s.chars() {
+        sum += parse_digit(c).into_std()?;
+    }
+    std::result::Result::Ok(sum)
+}
+
+pub fn optionresult_usage() {
+    let x: Option<i32> = Some(4);
+    let doubled = x.map(|v| v * 2);
+    let half = x.and_then(|v| if v % 2 == 0 { Some(v / 2) } else { None });
+    println!("{:?} {:?} {:?}", x, doubled, half);
+    println!("zip: {:?}", x.zip(Some('a')));
+    println!("ok_or: {:?}", None::<i32>.ok_or("nothing"));
+    println!("flatten: {:?}", Some(Some(3)).flatten());
+    let r: Result<Option<i32>, String> = Ok(Some(3));
+    println!("transpose: {:?}", r.transpose());
+    println!("digits of 1234: {:?}", sum_of_digits("1234"));
+    println!("digits of 12x4: {:?}", sum_of_digits("12x4"));
+    for v in Some(10).iter() {
+        println!("iterating over an Option: {}", v);
+    }
+}
+
+// Property tests: for many random inputs, our methods must give exactly
+// what the std ones give. `rand` (see `c05_modules`) generates the inputs.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    type StdOption<T> = std::option::Option<T>;
+    type StdResult<T, E> = std::result::Result<T, E>;
+
+    fn random_option(rng: &mut impl Rng) -> StdOption<i32> {
+        if rng.gen_bool(0.3) { StdOption::None } else { StdOption::Some(rng.gen_range(-100..100)) }
+    }
+    fn random_result(rng: &mut impl Rng) -> StdResult<i32, String> {
+        if rng.gen_bool(0.3) {
+            StdResult::Err(format!("e{}", rng.gen_range(0..10)))
+        } else {
+            StdResult::Ok(rng.gen_range(-100..100))
+        }
+    }
+    // the closures used by the combinators: they can fail on some inputs
+    fn half(x: i32) -> StdOption<i32> {
+        if x % 2 == 0 { StdOption::Some(x / 2) } else { StdOption::None }
+    }
+    fn checked_half(x: i32) -> StdResult<i32, String> {
+        if x % 2 == 0 { StdResult::Ok(x / 2) } else { StdResult::Err(format!("{} is odd", x)) }
+    }
+
+    #[test]
+    // the lazy `|| p`, `|| 8` are on purpose: the closure-taking methods are what we are testing
+    #[allow(clippy::unnecessary_lazy_evaluations)]
+    fn option_behaves_like_std() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            let std_o = random_option(&mut rng);
+            let std_p = random_option(&mut rng);
+            let o: Option<i32> = std_o.into();
+            let p: Option<i32> = std_p.into();
+
+            assert_eq!(o.is_some(), std_o.is_some());
+            assert_eq!(o.is_none(), std_o.is_none());
+            assert_eq!(o.map(|x| x * 3).into_std(), std_o.map(|x| x * 3));
+            assert_eq!(o.and_then(|x| half(x).into()).into_std(), std_o.and_then(half));
+            assert_eq!(o.or(p).into_std(), std_o.or(std_p));
+            assert_eq!(o.or_else(|| p).into_std(), std_o.or_else(|| std_p));
+            assert_eq!(o.unwrap_or(7), std_o.unwrap_or(7));
+            assert_eq!(o.unwrap_or_else(|| 8), std_o.unwrap_or_else(|| 8));
+            assert_eq!(o.filter(|x| *x > 0).into_std(), std_o.filter(|x| *x > 0));
+            assert_eq!(o.ok_or("none").into_std(), std_o.ok_or("none"));
+            assert_eq!(o.ok_or_else(|| 0u8).into_std(), std_o.ok_or_else(|| 0u8));
+            assert_eq!(o.zip(p).into_std(), std_o.zip(std_p));
+            assert_eq!(o.iter().collect::<Vec<_>>(), std_o.iter().collect::<Vec<_>>());
+            assert_eq!(Some(o).flatten().into_std(), StdOption::Some(std_o).flatten());
+
+            let mut o2 = o;
+            let mut std_o2 = std_o;
+            assert_eq!(o2.take().into_std(), std_o2.take());
+            assert_eq!(o2.into_std(), std_o2);
+        }
+    }
+
+    #[test]
+    // the lazy `|_| Ok(0)` are on purpose: the closure-taking methods are what we are testing
+    #[allow(clippy::unnecessary_lazy_evaluations)]
+    fn result_behaves_like_std() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            let std_r = random_result(&mut rng);
+            let r: Result<i32, String> = std_r.clone().into();
+
+            assert_eq!(r.is_ok(), std_r.is_ok());
+            assert_eq!(r.is_err(), std_r.is_err());
+            assert_eq!(r.clone().ok().into_std(), std_r.clone().ok());
+            assert_eq!(r.clone().err().into_std(), std_r.clone().err());
+            assert_eq!(r.clone().map(|x| x + 1).into_std(), std_r.clone().map(|x| x + 1));
+            assert_eq!(r.clone().map_err(|e| e.len()).into_std(), std_r.clone().map_err(|e| e.len()));
+            assert_eq!(r.clone().and_then(|x| checked_half(x).into()).into_std(), std_r.clone().and_then(checked_half));
+            assert_eq!(r.clone().or_else(|e| Result::<i32, usize>::Err(e.len())).into_std(),
+                       std_r.clone().or_else(|e| StdResult::<i32, usize>::Err(e.len())));
+            assert_eq!(r.clone().or_else(|_| Result::<i32, ()>::Ok(0)).into_std(),
+                       std_r.clone().or_else(|_| StdResult::<i32, ()>::Ok(0)));
+            assert_eq!(r.clone().unwrap_or(5), std_r.clone().unwrap_or(5));
+            assert_eq!(r.clone().unwrap_or_else(|e| e.len() as i32), std_r.clone().unwrap_or_else(|e| e.len() as i32));
+            assert_eq!(r.iter().collect::<Vec<_>>(), std_r.iter().collect::<Vec<_>>());
+            // std's `Result::flatten` is still unstable: `and_then(identity)` is its definition
+            let nested: Result<Result<i32, String>, String> = Ok(r.clone());
+            assert_eq!(nested.flatten().into_std(), StdResult::<StdResult<i32, String>, String>::Ok(std_r.clone()).and_then(|x| x));
+        }
+    }
+
+    #[test]
+    fn transpose_behaves_like_std() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            let std_r = random_result(&mut rng);
+            let std_o = random_option(&mut rng);
+            // Option<Result<..>>
+            let std_or: StdOption<StdResult<i32, String>> = std_o.map(|_| std_r.clone());
+            let or: Option<Result<i32, String>> = Option::from(std_or.clone()).map(Result::from);
+            let expected = std_or.transpose();
+            assert_eq!(or.transpose().map(|o| o.into_std()).into_std(), expected);
+            // Result<Option<..>>
+            let std_ro: StdResult<StdOption<i32>, String> = std_r.map(|_| std_o);
+            let ro: Result<Option<i32>, String> = Result::from(std_ro.clone()).map(Option::from);
+            assert_eq!(ro.transpose().map(|r| r.into_std()).into_std(), std_ro.transpose());
+        }
+    }
+
+    #[test]
+    fn question_mark_through_conversion() {
+        assert_eq!(sum_of_digits("1234"), StdResult::Ok(10));
+        assert_eq!(sum_of_digits("12x4"), StdResult::Err(String::from("'x' is not a digit")));
+    }
+
+    #[test]
+    #[should_panic(expected = "called `Option::unwrap()` on a `None` value")]
+    fn unwrap_none_panics_like_std() {
+        None::<i32>.unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "called `Result::unwrap()` on an `Err` value: \"bad\"")]
+    fn unwrap_err_panics_like_std() {
+        Result::<i32, &str>::Err("bad").unwrap();
+    }
+}
@@ -0,0 +1,458 @@
+/// This module generalises `calculateS` from `c03_enums`.
+/// `calculateS` answers one question: how many 's' or 'S' are in a String.
+/// `TextStats` answers the general one: how many times does each character
+/// (and each sequence of n characters, an "n-gram") appear in a text,
+/// where "character" is configurable:
+///     CaseFold     whether 'S' and 's' count as the same character
+///     Normalize    whether 'é' counts as 'e'
+///     CharSet      which characters are counted at all
+/// The text can come from a `&str`, from any `Read`er, or from a file,
+/// and is processed in chunks, so a large file is never loaded in memory all at once.
+/// See
+///         https://doc.rust-lang.org/std/primitive.char.html
+///         https://doc.rust-lang.org/std/collections/struct.HashMap.html#method.entry
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/* ======= Options =========
+   ========================= */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaseFold {
+    // 'S' and 's' are different
+    None,
+    // only 'A'..='Z' are folded, as `eq_ignore_ascii_case` in `calculateS` does
+    Ascii,
+    // every letter with a lowercase form: 'É' becomes 'é' too
+    Unicode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalize {
+    None,
+    // 'é' (one char) and 'e' + '\u{301}' (a letter and a combining accent) both become 'e'.
+    // Full Unicode normalisation (NFC, NFD, ...) needs big tables, found in the
+    // `unicode-normalization` crate; here we handle the combining accents and Latin-1 letters,
+    // which is enough for most European text.
+    StripAccents,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CharSet {
+    Any,
+    Alphabetic,
+    Alphanumeric,
+    // checked AFTER folding: with `CaseFold::Ascii`, `Only({'s'})` also counts 'S'
+    Only(BTreeSet<char>),
+}
+
+impl CharSet {
+    pub fn only(chars: &str) -> CharSet {
+        CharSet::Only(chars.chars().collect())
+    }
+
+    fn contains(&self, c: char) -> bool {
+        match self {
+            CharSet::Any => true,
+            CharSet::Alphabetic => c.is_alphabetic(),
+            CharSet::Alphanumeric => c.is_alphanumeric(),
+            CharSet::Only(set) => set.contains(&c),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub fold: CaseFold,
+    pub normalize: Normalize,
+    pub charset: CharSet,
+    // 0 disables n-gram counting. N-grams are made of COUNTED chars only:
+    // with `CharSet::Alphabetic`, "a b" has the 2-gram "ab"
+    pub ngram: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options { fold: CaseFold::None, normalize: Normalize::None, charset: CharSet::Any, ngram: 0 }
+    }
+}
+
+// Latin-1 letters with an accent, and their base letter
+fn strip_accent(c: char) -> Option<char> {
+    // combining diacritical marks: they decorate the previous char, so we drop them
+    if ('\u{300}'..='\u{36f}').contains(&c) {
+        return None;
+    }
+    let base = match c {
+        'à'..='å' => 'a',
+        'À'..='Å' => 'A',
+        'ç' => 'c',
+        'Ç' => 'C',
+        'è'..='ë' => 'e',
+        'È'..='Ë' => 'E',
+        'ì'..='ï' => 'i',
+        'Ì'..='Ï' => 'I',
+        'ñ' => 'n',
+        'Ñ' => 'N',
+        'ò'..='ö' | 'ø' => 'o',
+        'Ò'..='Ö' | 'Ø' => 'O',
+        'ù'..='ü' => 'u',
+        'Ù'..='Ü' => 'U',
+        'ý' | 'ÿ' => 'y',
+        'Ý' => 'Y',
+        other => other,
+    };
+    Some(base)
+}
+
+/* ======= The engine ======
+   ========================= */
+pub struct TextStats {
+    options: Options,
+    chars: HashMap<char, u64>,
+    ngrams: HashMap<String, u64>,
+    // the last `ngram` counted characters, kept between chunks
+    window: VecDeque<char>,
+    total: u64,
+}
+
+impl TextStats {
+    pub fn new(options: Options) -> TextStats {
+        TextStats { options, chars: HashMap::new(), ngrams: HashMap::new(), window: VecDeque::new(), total: 0 }
+    }
+
+    pub fn from_text(text: &str, options: Options) -> TextStats {
+        let mut stats = TextStats::new(options);
+        stats.feed_str(text);
+        stats
+    }
+
+    pub fn from_reader<R: Read>(reader: R, options: Options) -> io::Result<TextStats> {
+        let mut stats = TextStats::new(options);
+        stats.feed_reader(reader)?;
+        Ok(stats)
+    }
+
+    pub fn from_file(path: &Path, options: Options) -> io::Result<TextStats> {
+        TextStats::from_reader(File::open(path)?, options)
+    }
+
+    /// Counts one more piece of text; calling it twice is the same as calling it once
+    /// on the two pieces concatenated, n-grams included
+    pub fn feed_str(&mut self, text: &str) {
+        for c in text.chars() {
+            self.feed_char(c);
+        }
+    }
+
+    fn feed_char(&mut self, c: char) {
+        let c = match self.options.normalize {
+            Normalize::None => c,
+            Normalize::StripAccents => match strip_accent(c) {
+                Some(base) => base,
+                None => return,
+            },
+        };
+        // `to_lowercase` returns an iterator, because some chars lowercase to more than one:
+        // 'İ' (Turkish dotted I) becomes "i\u{307}"
+        match self.options.fold {
+            CaseFold::None => self.count(c),
+            CaseFold::Ascii => self.count(c.to_ascii_lowercase()),
+            CaseFold::Unicode => {
+                for lower in c.to_lowercase() {
+                    self.count(lower);
+                }
+            }
+        }
+    }
+
+    fn count(&mut self, c: char) {
+        if !self.options.charset.contains(c) {
+            return;
+        }
+        *self.chars.entry(c).or_insert(0) += 1;
+        self.total += 1;
+        let n = self.options.ngram;
+        if n > 0 {
+            self.window.push_back(c);
+            if self.window.len() > n {
+                self.window.pop_front();
+            }
+            if self.window.len() == n {
+                *self.ngrams.entry(self.window.iter().collect()).or_insert(0) += 1;
+            }
+        }
+    }
+
+    /// Reads `reader` to the end, one chunk at a time
+    pub fn feed_reader<R: Read>(&mut self, mut reader: R) -> io::Result<()> {
+        let mut buf = [0u8; 8192];
+        // bytes of a char split between two chunks: at most 3, as a char is at most 4 bytes
+        let mut pending: Vec<u8> = Vec::new();
+        loop {
+            let read = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            pending.extend_from_slice(&buf[..read]);
+            let valid = match std::str::from_utf8(&pending) {
+                Ok(text) => text.len(),
+                // `error_len() == None` means "the input ended in the middle of a char":
+                // not an error yet, the rest is in the next chunk
+                Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+            };
+            // `from_utf8` just checked these bytes: this cannot fail
+            let text = std::str::from_utf8(&pending[..valid]).unwrap();
+            self.feed_str(text);
+            pending.drain(..valid);
+        }
+        if pending.is_empty() {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::InvalidData, "stream ended in the middle of a UTF-8 character"))
+        }
+    }
+
+    /* ======= Queries =========
+       ========================= */
+    /// Total number of counted characters
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn count_of(&self, c: char) -> u64 {
+        self.chars.get(&c).copied().unwrap_or(0)
+    }
+
+    pub fn count_of_ngram(&self, ngram: &str) -> u64 {
+        self.ngrams.get(ngram).copied().unwrap_or(0)
+    }
+
+    /// The `k` most frequent characters, most frequent first; ties in character order
+    pub fn top_chars(&self, k: usize) -> Vec<(char, u64)> {
+        top_k(self.chars.iter().map(|(c, n)| (*c, *n)), k)
+    }
+
+    pub fn top_ngrams(&self, k: usize) -> Vec<(String, u64)> {
+        top_k(self.ngrams.iter().map(|(s, n)| (s.clone(), *n)), k)
+    }
+
+    /* ======= Output ==========
+       ========================= */
+    pub fn to_table(&self, k: usize) -> String {
+        let mut out = String::new();
+        // writing into a String cannot fail: `unwrap` is safe
+        writeln!(out, "{:<8}{:>10}{:>9}", "char", "count", "freq").unwrap();
+        for (c, n) in self.top_chars(k) {
+            writeln!(out, "{:<8}{:>10}{:>8.2}%", format!("{:?}", c), n, self.percent(n)).unwrap();
+        }
+        if self.options.ngram > 0 {
+            writeln!(out, "{:<8}{:>10}", format!("{}-gram", self.options.ngram), "count").unwrap();
+            for (g, n) in self.top_ngrams(k) {
+                writeln!(out, "{:<8}{:>10}", format!("{:?}", g), n).unwrap();
+            }
+        }
+        out
+    }
+
+    /// The top `k` entries as a JSON object; written by hand, as we have no `serde` here
+    pub fn to_json(&self, k: usize) -> String {
+        let chars: Vec<String> = self
+            .top_chars(k)
+            .into_iter()
+            .map(|(c, n)| format!("{{\"char\":{},\"count\":{}}}", json_string(&c.to_string()), n))
+            .collect();
+        let ngrams: Vec<String> = self
+            .top_ngrams(k)
+            .into_iter()
+            .map(|(g, n)| format!("{{\"ngram\":{},\"count\":{}}}", json_string(&g), n))
+            .collect();
+        format!(
+            "{{\"total\":{},\"distinct\":{},\"chars\":[{}],\"ngrams\":[{}]}}",
+            self.total,
+            self.chars.len(),
+            chars.join(","),
+            ngrams.join(",")
+        )
+    }
+
+    fn percent(&self, n: u64) -> f64 {
+        if self.total == 0 { 0.0 } else { n as f64 * 100.0 / self.total as f64 }
+    }
+}
+
+// generic over the key, so that chars and n-grams share the code
+fn top_k<K: Ord>(entries: impl Iterator<Item = (K, u64)>, k: usize) -> Vec<(K, u64)> {
+    let mut all: Vec<(K, u64)> = entries.collect();
+    // by count descending, then key ascending: the HashMap order is random, the output must not be
+    all.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    all.truncate(k);
+    all
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+pub fn textstatsexample() {
+    // `calculateS`, as a special case
+    let s_only = Options { fold: CaseFold::Ascii, charset: CharSet::only("s"), ..Options::default() };
+    println!("s or S: {}", TextStats::from_text("adv prog sssSS", s_only).count_of('s'));
+
+    let text = "Però è così: the café's crème brûlée is très, très bon.";
+    let options = Options {
+        fold: CaseFold::Unicode,
+        normalize: Normalize::StripAccents,
+        charset: CharSet::Alphabetic,
+        ngram: 2,
+    };
+    let stats = TextStats::from_text(text, options);
+    print!("{}", stats.to_table(5));
+    println!("{}", stats.to_json(3));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a reader that hands out one byte per `read`: the worst case for chunking
+    struct OneByte<'a>(&'a [u8]);
+    impl Read for OneByte<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.split_first() {
+                Some((b, rest)) if !buf.is_empty() => {
+                    buf[0] = *b;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn generalises_calculate_s() {
+        let text = "Sassy sisters say yes";
+        let expected = text.chars().filter(|c| c.eq_ignore_ascii_case(&'s')).count() as u64;
+        let options = Options { fold: CaseFold::Ascii, charset: CharSet::only("s"), ..Options::default() };
+        let stats = TextStats::from_text(text, options);
+        assert_eq!(stats.count_of('s'), expected);
+        assert_eq!(stats.total(), expected);
+    }
+
+    #[test]
+    fn case_folding_modes() {
+        let text = "SsÉé";
+        let none = TextStats::from_text(text, Options::default());
+        assert_eq!((none.count_of('s'), none.count_of('S')), (1, 1));
+        let ascii = TextStats::from_text(text, Options { fold: CaseFold::Ascii, ..Options::default() });
+        assert_eq!((ascii.count_of('s'), ascii.count_of('é'), ascii.count_of('É')), (2, 1, 1));
+        let unicode = TextStats::from_text(text, Options { fold: CaseFold::Unicode, ..Options::default() });
+        assert_eq!((unicode.count_of('s'), unicode.count_of('é'), unicode.count_of('É')), (2, 2, 0));
+    }
+
+    #[test]
+    fn accents_are_stripped_in_both_forms() {
+        // precomposed 'é', and 'e' followed by a combining acute accent
+        let options = Options { normalize: Normalize::StripAccents, ..Options::default() };
+        let stats = TextStats::from_text("é e\u{301} È", options);
+        assert_eq!(stats.count_of('e'), 2);
+        assert_eq!(stats.count_of('E'), 1);
+        assert_eq!(stats.count_of('\u{301}'), 0);
+    }
+
+    #[test]
+    fn charsets_filter_before_counting() {
+        let alpha = TextStats::from_text("a1 b2!", Options { charset: CharSet::Alphabetic, ..Options::default() });
+        assert_eq!(alpha.total(), 2);
+        let alnum = TextStats::from_text("a1 b2!", Options { charset: CharSet::Alphanumeric, ..Options::default() });
+        assert_eq!(alnum.total(), 4);
+        let only = TextStats::from_text("a1 b2!", Options { charset: CharSet::only("!1"), ..Options::default() });
+        assert_eq!(only.top_chars(10), vec![('!', 1), ('1', 1)]);
+    }
+
+    #[test]
+    fn ngrams_span_chunks() {
+        let options = Options { ngram: 2, ..Options::default() };
+        let whole = TextStats::from_text("abab", options.clone());
+        let mut pieces = TextStats::new(options);
+        pieces.feed_str("ab");
+        pieces.feed_str("ab");
+        assert_eq!(whole.top_ngrams(10), vec![(String::from("ab"), 2), (String::from("ba"), 1)]);
+        assert_eq!(pieces.top_ngrams(10), whole.top_ngrams(10));
+    }
+
+    #[test]
+    fn reader_handles_chars_split_across_reads() {
+        let text = "naïve café — ☃ 𝄞";
+        let options = Options { ngram: 3, ..Options::default() };
+        let streamed = TextStats::from_reader(OneByte(text.as_bytes()), options.clone()).unwrap();
+        let direct = TextStats::from_text(text, options);
+        assert_eq!(streamed.top_chars(100), direct.top_chars(100));
+        assert_eq!(streamed.top_ngrams(100), direct.top_ngrams(100));
+    }
+
+    #[test]
+    fn invalid_utf8_is_an_error() {
+        let bad = TextStats::from_reader(&b"ok\xffok"[..], Options::default());
+        assert_eq!(bad.err().map(|e| e.kind()), Some(io::ErrorKind::InvalidData));
+        // a truncated char at the very end is invalid too
+        let truncated = TextStats::from_reader(&"é".as_bytes()[..1], Options::default());
+        assert!(truncated.is_err());
+    }
+
+    #[test]
+    fn files_are_streamed() {
+        let path = std::env::temp_dir().join(format!("ap-class-textstats-{}.txt", std::process::id()));
+        // bigger than one 8 KiB chunk, with multi-byte chars across the boundaries
+        std::fs::write(&path, "ß".repeat(10_000)).unwrap();
+        let stats = TextStats::from_file(&path, Options::default()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(stats.count_of('ß'), 10_000);
+    }
+
+    #[test]
+    fn top_k_is_deterministic() {
+        let stats = TextStats::from_text("ccbbaad", Options::default());
+        assert_eq!(stats.top_chars(3), vec![('a', 2), ('b', 2), ('c', 2)]);
+        assert_eq!(stats.top_chars(0), vec![]);
+    }
+
+    #[test]
+    fn json_output() {
+        let stats = TextStats::from_text("a\"a\n", Options { ngram: 2, ..Options::default() });
+        assert_eq!(
+            stats.to_json(2),
+            "{\"total\":4,\"distinct\":3,\
+             \"chars\":[{\"char\":\"a\",\"count\":2},{\"char\":\"\\n\",\"count\":1}],\
+             \"ngrams\":[{\"ngram\":\"\\\"a\",\"count\":1},{\"ngram\":\"a\\n\",\"count\":1}]}"
+        );
+    }
+
+    #[test]
+    fn table_output() {
+        let table = TextStats::from_text("aab", Options::default()).to_table(2);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("'a'") && lines[1].ends_with("66.67%"), "{}", lines[1]);
+    }
+}
@@ -0,0 +1,381 @@
+/// This module shows how to construct structs that must be valid, with the BUILDER pattern:
+///     no overloading      Rust has no default arguments and no overloaded `new`s,
+///                         so optional fields become builder methods
+///     typestate           a missing REQUIRED field is a compile error, not a runtime one
+///     validated build     `build()` returns a `Result`, so no invalid value ever exists
+/// It builds three structs of the course: `User` (c04_structs), `Rhombus` (c04_structs)
+/// and `NewsArticle` (c08_traits).
+/// See
+///         https://rust-unofficial.github.io/patterns/patterns/creational/builder.html
+///         https://cliffle.com/blog/rust-typestate/
+///         https://doc.rust-lang.org/book/ch05-03-method-syntax.html#associated-functions
+
+use std::fmt;
+
+use crate::classes::c04_structs::{Rhombus, User};
+use crate::classes::c04_users::{is_valid_email, is_valid_username};
+use crate::classes::c08_traits::NewsArticle;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    Empty(&'static str),
+    TooLong { field: &'static str, max: usize },
+    Invalid { field: &'static str, value: String },
+    OutOfRange { field: &'static str, value: i64, min: i64, max: i64 },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::Empty(field) => write!(f, "{} must not be empty", field),
+            BuildError::TooLong { field, max } => write!(f, "{} is longer than {} characters", field, max),
+            BuildError::Invalid { field, value } => write!(f, "invalid {}: {:?}", field, value),
+            BuildError::OutOfRange { field, value, min, max } => {
+                write!(f, "{} must be in {}..={}, got {}", field, min, max, value)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+/* ===== Typestate markers =====
+   ========================= */
+
+// A builder with `Option` fields can only notice a missing field when `build()` runs.
+// Instead, every required field is a TYPE PARAMETER of the builder:
+// it is `Missing` until the field is given, and `Set<T>` afterwards.
+// `build()` exists only on the builder whose parameters are all `Set`,
+// so forgetting a field means there is no `build()` to call.
+// Both markers take no space at run time: this costs nothing.
+
+/// marker: the required field has not been given yet
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Missing;
+
+/// marker: the required field has been given, and here is its value
+#[derive(Debug, Clone)]
+pub struct Set<T>(T);
+
+/* ===== User =====
+   ========================= */
+
+/// required: username, email; optional: sign_in_count (0), active (true)
+#[derive(Debug, Clone)]
+pub struct UserBuilder<U, E> {
+    username: U,
+    email: E,
+    sign_in_count: u64,
+    active: bool,
+}
+
+impl UserBuilder<Missing, Missing> {
+    pub fn new() -> Self {
+        UserBuilder { username: Missing, email: Missing, sign_in_count: 0, active: true }
+    }
+}
+
+// these `impl`s are generic over the OTHER parameter, so the required fields can come in any order,
+// but each can only be given while it is still `Missing`
+impl<E> UserBuilder<Missing, E> {
+    pub fn username(self, username: impl Into<String>) -> UserBuilder<Set<String>, E> {
+        // the type changes, so `..self` cannot be used here: every field is listed
+        UserBuilder {
+            username: Set(username.into()),
+            email: self.email,
+            sign_in_count: self.sign_in_count,
+            active: self.active,
+        }
+    }
+}
+
+impl<U> UserBuilder<U, Missing> {
+    pub fn email(self, email: impl Into<String>) -> UserBuilder<U, Set<String>> {
+        UserBuilder {
+            username: self.username,
+            email: Set(email.into()),
+            sign_in_count: self.sign_in_count,
+            active: self.active,
+        }
+    }
+}
+
+// optional fields can be set in any state; here the type does not change, so `..self` works
+impl<U, E> UserBuilder<U, E> {
+    pub fn sign_in_count(self, sign_in_count: u64) -> Self {
+        UserBuilder { sign_in_count, ..self }
+    }
+    pub fn active(self, active: bool) -> Self {
+        UserBuilder { active, ..self }
+    }
+}
+
+impl UserBuilder<Set<String>, Set<String>> {
+    pub fn build(self) -> Result<User, BuildError> {
+        let (Set(username), Set(email)) = (self.username, self.email);
+        if !is_valid_username(&username) {
+            return Err(BuildError::Invalid { field: "username", value: username });
+        }
+        if !is_valid_email(&email) {
+            return Err(BuildError::Invalid { field: "email", value: email });
+        }
+        Ok(User::from_parts(username, email, self.sign_in_count, self.active))
+    }
+}
+
+/* ===== Rhombus =====
+   ========================= */
+
+/// required: side, acute_angle (in degrees, 90 makes it a square)
+#[derive(Debug, Clone)]
+pub struct RhombusBuilder<S, A> {
+    side: S,
+    acute_angle: A,
+}
+
+impl RhombusBuilder<Missing, Missing> {
+    pub fn new() -> Self {
+        RhombusBuilder { side: Missing, acute_angle: Missing }
+    }
+}
+
+impl<A> RhombusBuilder<Missing, A> {
+    pub fn side(self, side: u32) -> RhombusBuilder<Set<u32>, A> {
+        RhombusBuilder { side: Set(side), acute_angle: self.acute_angle }
+    }
+}
+
+impl<S> RhombusBuilder<S, Missing> {
+    pub fn acute_angle(self, degrees: i32) -> RhombusBuilder<S, Set<i32>> {
+        RhombusBuilder { side: self.side, acute_angle: Set(degrees) }
+    }
+}
+
+impl RhombusBuilder<Set<u32>, Set<i32>> {
+    pub fn build(self) -> Result<Rhombus, BuildError> {
+        let (Set(side), Set(angle)) = (self.side, self.acute_angle);
+        if side == 0 {
+            return Err(BuildError::OutOfRange { field: "side", value: 0, min: 1, max: u32::MAX as i64 });
+        }
+        // at 0 the rhombus is flat; above 90 the angle is not the acute one
+        if !(1..=90).contains(&angle) {
+            return Err(BuildError::OutOfRange { field: "acute_angle", value: angle as i64, min: 1, max: 90 });
+        }
+        Ok(Rhombus::from_parts(side, angle))
+    }
+}
+
+/* ===== NewsArticle =====
+   ========================= */
+
+/// required: headline, author; optional: location ("unknown"), content (empty)
+// the fields of `NewsArticle` are public, so nothing forces anyone to use this builder:
+// a builder protects invariants only when the struct cannot be built another way
+#[derive(Debug, Clone)]
+pub struct NewsArticleBuilder<H, A> {
+    headline: H,
+    author: A,
+    location: Option<String>,
+    content: String,
+}
+
+impl NewsArticleBuilder<Missing, Missing> {
+    pub fn new() -> Self {
+        NewsArticleBuilder { headline: Missing, author: Missing, location: None, content: String::new() }
+    }
+}
+
+impl<A> NewsArticleBuilder<Missing, A> {
+    pub fn headline(self, headline: impl Into<String>) -> NewsArticleBuilder<Set<String>, A> {
+        NewsArticleBuilder {
+            headline: Set(headline.into()),
+            author: self.author,
+            location: self.location,
+            content: self.content,
+        }
+    }
+}
+
+impl<H> NewsArticleBuilder<H, Missing> {
+    pub fn author(self, author: impl Into<String>) -> NewsArticleBuilder<H, Set<String>> {
+        NewsArticleBuilder {
+            headline: self.headline,
+            author: Set(author.into()),
+            location: self.location,
+            content: self.content,
+        }
+    }
+}
+
+impl<H, A> NewsArticleBuilder<H, A> {
+    pub fn location(self, location: impl Into<String>) -> Self {
+        NewsArticleBuilder { location: Some(location.into()), ..self }
+    }
+    pub fn content(self, content: impl Into<String>) -> Self {
+        NewsArticleBuilder { content: content.into(), ..self }
+    }
+}
+
+pub const MAX_HEADLINE: usize = 120;
+
+impl NewsArticleBuilder<Set<String>, Set<String>> {
+    pub fn build(self) -> Result<NewsArticle, BuildError> {
+        let headline = self.headline.0.trim().to_string();
+        let author = self.author.0.trim().to_string();
+        if headline.is_empty() {
+            return Err(BuildError::Empty("headline"));
+        }
+        if headline.chars().count() > MAX_HEADLINE {
+            return Err(BuildError::TooLong { field: "headline", max: MAX_HEADLINE });
+        }
+        if author.is_empty() {
+            return Err(BuildError::Empty("author"));
+        }
+        let location = match self.location {
+            Some(l) if l.trim().is_empty() => return Err(BuildError::Empty("location")),
+            Some(l) => l.trim().to_string(),
+            None => String::from("unknown"),
+        };
+        Ok(NewsArticle { headline, location, author, content: self.content })
+    }
+}
+
+/* ===== Misuse =====
+   ========================= */
+
+// All of the following are caught by the compiler.
+//
+// forgetting a required field:
+// let u = UserBuilder::new().username("alice").build();
+// DNC: error[E0599]: no method named `build` found for struct `UserBuilder<Set<String>, Missing>` in the current scope
+//
+// giving a required field twice:
+// let r = RhombusBuilder::new().side(3).side(4);
+// DNC: error[E0599]: no method named `side` found for struct `RhombusBuilder<Set<u32>, Missing>` in the current scope
+//
+// using a `Result` as if it were the value:
+// let a: NewsArticle = NewsArticleBuilder::new().headline("h").author("a").build();
+// DNC: error[E0308]: mismatched types: expected `NewsArticle`, found `Result<NewsArticle, BuildError>`
+//
+// building the struct directly, bypassing the checks (only for private fields):
+// let r = Rhombus { side: 3, acute_angle: 0 };
+// DNC: error[E0451]: field `acute_angle` of struct `Rhombus` is private
+//
+// QUIZ: what is the type of `b` below, and can we call `b.email("x@y.io")` afterwards?
+// let b = UserBuilder::new().email("a@b.co").active(false);
+
+
+
+
+
+//
+// `UserBuilder<Missing, Set<String>>`: `active` keeps the state, and `email` is only
+// available while the email is `Missing`, so no
+// DNC: error[E0599]: no method named `email` found for struct `UserBuilder<Missing, Set<String>>` in the current scope
+
+pub fn buildersexample() -> Result<(), BuildError> {
+    // required fields in any order, optional ones anywhere in between
+    let user = UserBuilder::new()
+        .email("ann@example.com")
+        .active(false)
+        .username("ann")
+        .build()?;
+    println!("{:?}", user);
+
+    match RhombusBuilder::new().side(5).acute_angle(120).build() {
+        Ok(r) => println!("rhombus with side {}", r.side),
+        Err(e) => println!("rejected: {}", e),
+    }
+    let rhombus = RhombusBuilder::new().acute_angle(60).side(5).build()?;
+    println!("rhombus with side {} and angle {}", rhombus.side, rhombus.acute_angle());
+
+    let article = NewsArticleBuilder::new()
+        .headline("  Wasps!  ")
+        .author("Patrignani")
+        .content("there is a wasp in my attic")
+        .build()?;
+    println!("{} by {} ({})", article.headline, article.author, article.location);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_defaults_and_optionals() {
+        let u = UserBuilder::new().username("alice").email("alice@example.com").build().unwrap();
+        assert_eq!(u, User::new("alice".into(), "alice@example.com".into()));
+
+        let u = UserBuilder::new()
+            .sign_in_count(7)
+            .email("bob@example.org")
+            .active(false)
+            .username("bob")
+            .build()
+            .unwrap();
+        assert_eq!((u.username(), u.email(), u.sign_in_count(), u.is_active()), ("bob", "bob@example.org", 7, false));
+    }
+
+    #[test]
+    fn user_validation() {
+        let bad_name = UserBuilder::new().username("9x").email("a@b.co").build();
+        assert_eq!(bad_name, Err(BuildError::Invalid { field: "username", value: "9x".into() }));
+        let bad_mail = UserBuilder::new().username("alice").email("alice").build();
+        assert_eq!(bad_mail, Err(BuildError::Invalid { field: "email", value: "alice".into() }));
+    }
+
+    #[test]
+    fn rhombus_angles() {
+        let r = RhombusBuilder::new().side(3).acute_angle(90).build().unwrap();
+        assert_eq!((r.side, r.acute_angle()), (3, 90));
+        assert!(RhombusBuilder::new().side(3).acute_angle(1).build().is_ok());
+        for angle in [-30, 0, 91, 180] {
+            let e = RhombusBuilder::new().side(3).acute_angle(angle).build().err().unwrap();
+            assert!(matches!(e, BuildError::OutOfRange { field: "acute_angle", .. }), "{}", angle);
+        }
+        let e = RhombusBuilder::new().side(0).acute_angle(45).build().err().unwrap();
+        assert!(matches!(e, BuildError::OutOfRange { field: "side", value: 0, .. }));
+    }
+
+    #[test]
+    fn article_trims_and_defaults() {
+        let a = NewsArticleBuilder::new().author(" Marco ").headline(" Wasps! ").build().unwrap();
+        assert_eq!(a.headline, "Wasps!");
+        assert_eq!(a.author, "Marco");
+        assert_eq!(a.location, "unknown");
+        assert_eq!(a.content, "");
+        let a = NewsArticleBuilder::new()
+            .headline("h")
+            .author("a")
+            .location("Povo")
+            .content("c")
+            .build()
+            .unwrap();
+        assert_eq!((a.location.as_str(), a.content.as_str()), ("Povo", "c"));
+    }
+
+    #[test]
+    fn article_validation() {
+        let build = |h: &str, a: &str| NewsArticleBuilder::new().headline(h).author(a).build().err();
+        assert_eq!(build("   ", "a"), Some(BuildError::Empty("headline")));
+        assert_eq!(build("h", ""), Some(BuildError::Empty("author")));
+        assert_eq!(build(&"é".repeat(MAX_HEADLINE), "a"), None);
+        assert_eq!(
+            build(&"é".repeat(MAX_HEADLINE + 1), "a"),
+            Some(BuildError::TooLong { field: "headline", max: MAX_HEADLINE })
+        );
+        let e = NewsArticleBuilder::new().headline("h").author("a").location(" ").build().err();
+        assert_eq!(e, Some(BuildError::Empty("location")));
+    }
+
+    #[test]
+    fn markers_are_free() {
+        assert_eq!(std::mem::size_of::<Missing>(), 0);
+        assert_eq!(std::mem::size_of::<Set<u32>>(), std::mem::size_of::<u32>());
+        assert_eq!(
+            std::mem::size_of::<RhombusBuilder<Set<u32>, Set<i32>>>(),
+            std::mem::size_of::<(u32, i32)>()
+        );
+    }
+}
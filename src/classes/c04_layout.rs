@@ -0,0 +1,328 @@
+/// This module gives the `Rectangle` of `c04_structs` a position, and with it
+/// the operations of 2D games and GUI toolkits:
+///     can_hold, contains, intersects      yes/no questions
+///     intersection, union, overlap_area   new rectangles out of two
+///     shelf packing                       placing many rectangles into a bin
+///     render                              drawing the result as ASCII art
+/// All rectangles are axis-aligned (sides parallel to the axes) and use integer coordinates,
+/// with `y` growing downwards as on a screen. For rotated, float shapes see `c10_shapes`.
+/// See
+///         https://doc.rust-lang.org/book/ch05-03-method-syntax.html#methods-with-more-parameters
+///         https://en.wikipedia.org/wiki/Bin_packing_problem
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Rect {
+        Rect { x, y, width, height }
+    }
+
+    // the first column and row AFTER the rectangle: a rectangle covers x..right, y..bottom.
+    // The fields are public, so nothing stops `x + width` from going past u32::MAX:
+    // the part of the rectangle beyond the edge of the plane is cut off, instead of panicking
+    pub fn right(&self) -> u32 {
+        self.x.saturating_add(self.width)
+    }
+    pub fn bottom(&self) -> u32 {
+        self.y.saturating_add(self.height)
+    }
+
+    pub fn area(&self) -> u64 {
+        // u64: two u32 multiplied can overflow a u32
+        self.width as u64 * self.height as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Would `other` fit inside, ignoring positions?
+    /// Unlike the Rust Book's `can_hold` in `c04_structs`, which uses `>`,
+    /// a rectangle CAN hold one of the same size
+    // (`>=` is what packing needs: an item as wide as the bin fits)
+    pub fn can_hold(&self, other: &Rect) -> bool {
+        self.width >= other.width && self.height >= other.height
+    }
+
+    pub fn contains_point(&self, x: u32, y: u32) -> bool {
+        self.x <= x && x < self.right() && self.y <= y && y < self.bottom()
+    }
+
+    /// Is `other` entirely inside, positions included?
+    pub fn contains(&self, other: &Rect) -> bool {
+        self.x <= other.x && self.y <= other.y && other.right() <= self.right() && other.bottom() <= self.bottom()
+    }
+
+    /// Do the two share at least one cell? Touching sides do not count
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.intersection(other).is_some()
+    }
+
+    /// The common part, if any.
+    /// Along each axis, the overlap of [a, b) and [c, d) is [max(a, c), min(b, d)), when not empty
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+        if x < right && y < bottom {
+            Some(Rect::new(x, y, right - x, bottom - y))
+        } else {
+            None
+        }
+    }
+
+    /// The smallest rectangle containing both
+    pub fn union(&self, other: &Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rect::new(x, y, self.right().max(other.right()) - x, self.bottom().max(other.bottom()) - y)
+    }
+
+    pub fn overlap_area(&self, other: &Rect) -> u64 {
+        self.intersection(other).map_or(0, |r| r.area())
+    }
+}
+
+impl fmt::Display for Rect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}x{} at ({}, {})", self.width, self.height, self.x, self.y)
+    }
+}
+
+/* ===== Collisions ========
+   ========================= */
+/// All the pairs of rectangles that overlap, as indices into `rects`
+pub fn collisions(rects: &[Rect]) -> Vec<(usize, usize)> {
+    // every pair once: j starts after i. Quadratic, fine for a few hundred rectangles
+    let mut pairs = vec![];
+    for (i, a) in rects.iter().enumerate() {
+        for (j, b) in rects.iter().enumerate().skip(i + 1) {
+            if a.intersects(b) {
+                pairs.push((i, j));
+            }
+        }
+    }
+    pairs
+}
+
+/* ===== Shelf packing =====
+   ========================= */
+// Packing rectangles optimally is NP-hard; the shelf heuristic is simple and decent:
+//  1. sort the items by height, tallest first
+//  2. place them left to right on a "shelf" as tall as its first item
+//  3. when the next item does not fit in the remaining width, open a new shelf below
+// Sorting by height wastes less space: the items on a shelf have similar heights.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packing {
+    pub bin: Rect,
+    // `placed[i]` is where item i went, in the ORIGINAL order; None if it did not fit
+    pub placed: Vec<Option<Rect>>,
+}
+
+impl Packing {
+    pub fn unplaced(&self) -> Vec<usize> {
+        self.placed.iter().enumerate().filter(|(_, p)| p.is_none()).map(|(i, _)| i).collect()
+    }
+
+    pub fn used_area(&self) -> u64 {
+        self.placed.iter().flatten().map(|r| r.area()).sum()
+    }
+
+    /// Fraction of the bin covered by the placed items
+    pub fn fill_ratio(&self) -> f64 {
+        if self.bin.is_empty() { 0.0 } else { self.used_area() as f64 / self.bin.area() as f64 }
+    }
+}
+
+/// Places items of the given `(width, height)` sizes into a `bin_width` x `bin_height` bin
+pub fn shelf_pack(bin_width: u32, bin_height: u32, sizes: &[(u32, u32)]) -> Packing {
+    let bin = Rect::new(0, 0, bin_width, bin_height);
+    // we sort the INDICES, so that the result can be reported in the caller's order.
+    // `sort_by_key` is stable: items of equal height keep their order
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(sizes[i].1));
+
+    let mut placed = vec![None; sizes.len()];
+    // the current shelf: its top, its height, and the first free column
+    let (mut shelf_y, mut shelf_height, mut cursor_x) = (0u32, 0u32, 0u32);
+    for i in order {
+        let (w, h) = sizes[i];
+        if w > bin_width || h > bin_height {
+            continue;
+        }
+        // `cursor_x <= bin_width` and `shelf_y <= bin_height` always hold, so the subtractions
+        // below cannot underflow, while `cursor_x + w` could overflow for a huge bin
+        if w > bin_width - cursor_x {
+            // new shelf
+            shelf_y += shelf_height;
+            shelf_height = 0;
+            cursor_x = 0;
+        }
+        if h > bin_height - shelf_y {
+            // does not fit below: shorter items further on may still fit on this shelf
+            continue;
+        }
+        // the first item sets the height of the shelf; later ones are not taller, since we sorted
+        if shelf_height == 0 {
+            shelf_height = h;
+        }
+        placed[i] = Some(Rect::new(cursor_x, shelf_y, w, h));
+        cursor_x += w;
+    }
+    Packing { bin, placed }
+}
+
+/* ========= ASCII art =========
+   ============================= */
+/// Draws `rects` inside `bin`: each rectangle is filled with its letter ('A' is the first),
+/// cells covered by more than one are '#', empty cells are '.'
+pub fn render(bin: Rect, rects: &[Option<Rect>]) -> String {
+    let (w, h) = (bin.width as usize, bin.height as usize);
+    let mut grid = vec![vec!['.'; w]; h];
+    // 'A' to 'Z', then 'a' to 'z', then again from 'A'
+    let labels = ('A'..='Z').chain('a'..='z').cycle();
+    for (r, label) in rects.iter().zip(labels) {
+        // `if let` skips the items that were not placed, and those lying entirely outside the bin.
+        // Only the part inside the bin is visited: a huge rectangle costs no more than the bin itself
+        if let Some(r) = r.and_then(|r| bin.intersection(&r)) {
+            for y in r.y..r.bottom() {
+                for x in r.x..r.right() {
+                    let cell = &mut grid[(y - bin.y) as usize][(x - bin.x) as usize];
+                    *cell = if *cell == '.' { label } else { '#' };
+                }
+            }
+        }
+    }
+    let mut out = String::with_capacity((w + 1) * h);
+    for row in grid {
+        out.extend(row);
+        out.push('\n');
+    }
+    out
+}
+
+impl fmt::Display for Packing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", render(self.bin, &self.placed))
+    }
+}
+
+pub fn layoutexample() {
+    let a = Rect::new(0, 0, 6, 4);
+    let b = Rect::new(4, 2, 5, 3);
+    let c = Rect::new(12, 0, 2, 2);
+    println!("{} and {}: intersection {:?}, union {}, overlap {}", a, b, a.intersection(&b), a.union(&b), a.overlap_area(&b));
+    println!("collisions among a, b, c: {:?}", collisions(&[a, b, c]));
+    print!("{}", render(Rect::new(0, 0, 15, 6), &[Some(a), Some(b), Some(c)]));
+
+    let sizes = [(4, 3), (6, 2), (3, 3), (5, 4), (2, 1), (8, 2), (3, 2), (20, 1)];
+    let packing = shelf_pack(16, 8, &sizes);
+    println!("\npacked {} of {} items, {:.0}% full, unplaced {:?}",
+             sizes.len() - packing.unplaced().len(), sizes.len(), packing.fill_ratio() * 100.0, packing.unplaced());
+    print!("{}", packing);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_hold_ignores_positions() {
+        let big = Rect::new(100, 100, 8, 7);
+        assert!(big.can_hold(&Rect::new(0, 0, 5, 1)));
+        assert!(!big.can_hold(&Rect::new(0, 0, 9, 1)));
+        // same size fits
+        assert!(big.can_hold(&Rect::new(3, 3, 8, 7)));
+    }
+
+    #[test]
+    fn edges_of_the_plane() {
+        let edge = Rect::new(u32::MAX, 0, 1, 1);
+        assert_eq!(edge.right(), u32::MAX);
+        assert!(!edge.intersects(&Rect::new(0, 0, 10, 10)));
+        // a bin as large as the plane
+        let p = shelf_pack(u32::MAX, u32::MAX, &[(u32::MAX, 1), (1, 1)]);
+        assert_eq!(p.placed, [Some(Rect::new(0, 0, u32::MAX, 1)), Some(Rect::new(0, 1, 1, 1))]);
+    }
+
+    #[test]
+    fn intersection_union_overlap() {
+        let a = Rect::new(0, 0, 6, 4);
+        let b = Rect::new(4, 2, 5, 3);
+        assert_eq!(a.intersection(&b), Some(Rect::new(4, 2, 2, 2)));
+        assert_eq!(a.intersection(&b), b.intersection(&a));
+        assert_eq!(a.union(&b), Rect::new(0, 0, 9, 5));
+        assert_eq!(a.overlap_area(&b), 4);
+        // touching sides is not overlapping
+        let c = Rect::new(6, 0, 2, 2);
+        assert!(!a.intersects(&c));
+        assert_eq!(a.overlap_area(&c), 0);
+        assert_eq!(a.union(&c), Rect::new(0, 0, 8, 4));
+    }
+
+    #[test]
+    fn containment() {
+        let outer = Rect::new(1, 1, 10, 10);
+        assert!(outer.contains(&Rect::new(1, 1, 10, 10)));
+        assert!(outer.contains(&Rect::new(5, 5, 6, 6)));
+        assert!(!outer.contains(&Rect::new(5, 5, 7, 6)));
+        assert!(outer.contains_point(1, 10) && !outer.contains_point(11, 1));
+        // an empty rectangle intersects nothing, not even itself
+        let empty = Rect::new(3, 3, 0, 5);
+        assert!(empty.is_empty() && !empty.intersects(&empty));
+    }
+
+    #[test]
+    fn collisions_lists_each_pair_once() {
+        let rects = [Rect::new(0, 0, 2, 2), Rect::new(1, 1, 2, 2), Rect::new(5, 5, 1, 1), Rect::new(0, 1, 5, 1)];
+        assert_eq!(collisions(&rects), vec![(0, 1), (0, 3), (1, 3)]);
+    }
+
+    #[test]
+    fn packing_places_without_overlaps_inside_the_bin() {
+        let sizes = [(4, 3), (6, 2), (3, 3), (5, 4), (2, 1), (8, 2), (3, 2)];
+        let p = shelf_pack(16, 8, &sizes);
+        let placed: Vec<Rect> = p.placed.iter().flatten().copied().collect();
+        assert!(collisions(&placed).is_empty());
+        assert!(placed.iter().all(|r| p.bin.contains(r)));
+        // sizes are kept, in the caller's order
+        for (size, r) in sizes.iter().zip(&p.placed) {
+            if let Some(r) = r {
+                assert_eq!((r.width, r.height), *size);
+            }
+        }
+        // the tallest item opens the first shelf
+        assert_eq!(p.placed[3], Some(Rect::new(0, 0, 5, 4)));
+        assert_eq!(p.unplaced(), Vec::<usize>::new());
+        assert_eq!(p.used_area(), sizes.iter().map(|(w, h)| (w * h) as u64).sum::<u64>());
+    }
+
+    #[test]
+    fn packing_skips_what_does_not_fit() {
+        let p = shelf_pack(5, 3, &[(6, 1), (5, 2), (5, 2), (1, 1)]);
+        // too wide, then a second 5x2 that would go below the bin
+        assert_eq!(p.unplaced(), vec![0, 2]);
+        assert_eq!(p.placed[3], Some(Rect::new(0, 2, 1, 1)));
+        assert_eq!(shelf_pack(0, 0, &[(1, 1)]).fill_ratio(), 0.0);
+    }
+
+    #[test]
+    fn rendering() {
+        let bin = Rect::new(0, 0, 5, 3);
+        let drawing = render(bin, &[Some(Rect::new(0, 0, 2, 2)), None, Some(Rect::new(1, 1, 3, 1)), Some(Rect::new(4, 2, 9, 9))]);
+        assert_eq!(drawing, "AA...\nA#CC.\n....D\n");
+        assert_eq!(shelf_pack(3, 2, &[(2, 1), (1, 2)]).to_string(), "BAA\nB..\n");
+        // only the cells inside the bin are visited, however large the rectangle
+        let huge = Rect::new(0, 0, u32::MAX, u32::MAX);
+        assert_eq!(render(bin, &[Some(huge), Some(Rect::new(7, 7, 1, 1))]), "AAAAA\nAAAAA\nAAAAA\n");
+    }
+}
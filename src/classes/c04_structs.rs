@@ -8,7 +8,10 @@
 // See
 //      https://doc.rust-lang.org/book/ch05-00-structs.html
 
-struct User {
+// (`c04_users` keeps many of these in a `UserRegistry`: the fields stay private,
+//  so everything it needs is offered by the `impl User` below)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct User {
     username: String,
     email: String,
     sign_in_count: u64,
@@ -102,6 +105,54 @@ pub fn retu() -> User{
     return _user5;
 }
 
+// other modules can neither build a `User` nor read its fields,
+// so we hand them a constructor and read-only accessors.
+// Every change returns a NEW `User` made with the struct update syntax:
+// only the field that changes is written, the rest is moved over from `self`
+impl User {
+    /// a fresh, active user that never signed in
+    pub fn new(username: String, email: String) -> User {
+        User { username, email, sign_in_count: 0, active: true }
+    }
+    /// rebuilds a user from all of its fields, e.g. when loading it from a file
+    pub fn from_parts(username: String, email: String, sign_in_count: u64, active: bool) -> User {
+        User { username, email, sign_in_count, active }
+    }
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+    pub fn email(&self) -> &str {
+        &self.email
+    }
+    pub fn sign_in_count(&self) -> u64 {
+        self.sign_in_count
+    }
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+    pub fn with_email(self, email: String) -> User {
+        User { email, ..self }
+    }
+    pub fn with_active(self, active: bool) -> User {
+        User { active, ..self }
+    }
+    // QUIZ: why can't we write `User { sign_in_count: self.sign_in_count + 1, ..self }`
+    // with `&self` instead of `self`?
+
+
+
+
+
+    //
+    // `..self` moves the remaining fields out of `self`, and the `String`s cannot be moved out of a borrow
+    // DNC: error[E0507]: cannot move out of `self.username` which is behind a shared reference
+
+    // the counter stops at u64::MAX instead of overflowing: a CSV file can load any u64
+    pub fn signed_in(self) -> User {
+        User { sign_in_count: self.sign_in_count.saturating_add(1), ..self }
+    }
+}
+
 
 // Oftentimes you want to print out a struct
 // the simplest way is to 'derive' a Trait called 'Debug'
@@ -154,6 +205,7 @@ pub struct Square {
     side: u32
 }
 /// a public struct `Rhombus` with a public `side` field and a private angle
+// (`c10_shapes` turns these structs into shapes sharing a `Shape` trait)
 pub struct Rhombus {
     pub side: u32,
     acute_angle: i32,
@@ -166,6 +218,18 @@ pub fn new_rhombus() -> Rhombus{
 pub fn _new_square() -> Square{
     return Square{ side: 0 };
 }
+// note that both functions happily return shapes with side 0 (and angle 0):
+// a constructor that cannot fail cannot reject nonsense.
+// `c04_builders` builds these through `RhombusBuilder`, whose `build()` returns a `Result`
+impl Rhombus {
+    // `pub(crate)`: usable by the builder in `c04_builders`, invisible outside this crate
+    pub(crate) fn from_parts(side: u32, acute_angle: i32) -> Rhombus {
+        Rhombus { side, acute_angle }
+    }
+    pub fn acute_angle(&self) -> i32 {
+        self.acute_angle
+    }
+}
 // GOTO structshelper file
 // come back
 
@@ -206,6 +270,11 @@ impl Rectangle {
     }
     fn take_ownership(self) {
     }
+    // a method with more parameters: can `self` contain `other`?
+    // (`c04_layout` gives rectangles a position, for intersections, packing and drawing)
+    fn can_hold(&self, other: &Rectangle) -> bool {
+        self.width > other.width && self.height > other.height
+    }
     // QUIZ: are these methods or functions:
     // 1 pub fn test1(&self) ...
     // 2 fn test2(&self, arg: int) ...
@@ -237,6 +306,7 @@ impl Rectangle {
     pub fn new_with_params( width : u32, height : u32) -> Rectangle {
         Rectangle{ width, height }
     }
+    // with many optional parameters this gets out of hand: see the builders in `c04_builders`
 }
 
 pub fn struct_impl(){
@@ -250,6 +320,7 @@ pub fn struct_impl(){
     let p = Rectangle::perimeter(&r);
     println!("The Rectangle is {:?}",r);
     println!("Area: {} and Perimeter: {}", a, p);
+    println!("Can it hold a 5x5 square? {}", r.can_hold(&Rectangle::square(5)));
 }
 
 // END
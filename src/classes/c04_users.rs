@@ -0,0 +1,504 @@
+/// This module keeps the `User`s of `c04_structs` in a registry, the way a small web app would:
+///     validation          a `User` only enters the registry with a sane username and email
+///     indexes             lookups by username AND by email, kept in sync on every change
+///     struct update       changes build a new `User` out of the old one (`User { email, ..old }`)
+///     persistence         saving to and loading from a CSV file, round-tripping exactly
+/// The fields of `User` are private to `c04_structs`, so this module only goes through
+/// the constructor and methods of its `impl User` block.
+/// See
+///         https://doc.rust-lang.org/book/ch05-01-defining-structs.html#creating-instances-from-other-instances-with-struct-update-syntax
+///         https://doc.rust-lang.org/std/collections/struct.BTreeMap.html
+///         https://www.rfc-editor.org/rfc/rfc4180
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::classes::c04_structs::User;
+
+#[derive(Debug)]
+pub enum RegistryError {
+    InvalidUsername(String),
+    InvalidEmail(String),
+    DuplicateUsername(String),
+    DuplicateEmail(String),
+    UnknownUser(String),
+    Inactive(String),
+    Io(io::Error),
+    // `line` is 1-based, as in editors
+    Parse { line: usize, reason: String },
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegistryError::InvalidUsername(u) => write!(f, "invalid username {:?}", u),
+            RegistryError::InvalidEmail(e) => write!(f, "invalid email {:?}", e),
+            RegistryError::DuplicateUsername(u) => write!(f, "username {:?} is already taken", u),
+            RegistryError::DuplicateEmail(e) => write!(f, "email {:?} is already registered", e),
+            RegistryError::UnknownUser(u) => write!(f, "no user named {:?}", u),
+            RegistryError::Inactive(u) => write!(f, "user {:?} is deactivated", u),
+            RegistryError::Io(e) => write!(f, "I/O error: {}", e),
+            RegistryError::Parse { line, reason } => write!(f, "line {}: {}", line, reason),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RegistryError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for RegistryError {
+    fn from(e: io::Error) -> Self {
+        RegistryError::Io(e)
+    }
+}
+
+/* ===== Validation =====
+   ========================= */
+
+/// 3 to 32 ASCII letters, digits, `_`, `-` or `.`, starting with a letter
+pub fn is_valid_username(username: &str) -> bool {
+    let len_ok = (3..=32).contains(&username.len());
+    let first_ok = username.chars().next().is_some_and(|c| c.is_ascii_alphabetic());
+    let rest_ok = username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    len_ok && first_ok && rest_ok
+}
+
+/// a pragmatic subset of the email syntax: `local@domain.tld`
+///     local   ASCII letters, digits and `. _ % + -`, no leading, trailing or double dots
+///     domain  at least two dot-separated labels of letters, digits and `-`
+///             (not at the ends of a label), the last one being 2+ letters
+// the full grammar (RFC 5322) allows quoted strings, comments and IP literals,
+// which no sign-up form wants to accept anyway
+pub fn is_valid_email(email: &str) -> bool {
+    let (local, domain) = match email.split_once('@') {
+        Some(parts) => parts,
+        None => return false,
+    };
+    let local_ok = !local.is_empty()
+        && local.len() <= 64
+        && !local.starts_with('.')
+        && !local.ends_with('.')
+        && !local.contains("..")
+        && local
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '%' | '+' | '-'));
+    let labels: Vec<&str> = domain.split('.').collect();
+    let labels_ok = labels.len() >= 2
+        && labels.iter().all(|l| {
+            !l.is_empty()
+                && !l.starts_with('-')
+                && !l.ends_with('-')
+                && l.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    // `labels` is never empty: `split` yields at least one piece
+    let tld = labels[labels.len() - 1];
+    let tld_ok = tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic());
+    local_ok && labels_ok && tld_ok && email.len() <= 254
+}
+
+// emails are unique regardless of case: `Ann@Example.com` and `ann@example.com` are one mailbox
+// (strictly, only the domain is case-insensitive, but no real provider disagrees)
+fn email_key(email: &str) -> String {
+    email.to_ascii_lowercase()
+}
+
+/* ===== Registry =====
+   ========================= */
+
+/// All users, by username, plus an index from (lowercased) email to username.
+// A `BTreeMap` keeps users sorted by name, so iteration and saving are deterministic:
+// saving the same registry twice produces the same bytes
+#[derive(Debug, Default)]
+pub struct UserRegistry {
+    users: BTreeMap<String, User>,
+    by_email: HashMap<String, String>,
+}
+
+impl UserRegistry {
+    pub fn new() -> UserRegistry {
+        UserRegistry::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.users.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty()
+    }
+
+    /// users in username order
+    pub fn iter(&self) -> impl Iterator<Item = &User> {
+        self.users.values()
+    }
+
+    /// validates and adds a fresh, active user with no sign-ins
+    pub fn create(&mut self, username: &str, email: &str) -> Result<&User, RegistryError> {
+        self.insert(User::new(username.to_string(), email.to_string()))
+    }
+
+    // the single door into the registry: `create` and `from_csv` both go through here,
+    // so a loaded file is held to the same rules as a sign-up form
+    fn insert(&mut self, user: User) -> Result<&User, RegistryError> {
+        if !is_valid_username(user.username()) {
+            return Err(RegistryError::InvalidUsername(user.username().to_string()));
+        }
+        if !is_valid_email(user.email()) {
+            return Err(RegistryError::InvalidEmail(user.email().to_string()));
+        }
+        if self.users.contains_key(user.username()) {
+            return Err(RegistryError::DuplicateUsername(user.username().to_string()));
+        }
+        let key = email_key(user.email());
+        if self.by_email.contains_key(&key) {
+            return Err(RegistryError::DuplicateEmail(user.email().to_string()));
+        }
+        let name = user.username().to_string();
+        self.by_email.insert(key, name.clone());
+        // `entry(..).or_insert(..)` hands back a reference to the value just stored
+        Ok(self.users.entry(name).or_insert(user))
+    }
+
+    pub fn find_by_username(&self, username: &str) -> Option<&User> {
+        self.users.get(username)
+    }
+
+    pub fn find_by_email(&self, email: &str) -> Option<&User> {
+        self.by_email
+            .get(&email_key(email))
+            .and_then(|name| self.users.get(name))
+    }
+
+    // Changing a user means replacing it: the `User` is taken out of the map,
+    // turned into a new one by one of the struct-update methods, and put back.
+    // `f` must not change the username, which is the key of the map
+    fn replace_with(
+        &mut self,
+        username: &str,
+        f: impl FnOnce(User) -> User,
+    ) -> Result<&User, RegistryError> {
+        let old = self
+            .users
+            .remove(username)
+            .ok_or_else(|| RegistryError::UnknownUser(username.to_string()))?;
+        let new = f(old);
+        debug_assert_eq!(new.username(), username);
+        Ok(self.users.entry(username.to_string()).or_insert(new))
+    }
+
+    /// counts a sign-in; deactivated users cannot sign in
+    pub fn sign_in(&mut self, username: &str) -> Result<u64, RegistryError> {
+        match self.find_by_username(username) {
+            None => return Err(RegistryError::UnknownUser(username.to_string())),
+            Some(u) if !u.is_active() => return Err(RegistryError::Inactive(username.to_string())),
+            Some(_) => {}
+        }
+        Ok(self.replace_with(username, User::signed_in)?.sign_in_count())
+    }
+
+    pub fn deactivate(&mut self, username: &str) -> Result<&User, RegistryError> {
+        self.replace_with(username, |u| u.with_active(false))
+    }
+
+    pub fn reactivate(&mut self, username: &str) -> Result<&User, RegistryError> {
+        self.replace_with(username, |u| u.with_active(true))
+    }
+
+    /// changes the email of a user, keeping the email index in sync
+    pub fn update_email(&mut self, username: &str, email: &str) -> Result<&User, RegistryError> {
+        let old_key = match self.find_by_username(username) {
+            Some(u) => email_key(u.email()),
+            None => return Err(RegistryError::UnknownUser(username.to_string())),
+        };
+        if !is_valid_email(email) {
+            return Err(RegistryError::InvalidEmail(email.to_string()));
+        }
+        let new_key = email_key(email);
+        // re-registering one's own address (maybe with a different case) is fine
+        if new_key != old_key && self.by_email.contains_key(&new_key) {
+            return Err(RegistryError::DuplicateEmail(email.to_string()));
+        }
+        self.by_email.remove(&old_key);
+        self.by_email.insert(new_key, username.to_string());
+        self.replace_with(username, |u| u.with_email(email.to_string()))
+    }
+
+    /* ===== Persistence =====
+       ========================= */
+
+    const HEADER: &'static str = "username,email,sign_in_count,active";
+
+    /// one header line, then one `username,email,sign_in_count,active` line per user
+    // validation forbids commas, quotes and newlines in usernames and emails,
+    // so no field ever needs CSV quoting
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from(Self::HEADER);
+        out.push('\n');
+        for u in self.users.values() {
+            out.push_str(&format!(
+                "{},{},{},{}\n",
+                u.username(),
+                u.email(),
+                u.sign_in_count(),
+                u.is_active()
+            ));
+        }
+        out
+    }
+
+    pub fn from_csv(text: &str) -> Result<UserRegistry, RegistryError> {
+        let mut lines = text.lines().enumerate().map(|(i, l)| (i + 1, l));
+        match lines.next() {
+            Some((_, header)) if header == Self::HEADER => {}
+            _ => {
+                return Err(RegistryError::Parse {
+                    line: 1,
+                    reason: format!("expected header {:?}", Self::HEADER),
+                })
+            }
+        }
+        let mut registry = UserRegistry::new();
+        for (line, row) in lines {
+            let parse_err = |reason: String| RegistryError::Parse { line, reason };
+            let fields: Vec<&str> = row.split(',').collect();
+            let (username, email, count, active) = match fields[..] {
+                [u, e, c, a] => (u, e, c, a),
+                _ => return Err(parse_err(format!("expected 4 fields, found {}", fields.len()))),
+            };
+            let count: u64 = count
+                .parse()
+                .map_err(|e| parse_err(format!("bad sign_in_count {:?}: {}", count, e)))?;
+            let active: bool = active
+                .parse()
+                .map_err(|_| parse_err(format!("bad active flag {:?}", active)))?;
+            let user = User::from_parts(username.to_string(), email.to_string(), count, active);
+            // validation and duplicate errors are reported with their line number
+            registry.insert(user).map_err(|e| parse_err(e.to_string()))?;
+        }
+        Ok(registry)
+    }
+
+    /// writes the registry to `path`, replacing the file in one step
+    // writing a temporary file and renaming it over the old one means a crash halfway
+    // leaves either the old file or the new one, never half of each
+    pub fn save(&self, path: &Path) -> Result<(), RegistryError> {
+        let tmp = path.with_extension("csv.tmp");
+        fs::write(&tmp, self.to_csv())?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<UserRegistry, RegistryError> {
+        UserRegistry::from_csv(&fs::read_to_string(path)?)
+    }
+}
+
+pub fn usersexample() -> Result<(), RegistryError> {
+    let mut registry = UserRegistry::new();
+    registry.create("alice", "alice@example.com")?;
+    registry.create("bob", "bob@example.org")?;
+    if let Err(e) = registry.create("bob", "other@example.org") {
+        println!("rejected: {}", e);
+    }
+    if let Err(e) = registry.create("carol", "carol@example") {
+        println!("rejected: {}", e);
+    }
+
+    registry.sign_in("alice")?;
+    registry.sign_in("alice")?;
+    registry.update_email("bob", "robert@example.org")?;
+    registry.deactivate("bob")?;
+    if let Err(e) = registry.sign_in("bob") {
+        println!("rejected: {}", e);
+    }
+    println!("{:?}", registry.find_by_email("ROBERT@example.org"));
+
+    let path = std::env::temp_dir().join("ap-class-users.csv");
+    registry.save(&path)?;
+    print!("{}", fs::read_to_string(&path)?);
+    let loaded = UserRegistry::load(&path)?;
+    println!("round trip ok: {}", loaded.to_csv() == registry.to_csv());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> UserRegistry {
+        let mut r = UserRegistry::new();
+        r.create("alice", "alice@example.com").unwrap();
+        r.create("bob", "Bob.Smith+news@mail.example.org").unwrap();
+        r.create("carol_99", "carol@example.io").unwrap();
+        r.sign_in("alice").unwrap();
+        r.sign_in("alice").unwrap();
+        r.sign_in("carol_99").unwrap();
+        r.deactivate("bob").unwrap();
+        r
+    }
+
+    #[test]
+    fn usernames() {
+        for ok in ["abc", "alice", "a.b-c_d", "Zed", "x23"] {
+            assert!(is_valid_username(ok), "{}", ok);
+        }
+        for bad in ["", "ab", "9lives", "_x1", "al ice", "al,ice", "àlice", &"a".repeat(33)] {
+            assert!(!is_valid_username(bad), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn emails() {
+        for ok in ["a@b.co", "first.last@example.com", "x+tag@sub.domain.org", "a_b%c-d@x-y.io"] {
+            assert!(is_valid_email(ok), "{}", ok);
+        }
+        for bad in [
+            "", "plain", "@example.com", "a@", "a@b", "a@@b.com", "a@b@c.com", ".a@b.com",
+            "a.@b.com", "a..b@c.com", "a@-b.com", "a@b-.com", "a@b..com", "a@b.c", "a@b.c0m",
+            "a b@c.com", "a,b@c.com", "\"a\"@b.com",
+        ] {
+            assert!(!is_valid_email(bad), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn create_and_find() {
+        let r = sample();
+        assert_eq!(r.len(), 3);
+        let alice = r.find_by_username("alice").unwrap();
+        assert_eq!(alice.email(), "alice@example.com");
+        assert_eq!(alice.sign_in_count(), 2);
+        assert!(alice.is_active());
+        assert_eq!(r.find_by_email("ALICE@Example.COM").unwrap().username(), "alice");
+        assert!(r.find_by_username("Alice").is_none());
+        assert!(r.find_by_email("nobody@example.com").is_none());
+        let names: Vec<&str> = r.iter().map(|u| u.username()).collect();
+        assert_eq!(names, ["alice", "bob", "carol_99"]);
+    }
+
+    #[test]
+    fn create_rejects_invalid_and_duplicates() {
+        let mut r = sample();
+        assert!(matches!(r.create("x", "x@example.com"), Err(RegistryError::InvalidUsername(_))));
+        assert!(matches!(r.create("dave", "dave"), Err(RegistryError::InvalidEmail(_))));
+        assert!(matches!(
+            r.create("alice", "alice2@example.com"),
+            Err(RegistryError::DuplicateUsername(_))
+        ));
+        assert!(matches!(
+            r.create("alice2", "Alice@Example.com"),
+            Err(RegistryError::DuplicateEmail(_))
+        ));
+        // failed attempts leave no trace
+        assert_eq!(r.len(), 3);
+        assert!(r.find_by_email("alice2@example.com").is_none());
+    }
+
+    #[test]
+    fn sign_in_counts_and_respects_deactivation() {
+        let mut r = sample();
+        assert_eq!(r.sign_in("alice").unwrap(), 3);
+        assert!(matches!(r.sign_in("bob"), Err(RegistryError::Inactive(_))));
+        assert!(matches!(r.sign_in("nobody"), Err(RegistryError::UnknownUser(_))));
+        assert_eq!(r.find_by_username("bob").unwrap().sign_in_count(), 0);
+        r.reactivate("bob").unwrap();
+        assert_eq!(r.sign_in("bob").unwrap(), 1);
+        assert!(matches!(r.deactivate("nobody"), Err(RegistryError::UnknownUser(_))));
+        // a counter loaded at its maximum stays there
+        let mut r = UserRegistry::from_csv(&format!(
+            "username,email,sign_in_count,active\ncarol,carol@example.com,{},true\n",
+            u64::MAX
+        ))
+        .unwrap();
+        assert_eq!(r.sign_in("carol").unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn update_email_keeps_index_in_sync() {
+        let mut r = sample();
+        let u = r.update_email("alice", "alice@new.example").unwrap();
+        // only the email changed: the other fields came over with `..old`
+        assert_eq!(u.sign_in_count(), 2);
+        assert!(u.is_active());
+        assert!(r.find_by_email("alice@example.com").is_none());
+        assert_eq!(r.find_by_email("alice@new.example").unwrap().username(), "alice");
+        // the old address is free again
+        r.create("alice2", "alice@example.com").unwrap();
+        // a change of case of one's own address is allowed, someone else's is not
+        r.update_email("alice", "ALICE@new.example").unwrap();
+        assert!(matches!(
+            r.update_email("alice", "carol@example.io"),
+            Err(RegistryError::DuplicateEmail(_))
+        ));
+        assert!(matches!(r.update_email("alice", "nope"), Err(RegistryError::InvalidEmail(_))));
+        assert!(matches!(
+            r.update_email("nobody", "n@example.com"),
+            Err(RegistryError::UnknownUser(_))
+        ));
+        assert_eq!(r.find_by_username("alice").unwrap().email(), "ALICE@new.example");
+    }
+
+    #[test]
+    fn csv_round_trip_is_exact() {
+        let r = sample();
+        let csv = r.to_csv();
+        assert_eq!(
+            csv,
+            "username,email,sign_in_count,active\n\
+             alice,alice@example.com,2,true\n\
+             bob,Bob.Smith+news@mail.example.org,0,false\n\
+             carol_99,carol@example.io,1,true\n"
+        );
+        let back = UserRegistry::from_csv(&csv).unwrap();
+        assert_eq!(back.to_csv(), csv);
+        let a: Vec<&User> = r.iter().collect();
+        let b: Vec<&User> = back.iter().collect();
+        assert_eq!(a, b);
+        assert_eq!(back.find_by_email("bob.smith+news@mail.example.org").unwrap().username(), "bob");
+        assert!(UserRegistry::from_csv(&UserRegistry::new().to_csv()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn save_and_load() {
+        // only this test touches the disk: a file named after the test and the process is enough
+        let path = std::env::temp_dir().join(format!("ap-class-users-save-{}.csv", std::process::id()));
+        let r = sample();
+        r.save(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        let back = UserRegistry::load(&path).unwrap();
+        back.save(&path).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+        assert!(!path.with_extension("csv.tmp").exists());
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(UserRegistry::load(&path), Err(RegistryError::Io(_))));
+    }
+
+    #[test]
+    fn from_csv_reports_bad_lines() {
+        let header = "username,email,sign_in_count,active\n";
+        let line_of = |text: &str| match UserRegistry::from_csv(text) {
+            Err(RegistryError::Parse { line, .. }) => line,
+            other => panic!("expected a parse error, got {:?}", other),
+        };
+        assert_eq!(line_of(""), 1);
+        assert_eq!(line_of("name,mail\n"), 1);
+        assert_eq!(line_of(&format!("{}alice,a@b.co,1\n", header)), 2);
+        assert_eq!(line_of(&format!("{}alice,a@b.co,x,true\n", header)), 2);
+        assert_eq!(line_of(&format!("{}alice,a@b.co,-1,true\n", header)), 2);
+        assert_eq!(line_of(&format!("{}alice,a@b.co,1,yes\n", header)), 2);
+        assert_eq!(line_of(&format!("{}alice,a@b.co,1,true\nbob,bad,0,true\n", header)), 3);
+        assert_eq!(line_of(&format!("{}alice,a@b.co,1,true\nalice,c@d.co,0,true\n", header)), 3);
+        assert_eq!(line_of(&format!("{}alice,a@b.co,1,true\nbob,A@B.co,0,true\n", header)), 3);
+    }
+}
@@ -86,6 +86,9 @@ impl AveragedCollection {
 // As long as the signatures of the `add`, `remove`,
 // and `average` public methods stay the same,
 // code using `AveragedCollection` wouldn’t need to change.
+// (`c10_stats::Stats` does exactly this: same idea, but with min, max, median, mode,
+//  variance and percentiles, all kept up to date without recomputing anything;
+//  `c10_averagers` averages only the recent values instead of all of them)
 // If we made `list` public instead, this wouldn’t
 // necessarily be the case:
 // `HashMap` and `Vec` have different methods for adding
@@ -155,6 +158,8 @@ impl AveragedCollection {
 // Even if we define a generic type, the generic type
 //  can be substituted with **one** concrete type at a time.
 
+use crate::classes::c10_show::Doc;
+
 fn wrong_Vecs() {
     // Vec<T>
     let v1 = vec![1u32, 1u32];
@@ -167,8 +172,16 @@ fn wrong_Vecs() {
 //  that implement the trait.
 // so we can declare a Trait: Show and use it
 // to populate a vector of Show s
-trait Show {
+// (it is `pub` so that `c10_show` can implement it for many more types
+//  and build a pretty printer on top of it)
+pub trait Show {
     fn show(&self) -> String;
+    // a default method: every implementor gets it for free, and may override it.
+    // `Doc` is the structure the pretty printer of `c10_show` lays out;
+    // by default a value is just one unbreakable piece of text
+    fn doc(&self) -> Doc {
+        Doc::text(self.show())
+    }
 }
 // we define this trait for 2 known types,
 // note that we're defining Traits for non-struct types!
@@ -289,6 +302,8 @@ fn quack_trait<Q> (q: &Q)
 // Instead, quack_ref uses Trait Objects,
 // and it'll look up into the parameter Trait object the
 // v-table where to do the method dispatch for quack();
+// (how much that costs is measured by `c10_plugins::benchmark`:
+//  `cargo run --release -- quack --bench`)
 
 
 /* Problems with Inheritance: how to subclass?
@@ -304,6 +319,7 @@ fn quack_trait<Q> (q: &Q)
     common problem!
 
     You could, instead, compose these different aspects, with Traits, into the Vehicle struct!
+    (`c10_ecs` takes this all the way: entities made only of components, no hierarchy at all)
     Generally in Rust there is trait inheritance, which is done by
         :  and  +
     in trait signatures
@@ -323,21 +339,21 @@ fn quack_trait<Q> (q: &Q)
 
 
 // recall the trait Show from above, this is a new trait
-trait Location {
+pub trait Location {
     fn location(&self) -> String;
 }
 // Recall the : notation from `trait CompSciStudent: Programmer + Student`.
-trait ShowTell: Show + Location {}
+pub trait ShowTell: Show + Location {}
 // The last trait simply combines our two distinct traits into one, although it could specify other methods.
 
 #[derive(Debug)]
-struct Foo {
+pub struct Foo {
     name: String,
     location: String
 }
 // some methods for allocating this struct
 impl Foo {
-    fn new(name: &str, location: &str) -> Foo {
+    pub fn new(name: &str, location: &str) -> Foo {
         Foo{
             name: name.to_string(),
             location: location.to_string()
@@ -365,7 +381,7 @@ fn transferShow(x: &dyn Show) -> &dyn Show {
 fn transferLocation(x: &dyn Location) -> &dyn Location {
     x
 }
-fn transferShowTell(x: &dyn ShowTell) -> &dyn ShowTell {
+pub(crate) fn transferShowTell(x: &dyn ShowTell) -> &dyn ShowTell {
     x
 }
 
@@ -379,6 +395,7 @@ pub fn example_multiple_traits(){
     let ff2 = transferLocation(&f);
     let ff3 = transferShowTell(&f);
     // we can effectively treat, and use `f` as something of all the other Trait types!
+    // (and `ff3` can become a `&dyn Show` or `&dyn Location` itself: see `c10_casts`)
     // look at the autocomplete suggestion for each of ff1, ff2, ff3
     println!(" Foo's: {}, Show's: {}, Location's {}, ShowTell's: {}", f.name, ff1.show(), ff2.location(), ff3.show() );
 }
\ No newline at end of file
@@ -0,0 +1,587 @@
+/// This module puts several ways of averaging behind one trait, `Averager`:
+///     CumulativeAverage   everything ever added, like `c10_OOP::AveragedCollection`, in O(1)
+///     WindowAverage       the last N values
+///     TimeWindowAverage   the values of the last few seconds, grouped into time buckets
+///     Ewma                exponentially weighted: recent values count more
+/// All of them can be swapped at run time through a `Box<dyn Averager>`,
+/// and `averagecommand` feeds them numbers from stdin (`cargo run -- average window:5 < numbers.txt`).
+/// Whatever arrives on stdin, the encapsulated state stays consistent:
+/// bad input is rejected at the door, by `add`, and never reaches the fields.
+/// See
+///         https://doc.rust-lang.org/book/ch17-02-trait-objects.html
+///         https://en.wikipedia.org/wiki/Moving_average#Exponential_moving_average
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AveragerError {
+    NotFinite(f64),
+    InvalidAlpha(f64),
+    ZeroCapacity,
+    ZeroDuration,
+    BadSpec(String),
+}
+
+impl fmt::Display for AveragerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AveragerError::NotFinite(v) => write!(f, "{} is not a finite number", v),
+            AveragerError::InvalidAlpha(a) => write!(f, "alpha must be in (0, 1], got {}", a),
+            AveragerError::ZeroCapacity => write!(f, "a window must hold at least one value"),
+            AveragerError::ZeroDuration => write!(f, "a time window must have a non-zero duration"),
+            AveragerError::BadSpec(s) => write!(
+                f,
+                "unknown averager {:?} (expected all, window:N, ewma:ALPHA or time:MILLIS:BUCKETS)",
+                s
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AveragerError {}
+
+/// Something that is fed numbers and can tell their average.
+pub trait Averager {
+    /// rejects NaN and infinities, which would poison every average after them
+    fn add(&mut self, value: f64) -> Result<(), AveragerError>;
+    /// `None` until there is something to average
+    fn average(&self) -> Option<f64>;
+    /// a short description, e.g. `window:5`
+    fn name(&self) -> String;
+}
+
+// the single check every `add` starts with
+fn finite(value: f64) -> Result<f64, AveragerError> {
+    if value.is_finite() { Ok(value) } else { Err(AveragerError::NotFinite(value)) }
+}
+
+// moves `mean` towards `value` by `weight` (between 0 and 1): `mean + (value - mean) * weight`.
+// Written like this, with finite inputs the result stays finite, because it stays between them;
+// a plain sum of the values, or `value - mean`, can overflow, e.g. `1e308 + 1e308` is infinite
+fn towards(mean: f64, value: f64, weight: f64) -> f64 {
+    mean + (value * weight - mean * weight)
+}
+
+/* ===== Cumulative =====
+   ========================= */
+
+/// the average of everything ever added; unlike `AveragedCollection`,
+/// it keeps no list and recomputes nothing: a running mean and a count are enough
+#[derive(Debug, Clone, Default)]
+pub struct CumulativeAverage {
+    mean: f64,
+    count: u64,
+}
+
+impl CumulativeAverage {
+    pub fn new() -> Self {
+        CumulativeAverage::default()
+    }
+}
+
+impl Averager for CumulativeAverage {
+    fn add(&mut self, value: f64) -> Result<(), AveragerError> {
+        let value = finite(value)?;
+        self.count += 1;
+        self.mean = towards(self.mean, value, 1.0 / self.count as f64);
+        Ok(())
+    }
+    fn average(&self) -> Option<f64> {
+        if self.count == 0 { None } else { Some(self.mean) }
+    }
+    fn name(&self) -> String {
+        String::from("all")
+    }
+}
+
+/* ===== Sliding window =====
+   ========================= */
+
+/// the average of the last `capacity` values
+#[derive(Debug, Clone)]
+pub struct WindowAverage {
+    values: VecDeque<f64>,
+    capacity: usize,
+    // kept up to date on every add, so `average` is O(1)
+    mean: f64,
+    // updating a float again and again leaves rounding errors behind:
+    // every `capacity` adds, the mean is recomputed from the values to wipe them out
+    adds_since_recompute: usize,
+}
+
+impl WindowAverage {
+    pub fn new(capacity: usize) -> Result<Self, AveragerError> {
+        if capacity == 0 {
+            return Err(AveragerError::ZeroCapacity);
+        }
+        Ok(WindowAverage {
+            values: VecDeque::with_capacity(capacity),
+            capacity,
+            mean: 0.0,
+            adds_since_recompute: 0,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+}
+
+impl Averager for WindowAverage {
+    fn add(&mut self, value: f64) -> Result<(), AveragerError> {
+        let value = finite(value)?;
+        if self.values.len() == self.capacity {
+            // `capacity` > 0, so a full window is never empty
+            let old = self.values.pop_front().unwrap();
+            let n = self.capacity as f64;
+            // one value replaces another: the mean moves by their difference over n
+            self.mean += value / n - old / n;
+        } else {
+            self.mean = towards(self.mean, value, 1.0 / (self.values.len() + 1) as f64);
+        }
+        self.values.push_back(value);
+        self.adds_since_recompute += 1;
+        if self.adds_since_recompute == self.capacity {
+            // dividing first keeps every term, and so their sum, within range
+            let n = self.values.len() as f64;
+            self.mean = self.values.iter().map(|v| v / n).sum();
+            self.adds_since_recompute = 0;
+        }
+        Ok(())
+    }
+    fn average(&self) -> Option<f64> {
+        if self.values.is_empty() { None } else { Some(self.mean) }
+    }
+    fn name(&self) -> String {
+        format!("window:{}", self.capacity)
+    }
+}
+
+/* ===== Time window =====
+   ========================= */
+
+/// Where `TimeWindowAverage` reads the time from: the real clock when running,
+/// a clock moved by hand in tests.
+pub trait Clock {
+    /// time elapsed since some fixed starting point
+    fn now(&self) -> Duration;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        SystemClock { start: Instant::now() }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// a clock that only moves when told to; clones share the same time (see `c11_cells`)
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock(Rc<Cell<Duration>>);
+
+impl ManualClock {
+    pub fn new() -> Self {
+        ManualClock::default()
+    }
+    pub fn advance(&self, by: Duration) {
+        self.0.set(self.0.get() + by);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.0.get()
+    }
+}
+
+// the mean and count of the values that arrived during one bucket of time
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    index: u64,
+    mean: f64,
+    count: u64,
+}
+
+/// the average of the values added during the last `buckets * bucket_width`.
+// Storing every value with its time would cost memory proportional to the input rate;
+// grouping them into a fixed number of buckets bounds it, at the price of precision:
+// a whole bucket leaves the window at once
+#[derive(Debug, Clone)]
+pub struct TimeWindowAverage<C: Clock> {
+    clock: C,
+    bucket_width: Duration,
+    buckets: u64,
+    ring: VecDeque<Bucket>,
+}
+
+impl<C: Clock> TimeWindowAverage<C> {
+    pub fn new(clock: C, bucket_width: Duration, buckets: u64) -> Result<Self, AveragerError> {
+        if bucket_width.is_zero() || buckets == 0 {
+            return Err(AveragerError::ZeroDuration);
+        }
+        Ok(TimeWindowAverage { clock, bucket_width, buckets, ring: VecDeque::new() })
+    }
+
+    fn current_index(&self) -> u64 {
+        (self.clock.now().as_nanos() / self.bucket_width.as_nanos()) as u64
+    }
+
+    // whether bucket `index` has left the window at bucket `now`;
+    // not `index + buckets <= now`: that overflows when `buckets` is huge, e.g. `time:1:18446744073709551615`
+    fn expired(&self, index: u64, now: u64) -> bool {
+        now.saturating_sub(index) >= self.buckets
+    }
+
+    // the buckets that are still inside the window at bucket `now`
+    fn live(&self, now: u64) -> impl Iterator<Item = &Bucket> {
+        self.ring.iter().filter(move |b| !self.expired(b.index, now))
+    }
+}
+
+impl<C: Clock> Averager for TimeWindowAverage<C> {
+    fn add(&mut self, value: f64) -> Result<(), AveragerError> {
+        let value = finite(value)?;
+        let now = self.current_index();
+        // expired buckets are dropped here, so the ring never holds more than `buckets` of them
+        while self.ring.front().is_some_and(|b| self.expired(b.index, now)) {
+            self.ring.pop_front();
+        }
+        match self.ring.back_mut() {
+            Some(b) if b.index == now => {
+                b.count += 1;
+                b.mean = towards(b.mean, value, 1.0 / b.count as f64);
+            }
+            _ => self.ring.push_back(Bucket { index: now, mean: value, count: 1 }),
+        }
+        Ok(())
+    }
+    // `average` takes `&self`, so it cannot drop expired buckets: it skips them instead
+    fn average(&self) -> Option<f64> {
+        let (mean, count) = self.live(self.current_index()).fold((0.0, 0), |(m, c), b| {
+            // each bucket weighs as much as the values in it
+            (towards(m, b.mean, b.count as f64 / (c + b.count) as f64), c + b.count)
+        });
+        if count == 0 { None } else { Some(mean) }
+    }
+    fn name(&self) -> String {
+        format!("time:{}:{}", self.bucket_width.as_millis(), self.buckets)
+    }
+}
+
+/* ===== EWMA =====
+   ========================= */
+
+/// exponentially weighted moving average: `avg = alpha * value + (1 - alpha) * avg`.
+/// With alpha close to 1 it follows the input closely, close to 0 it smooths a lot.
+#[derive(Debug, Clone)]
+pub struct Ewma {
+    alpha: f64,
+    current: Option<f64>,
+}
+
+impl Ewma {
+    pub fn new(alpha: f64) -> Result<Self, AveragerError> {
+        // written so that NaN fails too: every comparison with NaN is false
+        if !(alpha > 0.0 && alpha <= 1.0) {
+            return Err(AveragerError::InvalidAlpha(alpha));
+        }
+        Ok(Ewma { alpha, current: None })
+    }
+}
+
+impl Averager for Ewma {
+    fn add(&mut self, value: f64) -> Result<(), AveragerError> {
+        let value = finite(value)?;
+        self.current = Some(match self.current {
+            // the first value has nothing to be weighted against
+            None => value,
+            Some(avg) => self.alpha * value + (1.0 - self.alpha) * avg,
+        });
+        Ok(())
+    }
+    fn average(&self) -> Option<f64> {
+        self.current
+    }
+    fn name(&self) -> String {
+        format!("ewma:{}", self.alpha)
+    }
+}
+
+/* ===== Choosing at run time =====
+   ========================= */
+
+/// builds an averager from a description:
+/// `all`, `window:N`, `ewma:ALPHA` or `time:MILLIS:BUCKETS`
+// the caller only sees `dyn Averager`: which struct is behind it is decided here, at run time
+pub fn make_averager(spec: &str) -> Result<Box<dyn Averager>, AveragerError> {
+    let bad = || AveragerError::BadSpec(spec.to_string());
+    let parts: Vec<&str> = spec.split(':').collect();
+    Ok(match parts[..] {
+        ["all"] => Box::new(CumulativeAverage::new()),
+        ["window", n] => Box::new(WindowAverage::new(n.parse().map_err(|_| bad())?)?),
+        ["ewma", alpha] => Box::new(Ewma::new(alpha.parse().map_err(|_| bad())?)?),
+        ["time", millis, buckets] => Box::new(TimeWindowAverage::new(
+            SystemClock::new(),
+            Duration::from_millis(millis.parse().map_err(|_| bad())?),
+            buckets.parse().map_err(|_| bad())?,
+        )?),
+        _ => return Err(bad()),
+    })
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FeedReport {
+    pub accepted: usize,
+    pub rejected: usize,
+}
+
+impl fmt::Display for FeedReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} values averaged, {} rejected", self.accepted, self.rejected)
+    }
+}
+
+/// Reads whitespace-separated numbers from `input` and feeds each to every averager,
+/// writing one line per value with all the averages. Words that are not numbers,
+/// and numbers that are not finite, are reported on `errors` and skipped.
+pub fn feed(
+    averagers: &mut [Box<dyn Averager>],
+    input: impl BufRead,
+    mut out: impl Write,
+    mut errors: impl Write,
+) -> io::Result<FeedReport> {
+    let mut report = FeedReport::default();
+    let header: Vec<String> = averagers.iter().map(|a| format!("{:>12}", a.name())).collect();
+    writeln!(out, "{:>12}{}", "value", header.concat())?;
+    for (n, line) in input.lines().enumerate() {
+        let line = line?;
+        for word in line.split_whitespace() {
+            let value = match word.parse::<f64>() {
+                Ok(v) => v,
+                Err(_) => {
+                    writeln!(errors, "line {}: {:?} is not a number, skipped", n + 1, word)?;
+                    report.rejected += 1;
+                    continue;
+                }
+            };
+            // checked once, up front: if one averager took the value and the next refused it,
+            // they would be averaging different inputs from then on
+            if let Err(e) = finite(value) {
+                writeln!(errors, "line {}: {}, skipped", n + 1, e)?;
+                report.rejected += 1;
+                continue;
+            }
+            for a in averagers.iter_mut() {
+                if let Err(e) = a.add(value) {
+                    writeln!(errors, "line {}: {}: {}", n + 1, a.name(), e)?;
+                }
+            }
+            report.accepted += 1;
+            let row: Vec<String> = averagers
+                .iter()
+                .map(|a| match a.average() {
+                    Some(avg) => format!("{:>12.3}", avg),
+                    None => format!("{:>12}", "-"),
+                })
+                .collect();
+            writeln!(out, "{:>12}{}", value, row.concat())?;
+        }
+    }
+    Ok(report)
+}
+
+/// the `average` subcommand: `average [SPEC...]`, reading numbers from stdin
+pub fn averagecommand(args: &[String]) -> Result<FeedReport, String> {
+    let specs: Vec<&str> = if args.is_empty() {
+        vec!["all", "window:5", "ewma:0.5"]
+    } else {
+        args.iter().map(|s| s.as_str()).collect()
+    };
+    let mut averagers = specs
+        .iter()
+        .map(|s| make_averager(s))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("usage: average [all | window:N | ewma:ALPHA | time:MILLIS:BUCKETS]...\n{}", e))?;
+    feed(&mut averagers, io::stdin().lock(), io::stdout().lock(), io::stderr().lock())
+        .map_err(|e| format!("I/O error: {}", e))
+}
+
+pub fn averagersexample() {
+    let mut averagers: Vec<Box<dyn Averager>> = vec![
+        Box::new(CumulativeAverage::new()),
+        Box::new(WindowAverage::new(3).unwrap()),
+        Box::new(Ewma::new(0.5).unwrap()),
+    ];
+    let input = "10 10 10 40\n40 oops 40\nNaN inf 10\n";
+    match feed(&mut averagers, input.as_bytes(), io::stdout(), io::stdout()) {
+        Ok(report) => println!("{}", report),
+        Err(e) => println!("Error: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed_all(a: &mut dyn Averager, values: &[f64]) {
+        for &v in values {
+            a.add(v).unwrap();
+        }
+    }
+
+    #[test]
+    fn cumulative() {
+        let mut a = CumulativeAverage::new();
+        assert_eq!(a.average(), None);
+        feed_all(&mut a, &[1.0, 2.0, 3.0, 6.0]);
+        assert_eq!(a.average(), Some(3.0));
+    }
+
+    #[test]
+    fn window_keeps_last_n() {
+        assert_eq!(WindowAverage::new(0).err(), Some(AveragerError::ZeroCapacity));
+        let mut w = WindowAverage::new(3).unwrap();
+        assert_eq!(w.average(), None);
+        feed_all(&mut w, &[1.0, 2.0]);
+        assert_eq!(w.average(), Some(1.5));
+        feed_all(&mut w, &[3.0, 4.0, 5.0]);
+        assert_eq!(w.len(), 3);
+        assert_eq!(w.average(), Some(4.0));
+    }
+
+    #[test]
+    fn window_mean_does_not_drift() {
+        // huge and tiny values together: a running mean alone would keep the rounding errors forever
+        let mut w = WindowAverage::new(4).unwrap();
+        for i in 0..10_000 {
+            let v = if i % 2 == 0 { 1e16 } else { 0.1 };
+            w.add(v).unwrap();
+        }
+        // 10_000 is a multiple of the capacity: the mean is recomputed right after the 4.0
+        feed_all(&mut w, &[1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(w.average(), Some(2.5));
+    }
+
+    #[test]
+    fn time_window_expires_buckets() {
+        let clock = ManualClock::new();
+        let second = Duration::from_secs(1);
+        // a window of 3 buckets of one second each
+        let mut t = TimeWindowAverage::new(clock.clone(), second, 3).unwrap();
+        assert_eq!(t.name(), "time:1000:3");
+        feed_all(&mut t, &[10.0, 20.0]);
+        clock.advance(second);
+        feed_all(&mut t, &[30.0]);
+        assert_eq!(t.average(), Some(20.0));
+        clock.advance(second * 2);
+        // the first bucket is out, the second one is still in
+        assert_eq!(t.average(), Some(30.0));
+        clock.advance(second);
+        assert_eq!(t.average(), None);
+        feed_all(&mut t, &[5.0]);
+        assert_eq!(t.average(), Some(5.0));
+        assert_eq!(t.ring.len(), 1);
+        assert!(TimeWindowAverage::new(clock.clone(), Duration::ZERO, 3).is_err());
+        assert!(TimeWindowAverage::new(clock, second, 0).is_err());
+    }
+
+    #[test]
+    fn ewma_weights() {
+        for bad in [0.0, -0.5, 1.5, f64::NAN] {
+            assert!(matches!(Ewma::new(bad), Err(AveragerError::InvalidAlpha(_))), "{}", bad);
+        }
+        let mut e = Ewma::new(0.5).unwrap();
+        feed_all(&mut e, &[8.0, 4.0, 0.0]);
+        assert_eq!(e.average(), Some(3.0));
+        // alpha 1 just follows the input
+        let mut e = Ewma::new(1.0).unwrap();
+        feed_all(&mut e, &[8.0, 4.0]);
+        assert_eq!(e.average(), Some(4.0));
+    }
+
+    #[test]
+    fn non_finite_values_leave_state_untouched() {
+        let clock = ManualClock::new();
+        let mut all: Vec<Box<dyn Averager>> = vec![
+            Box::new(CumulativeAverage::new()),
+            Box::new(WindowAverage::new(2).unwrap()),
+            Box::new(TimeWindowAverage::new(clock, Duration::from_secs(1), 2).unwrap()),
+            Box::new(Ewma::new(0.3).unwrap()),
+        ];
+        for a in all.iter_mut() {
+            a.add(4.0).unwrap();
+            for bad in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+                assert!(matches!(a.add(bad), Err(AveragerError::NotFinite(_))));
+            }
+            assert_eq!(a.average(), Some(4.0), "{}", a.name());
+        }
+    }
+
+    #[test]
+    fn huge_values_do_not_overflow() {
+        let clock = ManualClock::new();
+        let mut all: Vec<Box<dyn Averager>> = vec![
+            Box::new(CumulativeAverage::new()),
+            Box::new(WindowAverage::new(3).unwrap()),
+            Box::new(TimeWindowAverage::new(clock.clone(), Duration::from_secs(1), 2).unwrap()),
+            Box::new(Ewma::new(0.5).unwrap()),
+        ];
+        for a in all.iter_mut() {
+            feed_all(a.as_mut(), &[f64::MAX, f64::MAX]);
+            assert_eq!(a.average(), Some(f64::MAX), "{}", a.name());
+            clock.advance(Duration::from_secs(1));
+            feed_all(a.as_mut(), &[-f64::MAX, -f64::MAX]);
+            assert!(a.average().unwrap().is_finite(), "{}", a.name());
+        }
+        // `now - index` cannot overflow, however many buckets are asked for
+        let mut t = make_averager("time:1:18446744073709551615").unwrap();
+        feed_all(t.as_mut(), &[1.0, 3.0]);
+        assert_eq!(t.average(), Some(2.0));
+    }
+
+    #[test]
+    fn specs() {
+        let names: Vec<String> = ["all", "window:5", "ewma:0.25", "time:500:4"]
+            .iter()
+            .map(|s| make_averager(s).unwrap().name())
+            .collect();
+        assert_eq!(names, ["all", "window:5", "ewma:0.25", "time:500:4"]);
+        for bad in ["", "window", "window:x", "ewma:2", "time:0:4", "time:10", "median"] {
+            assert!(make_averager(bad).is_err(), "{}", bad);
+        }
+        assert_eq!(make_averager("window:0").err(), Some(AveragerError::ZeroCapacity));
+    }
+
+    #[test]
+    fn feed_reports_and_skips_bad_input() {
+        let mut averagers = vec![make_averager("all").unwrap(), make_averager("window:2").unwrap()];
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let report = feed(&mut averagers, "1 2\nx 3\n\nNaN\n".as_bytes(), &mut out, &mut err).unwrap();
+        assert_eq!(report, FeedReport { accepted: 3, rejected: 2 });
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "       value         all    window:2\n\
+             \x20          1       1.000       1.000\n\
+             \x20          2       1.500       1.500\n\
+             \x20          3       2.000       2.500\n"
+        );
+        assert_eq!(
+            String::from_utf8(err).unwrap(),
+            "line 2: \"x\" is not a number, skipped\nline 4: NaN is not a finite number, skipped\n"
+        );
+    }
+}
@@ -0,0 +1,385 @@
+/// This module moves trait objects up and down the trait hierarchy of `c10_OOP`
+/// (`ShowTell: Show + Location`, implemented by `Foo`):
+///     upcasting       `&dyn ShowTell` -> `&dyn Show` / `&dyn Location`, as the compiler does it
+///                     since Rust 1.86, and by hand with an `as_show` method before that
+///     downcasting     `&dyn ShowTellAny` -> `&Foo`, asking `Any` whether the type is right
+///     capabilities    a heterogeneous container that records, per element,
+///                     which traits it supports, and can be asked about them
+/// See
+///         https://doc.rust-lang.org/reference/type-coercions.html#unsized-coercions
+///         https://blog.rust-lang.org/2025/04/03/Rust-1.86.0.html#trait-upcasting
+///         https://doc.rust-lang.org/std/any/index.html
+
+use std::any::{type_name, Any};
+use std::marker::PhantomData;
+
+use crate::classes::c10_OOP::{Foo, Location, Show, ShowTell};
+use crate::classes::c10_plugins::Quacker;
+
+/// a `ShowTell` that can also be turned back into its concrete type.
+// `ShowTell` alone cannot: only `Any` knows the type behind a trait object.
+// The blanket impl makes every `ShowTell` without borrowed data (`'static`) a `ShowTellAny` too,
+// so `Foo` needs nothing new
+pub trait ShowTellAny: ShowTell + Any {}
+
+impl<T: ShowTell + Any> ShowTellAny for T {}
+
+/* ===== Upcasting =====
+   ========================= */
+
+// A `&dyn ShowTell` points to the value and to the v-table of `ShowTell`,
+// which contains the v-tables of `Show` and `Location`.
+// Upcasting just picks the inner v-table: the compiler does it when we return `x`.
+// No `Any` is needed for this: any `&dyn ShowTell` will do, e.g. the one of `c10_OOP::transferShowTell`
+pub fn as_show(x: &dyn ShowTell) -> &dyn Show {
+    x
+}
+
+pub fn as_location(x: &dyn ShowTell) -> &dyn Location {
+    x
+}
+
+// going up to `Any` does need it: the v-table of `ShowTellAny` contains the one of `Any` too
+pub fn as_any(x: &dyn ShowTellAny) -> &dyn Any {
+    x
+}
+
+// Before Rust 1.86 the lines above were rejected:
+// DNC: error[E0658]: cannot cast `dyn ShowTell` to `dyn Show`, trait upcasting coercion is experimental
+// The workaround, still found in many crates, is a method that every implementor provides.
+// A blanket impl writes it once for all sized types, where `self` CAN become a `&dyn Show`
+pub trait AsShow {
+    fn as_show(&self) -> &dyn Show;
+}
+
+impl<T: Show> AsShow for T {
+    fn as_show(&self) -> &dyn Show {
+        self
+    }
+}
+
+/* ===== Downcasting =====
+   ========================= */
+
+// Going down is not always possible: a `&dyn ShowTellAny` may point to any implementor.
+// `Any` knows the `TypeId` of the value behind it, so it can check and answer with an `Option`
+
+/// the `T` behind `x`, if that is what it is
+pub fn downcast<T: ShowTellAny>(x: &dyn ShowTellAny) -> Option<&T> {
+    as_any(x).downcast_ref::<T>()
+}
+
+pub fn downcast_foo(x: &dyn ShowTellAny) -> Option<&Foo> {
+    downcast::<Foo>(x)
+}
+
+/// the owned version: on a wrong guess we get the box back, still usable as a `ShowTellAny`
+pub fn downcast_box<T: ShowTellAny>(x: Box<dyn ShowTellAny>) -> Result<Box<T>, Box<dyn ShowTellAny>> {
+    // `Box<dyn Any>::downcast` would hand back a `Box<dyn Any>` on failure,
+    // losing `ShowTellAny` for good, so we check first and only then convert
+    if as_any(&*x).is::<T>() {
+        let any: Box<dyn Any> = x;
+        Ok(any.downcast::<T>().expect("checked with `is` just above"))
+    } else {
+        Err(x)
+    }
+}
+
+// QUIZ: `b` is a `Box<dyn ShowTellAny>` holding a `Foo`. What does this print?
+// println!("{}", (&b as &dyn Any).is::<Foo>());
+
+
+
+
+
+//
+// `false`: the `Any` here is the BOX itself, a `Box<dyn ShowTellAny>`, not what is inside it.
+// Always go through `&*b` (the contents) as `downcast_box` does
+
+/* ===== Capabilities =====
+   ========================= */
+
+// Rust cannot ask a `dyn Any` at run time whether its type implements some trait:
+// trait impls are a compile-time fact. So we ask the compiler while we still KNOW the type,
+// at insertion, and store the answer as a function pointer that performs the upcast.
+// `upcast_show::<Foo>` is such a function: one copy per type `T`, made by the compiler
+fn upcast_show<T: Show + Any>(value: &dyn Any) -> Option<&(dyn Show + 'static)> {
+    value.downcast_ref::<T>().map(|t| t as &dyn Show)
+}
+
+fn upcast_location<T: Location + Any>(value: &dyn Any) -> Option<&(dyn Location + 'static)> {
+    value.downcast_ref::<T>().map(|t| t as &dyn Location)
+}
+
+fn upcast_quacker<T: Quacker + Any>(value: &dyn Any) -> Option<&(dyn Quacker + 'static)> {
+    value.downcast_ref::<T>().map(|t| t as &dyn Quacker)
+}
+
+// the type of those functions, for a target trait object `D` such as `dyn Show`.
+// Inside `Upcast<dyn Show>` the object has no reference around it, so it means `dyn Show + 'static`:
+// this is why the functions above spell out the `'static`, which holds since `Any` requires it
+type Upcast<D> = fn(&dyn Any) -> Option<&D>;
+
+struct Creature {
+    value: Box<dyn Any>,
+    type_name: &'static str,
+    show: Option<Upcast<dyn Show>>,
+    location: Option<Upcast<dyn Location>>,
+    quacker: Option<Upcast<dyn Quacker>>,
+}
+
+/// A vector of values of any types, each remembering which of
+/// `Show`, `Location` and `Quacker` it supports.
+#[derive(Default)]
+pub struct Menagerie {
+    creatures: Vec<Creature>,
+}
+
+/// Returned by `Menagerie::add`, to declare the capabilities of the value just added.
+// Each method is only available when `T` really has the trait: `where T: Show` and so on.
+// `PhantomData<T>` remembers `T` without storing a `T`
+pub struct Adding<'a, T> {
+    creature: &'a mut Creature,
+    _type: PhantomData<T>,
+}
+
+impl<'a, T: Any> Adding<'a, T> {
+    pub fn show(self) -> Self
+    where
+        T: Show,
+    {
+        self.creature.show = Some(upcast_show::<T>);
+        self
+    }
+
+    pub fn location(self) -> Self
+    where
+        T: Location,
+    {
+        self.creature.location = Some(upcast_location::<T>);
+        self
+    }
+
+    pub fn quacker(self) -> Self
+    where
+        T: Quacker,
+    {
+        self.creature.quacker = Some(upcast_quacker::<T>);
+        self
+    }
+}
+
+// a capability can only be declared for a type that has it:
+// menagerie.add(5u8).location();
+// DNC: error[E0277]: the trait bound `u8: Location` is not satisfied
+
+impl Menagerie {
+    pub fn new() -> Self {
+        Menagerie::default()
+    }
+
+    /// adds `value` with no capabilities; chain `.show()`, `.location()`, `.quacker()` to declare them
+    pub fn add<T: Any>(&mut self, value: T) -> Adding<'_, T> {
+        self.creatures.push(Creature {
+            value: Box::new(value),
+            type_name: type_name::<T>(),
+            show: None,
+            location: None,
+            quacker: None,
+        });
+        let creature = self.creatures.last_mut().expect("just pushed");
+        Adding { creature, _type: PhantomData }
+    }
+
+    /// a `ShowTell` brings both of its capabilities along
+    pub fn add_showtell<T: ShowTellAny>(&mut self, value: T) -> Adding<'_, T> {
+        self.add(value).show().location()
+    }
+
+    pub fn len(&self) -> usize {
+        self.creatures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.creatures.is_empty()
+    }
+
+    pub fn as_show(&self, index: usize) -> Option<&dyn Show> {
+        let c = self.creatures.get(index)?;
+        (c.show?)(&*c.value)
+    }
+
+    pub fn as_location(&self, index: usize) -> Option<&dyn Location> {
+        let c = self.creatures.get(index)?;
+        (c.location?)(&*c.value)
+    }
+
+    pub fn as_quacker(&self, index: usize) -> Option<&dyn Quacker> {
+        let c = self.creatures.get(index)?;
+        (c.quacker?)(&*c.value)
+    }
+
+    /// the element as its concrete type, if it is a `T`
+    pub fn get<T: Any>(&self, index: usize) -> Option<&T> {
+        self.creatures.get(index)?.value.downcast_ref::<T>()
+    }
+
+    /// the names of the traits element `index` was declared with
+    pub fn capabilities(&self, index: usize) -> Vec<&'static str> {
+        let c = match self.creatures.get(index) {
+            Some(c) => c,
+            None => return Vec::new(),
+        };
+        let mut caps = Vec::new();
+        if c.show.is_some() {
+            caps.push("Show");
+        }
+        if c.location.is_some() {
+            caps.push("Location");
+        }
+        if c.quacker.is_some() {
+            caps.push("Quacker");
+        }
+        caps
+    }
+
+    pub fn type_name(&self, index: usize) -> Option<&'static str> {
+        self.creatures.get(index).map(|c| c.type_name)
+    }
+
+    /// every element that can be shown, in order
+    pub fn shows(&self) -> impl Iterator<Item = &dyn Show> {
+        (0..self.len()).filter_map(move |i| self.as_show(i))
+    }
+
+    pub fn locations(&self) -> impl Iterator<Item = &dyn Location> {
+        (0..self.len()).filter_map(move |i| self.as_location(i))
+    }
+}
+
+pub fn castsexample() {
+    let f = Foo::new("Foo", "Povo");
+    let st: &dyn ShowTellAny = &f;
+    println!("up:   {} at {}", as_show(st).show(), as_location(st).location());
+    println!("down: {:?}", downcast_foo(st));
+
+    let mut m = Menagerie::new();
+    m.add_showtell(Foo::new("Bar", "Trento"));
+    m.add(42).show();
+    m.add(crate::classes::c10_plugins::Duck).quacker();
+    m.add([1u8, 2, 3]);
+    for i in 0..m.len() {
+        println!("{:<40} {:?}", m.type_name(i).unwrap_or("?"), m.capabilities(i));
+    }
+    for s in m.shows() {
+        println!("shows as: {}", s.show());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classes::c10_OOP::transferShowTell;
+    use crate::classes::c10_plugins::Duck;
+
+    // another `ShowTell`, to have something that is not a `Foo`
+    struct Place(&'static str);
+    impl Show for Place {
+        fn show(&self) -> String {
+            format!("place {}", self.0)
+        }
+    }
+    impl Location for Place {
+        fn location(&self) -> String {
+            self.0.to_string()
+        }
+    }
+    impl ShowTell for Place {}
+
+    #[test]
+    fn upcasts() {
+        let f = Foo::new("n", "a");
+        let st: &dyn ShowTellAny = &f;
+        assert_eq!(as_show(st).show(), "n");
+        assert_eq!(as_location(st).location(), "a");
+        assert!(as_any(st).is::<Foo>());
+        // the hand-written way gives the same object
+        assert_eq!(f.as_show().show(), "n");
+        assert_eq!(String::from("s").as_show().show(), "s");
+    }
+
+    #[test]
+    fn upcasts_what_c10_oop_hands_out() {
+        let f = Foo::new("n", "a");
+        let st = transferShowTell(&f);
+        assert_eq!(as_show(st).show(), "n");
+        assert_eq!(as_location(st).location(), "a");
+    }
+
+    #[test]
+    fn downcasts() {
+        let f = Foo::new("n", "a");
+        let place = Place("Povo");
+        let both: [&dyn ShowTellAny; 2] = [&f, &place];
+        assert!(downcast_foo(both[0]).is_some());
+        assert!(downcast_foo(both[1]).is_none());
+        assert_eq!(downcast::<Place>(both[1]).map(|p| p.0), Some("Povo"));
+        assert!(std::ptr::eq(downcast_foo(both[0]).unwrap(), &f));
+    }
+
+    #[test]
+    fn downcast_box_gives_the_box_back() {
+        let b: Box<dyn ShowTellAny> = Box::new(Place("Trento"));
+        // the classic mistake of the QUIZ: this asks about the box, not its contents
+        assert!(!(&b as &dyn Any).is::<Place>());
+        let b = match downcast_box::<Foo>(b) {
+            Ok(_) => panic!("not a Foo"),
+            Err(b) => b,
+        };
+        assert_eq!(b.show(), "place Trento");
+        let place: Box<Place> = downcast_box::<Place>(b).ok().unwrap();
+        assert_eq!(place.0, "Trento");
+    }
+
+    #[test]
+    fn menagerie_capabilities() {
+        let mut m = Menagerie::new();
+        assert!(m.is_empty());
+        m.add_showtell(Foo::new("foo", "Povo"));
+        m.add(7).show();
+        m.add(Duck).quacker();
+        m.add(Place("Rovereto")).location();
+        m.add(());
+        assert_eq!(m.len(), 5);
+        assert_eq!(m.capabilities(0), ["Show", "Location"]);
+        assert_eq!(m.capabilities(1), ["Show"]);
+        assert_eq!(m.capabilities(2), ["Quacker"]);
+        // `Place` is also `Show`, but only `Location` was declared
+        assert_eq!(m.capabilities(3), ["Location"]);
+        assert!(m.capabilities(4).is_empty());
+        assert!(m.capabilities(99).is_empty());
+
+        assert_eq!(m.as_show(1).unwrap().show(), "four-byte signed 7");
+        assert!(m.as_show(2).is_none());
+        assert_eq!(m.as_quacker(2).unwrap().quack(), "quack!");
+        assert_eq!(m.as_location(3).unwrap().location(), "Rovereto");
+        assert!(m.as_location(99).is_none());
+
+        let shown: Vec<String> = m.shows().map(|s| s.show()).collect();
+        assert_eq!(shown, ["foo", "four-byte signed 7"]);
+        let places: Vec<String> = m.locations().map(|l| l.location()).collect();
+        assert_eq!(places, ["Povo", "Rovereto"]);
+    }
+
+    #[test]
+    fn menagerie_downcasts() {
+        let mut m = Menagerie::new();
+        m.add(String::from("hi")).show();
+        m.add(1u8);
+        assert_eq!(m.get::<String>(0).map(String::as_str), Some("hi"));
+        assert_eq!(m.get::<u8>(0), None);
+        assert_eq!(m.get::<u8>(1), Some(&1));
+        assert_eq!(m.type_name(1), Some("u8"));
+        assert_eq!(m.type_name(2), None);
+    }
+}
@@ -0,0 +1,531 @@
+/// This module answers "no inheritance? then how?" with a small entity-component system (ECS),
+/// the design most game engines use instead of class hierarchies:
+///     entities        just ids; an entity IS nothing, it only HAS components
+///     components      plain data (`Position`, `Name`, `Health`, ...), one typed storage per type
+///     systems         functions that update every entity having a given set of components
+///     delegation      `World::show` / `World::location` ask the components in a fixed order,
+///                     and fall back to a default: the composition version of an inherited method
+/// It reuses the traits `Show` and `Location` of `c10_OOP`, and `Any` as in `c10_casts`.
+/// See
+///         https://doc.rust-lang.org/book/ch17-01-what-is-oo.html#inheritance-as-a-type-system-and-as-code-sharing
+///         https://en.wikipedia.org/wiki/Entity_component_system
+///         https://en.wikipedia.org/wiki/Composition_over_inheritance
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::classes::c10_OOP::{Location, Show};
+
+/* ===== Inheritance vs composition =====
+   ========================= */
+
+// With classes, one would write something like
+//      class Creature           { name; health; describe() {...} }
+//      class Monster  extends Creature { position; }
+//      class Ghost    extends Monster  { /* but ghosts have no health!? */ }
+// and every new combination (a flying, poisoned, invisible ... thing) needs a new class,
+// or an awkward place in the hierarchy.
+// With components, a ghost is an entity with a `Name` and a `Position` and no `Health`,
+// and any combination is just a different set of components.
+
+/* ===== Components =====
+   ========================= */
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Location for Position {
+    fn location(&self) -> String {
+        format!("({}, {})", self.x, self.y)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Velocity {
+    pub dx: i32,
+    pub dy: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Name(pub String);
+
+impl Show for Name {
+    fn show(&self) -> String {
+        self.0.clone()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Health {
+    pub hp: u32,
+    pub max: u32,
+}
+
+impl Health {
+    pub fn full(max: u32) -> Health {
+        Health { hp: max, max }
+    }
+    pub fn damage(&mut self, amount: u32) {
+        self.hp = self.hp.saturating_sub(amount);
+    }
+    pub fn heal(&mut self, amount: u32) {
+        self.hp = self.hp.saturating_add(amount).min(self.max);
+    }
+    pub fn is_dead(&self) -> bool {
+        self.hp == 0
+    }
+}
+
+impl Show for Health {
+    fn show(&self) -> String {
+        format!("hp {}/{}", self.hp, self.max)
+    }
+}
+
+/// loses this many hp per tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Poisoned(pub u32);
+
+/* ===== Entities and storages =====
+   ========================= */
+
+/// An index into the storages, plus a generation: when a despawned entity's index
+/// is reused, the generation changes, so old copies of the `Entity` stop working
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl fmt::Display for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.index)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EcsError {
+    NoSuchEntity(Entity),
+}
+
+impl fmt::Display for EcsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EcsError::NoSuchEntity(e) => write!(f, "entity {} does not exist (any more)", e),
+        }
+    }
+}
+
+impl std::error::Error for EcsError {}
+
+// the components of ONE type, indexed by entity index
+struct Storage<T> {
+    slots: Vec<Option<T>>,
+}
+
+// The world keeps storages of many different types in one map, so it sees them as trait objects.
+// `Any` lets it get the typed `Storage<T>` back; `clear` lets it remove an entity's component
+// without knowing its type, which is all `despawn` needs
+trait AnyStorage: Any {
+    fn clear(&mut self, index: usize);
+}
+
+impl<T: 'static> AnyStorage for Storage<T> {
+    fn clear(&mut self, index: usize) {
+        if let Some(slot) = self.slots.get_mut(index) {
+            *slot = None;
+        }
+    }
+}
+
+/// How `World::show` and `World::location` ask one kind of component for an answer
+type Delegate = fn(&World, Entity) -> Option<String>;
+
+#[derive(Default)]
+pub struct World {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free: Vec<u32>,
+    storages: HashMap<TypeId, Box<dyn AnyStorage>>,
+    show_delegates: Vec<Delegate>,
+    location_delegates: Vec<Delegate>,
+}
+
+impl World {
+    pub fn new() -> Self {
+        World::default()
+    }
+
+    pub fn spawn(&mut self) -> Entity {
+        match self.free.pop() {
+            Some(index) => {
+                let i = index as usize;
+                self.alive[i] = true;
+                Entity { index, generation: self.generations[i] }
+            }
+            None => {
+                self.generations.push(0);
+                self.alive.push(true);
+                Entity { index: (self.alive.len() - 1) as u32, generation: 0 }
+            }
+        }
+    }
+
+    pub fn is_alive(&self, e: Entity) -> bool {
+        let i = e.index as usize;
+        i < self.alive.len() && self.alive[i] && self.generations[i] == e.generation
+    }
+
+    /// removes the entity and all its components; `false` if it was already gone
+    pub fn despawn(&mut self, e: Entity) -> bool {
+        if !self.is_alive(e) {
+            return false;
+        }
+        let i = e.index as usize;
+        for storage in self.storages.values_mut() {
+            storage.clear(i);
+        }
+        self.alive[i] = false;
+        // after 2^32 reuses of one slot the generation starts over: a handle that old is not expected
+        self.generations[i] = self.generations[i].wrapping_add(1);
+        self.free.push(e.index);
+        true
+    }
+
+    /// every living entity, by index
+    pub fn entities(&self) -> Vec<Entity> {
+        (0..self.alive.len())
+            .filter(|&i| self.alive[i])
+            .map(|i| Entity { index: i as u32, generation: self.generations[i] })
+            .collect()
+    }
+
+    fn storage<T: 'static>(&self) -> Option<&Storage<T>> {
+        let s: &dyn Any = &**self.storages.get(&TypeId::of::<T>())?;
+        // the map only ever holds a `Storage<T>` under the key `TypeId::of::<T>()`
+        Some(s.downcast_ref::<Storage<T>>().expect("storage of the wrong type"))
+    }
+
+    fn storage_mut<T: 'static>(&mut self) -> Option<&mut Storage<T>> {
+        let s: &mut dyn Any = &mut **self.storages.get_mut(&TypeId::of::<T>())?;
+        Some(s.downcast_mut::<Storage<T>>().expect("storage of the wrong type"))
+    }
+
+    // the first `insert` of a component type creates its storage
+    fn storage_or_new<T: 'static>(&mut self) -> &mut Storage<T> {
+        let s: &mut dyn Any = &mut **self
+            .storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Storage::<T> { slots: Vec::new() }));
+        s.downcast_mut::<Storage<T>>().expect("storage of the wrong type")
+    }
+
+    /// adds (or replaces) the `T` of `e`, returning the old one
+    pub fn insert<T: 'static>(&mut self, e: Entity, component: T) -> Result<Option<T>, EcsError> {
+        if !self.is_alive(e) {
+            return Err(EcsError::NoSuchEntity(e));
+        }
+        let i = e.index as usize;
+        let storage = self.storage_or_new::<T>();
+        if storage.slots.len() <= i {
+            storage.slots.resize_with(i + 1, || None);
+        }
+        Ok(storage.slots[i].replace(component))
+    }
+
+    pub fn remove<T: 'static>(&mut self, e: Entity) -> Option<T> {
+        if !self.is_alive(e) {
+            return None;
+        }
+        self.storage_mut::<T>()?.slots.get_mut(e.index as usize)?.take()
+    }
+
+    pub fn get<T: 'static>(&self, e: Entity) -> Option<&T> {
+        if !self.is_alive(e) {
+            return None;
+        }
+        self.storage::<T>()?.slots.get(e.index as usize)?.as_ref()
+    }
+
+    pub fn get_mut<T: 'static>(&mut self, e: Entity) -> Option<&mut T> {
+        if !self.is_alive(e) {
+            return None;
+        }
+        self.storage_mut::<T>()?.slots.get_mut(e.index as usize)?.as_mut()
+    }
+
+    pub fn has<T: 'static>(&self, e: Entity) -> bool {
+        self.get::<T>(e).is_some()
+    }
+
+    /// every living entity with a `T`, and its `T`
+    pub fn query<T: 'static>(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.entities().into_iter().filter_map(move |e| self.get::<T>(e).map(|c| (e, c)))
+    }
+
+    /// every living entity having ALL the components of `S`, e.g. `(Position, Velocity)`
+    // Systems first collect these ids, then update the entities one by one:
+    // borrowing two storages of the map mutably at the same time is not allowed,
+    // but one `get_mut` after the other is fine
+    pub fn entities_with<S: ComponentSet>(&self) -> Vec<Entity> {
+        self.entities().into_iter().filter(|&e| S::all_present(self, e)).collect()
+    }
+
+    /* ===== Delegation =====
+       ========================= */
+
+    /// from now on, `show` asks the `T` component of an entity, after the ones registered before
+    pub fn delegate_show<T: Show + 'static>(&mut self) {
+        // a closure that captures nothing is a plain function, and fits a `fn` pointer
+        self.show_delegates.push(|w, e| w.get::<T>(e).map(|c| c.show()));
+    }
+
+    pub fn delegate_location<T: Location + 'static>(&mut self) {
+        self.location_delegates.push(|w, e| w.get::<T>(e).map(|c| c.location()));
+    }
+
+    /// the answer of the first component that has one, or the default:
+    /// like a method looked up from the subclass up to the base class
+    pub fn show(&self, e: Entity) -> String {
+        self.show_delegates
+            .iter()
+            .find_map(|d| d(self, e))
+            .unwrap_or_else(|| format!("entity {}", e))
+    }
+
+    pub fn location(&self, e: Entity) -> String {
+        self.location_delegates
+            .iter()
+            .find_map(|d| d(self, e))
+            .unwrap_or_else(|| String::from("nowhere"))
+    }
+}
+
+/// A tuple of component types that an entity must all have.
+pub trait ComponentSet {
+    fn all_present(world: &World, e: Entity) -> bool;
+}
+
+// `$(...)&&+` repeats the check for every type, with `&&` in between
+macro_rules! component_set {
+    ($($t:ident),+) => {
+        impl<$($t: 'static),+> ComponentSet for ($($t,)+) {
+            fn all_present(world: &World, e: Entity) -> bool {
+                $(world.has::<$t>(e))&&+
+            }
+        }
+    };
+}
+
+component_set!(A);
+component_set!(A, B);
+component_set!(A, B, C);
+component_set!(A, B, C, D);
+
+/* ===== Systems =====
+   ========================= */
+
+pub fn movement_system(world: &mut World) {
+    for e in world.entities_with::<(Position, Velocity)>() {
+        // `Velocity` is `Copy`: we copy it out, ending that borrow before the next one
+        let v = *world.get::<Velocity>(e).expect("selected by entities_with");
+        let p = world.get_mut::<Position>(e).expect("selected by entities_with");
+        // like `Health::heal`, never overflow: an entity stops at the edge of the world
+        p.x = p.x.saturating_add(v.dx);
+        p.y = p.y.saturating_add(v.dy);
+    }
+}
+
+pub fn poison_system(world: &mut World) {
+    for e in world.entities_with::<(Health, Poisoned)>() {
+        let Poisoned(amount) = *world.get::<Poisoned>(e).expect("selected by entities_with");
+        world.get_mut::<Health>(e).expect("selected by entities_with").damage(amount);
+    }
+}
+
+/// despawns the dead, and tells who died where
+pub fn death_system(world: &mut World) -> Vec<String> {
+    let mut obituaries = Vec::new();
+    for e in world.entities_with::<(Health,)>() {
+        if world.get::<Health>(e).is_some_and(Health::is_dead) {
+            obituaries.push(format!("{} died at {}", world.show(e), world.location(e)));
+            world.despawn(e);
+        }
+    }
+    obituaries
+}
+
+/// runs all systems `ticks` times, in a fixed order, and returns what happened
+pub fn simulate(world: &mut World, ticks: u32) -> Vec<String> {
+    let mut log = Vec::new();
+    for tick in 1..=ticks {
+        movement_system(world);
+        poison_system(world);
+        for line in death_system(world) {
+            log.push(format!("tick {}: {}", tick, line));
+        }
+    }
+    log
+}
+
+/// the small world of the example and of the tests
+pub fn sample_world() -> World {
+    let mut w = World::new();
+    // `Name` first: an entity with a name is shown by its name, even if it has health
+    w.delegate_show::<Name>();
+    w.delegate_show::<Health>();
+    w.delegate_location::<Position>();
+
+    let hero = w.spawn();
+    w.insert(hero, Name("hero".into())).unwrap();
+    w.insert(hero, Position { x: 0, y: 0 }).unwrap();
+    w.insert(hero, Velocity { dx: 1, dy: 0 }).unwrap();
+    w.insert(hero, Health::full(10)).unwrap();
+
+    let slime = w.spawn();
+    w.insert(slime, Name("slime".into())).unwrap();
+    w.insert(slime, Position { x: 5, y: 0 }).unwrap();
+    w.insert(slime, Health::full(3)).unwrap();
+    w.insert(slime, Poisoned(1)).unwrap();
+
+    // no name: shown by its health
+    let blob = w.spawn();
+    w.insert(blob, Health::full(2)).unwrap();
+    w.insert(blob, Position { x: 1, y: 1 }).unwrap();
+
+    // a ghost: it has a `Velocity`, but no `Position`, so `movement_system` skips it; and no health to lose
+    let ghost = w.spawn();
+    w.insert(ghost, Name("ghost".into())).unwrap();
+    w.insert(ghost, Velocity { dx: 0, dy: 1 }).unwrap();
+    w
+}
+
+pub fn ecsexample() {
+    let mut w = sample_world();
+    for line in simulate(&mut w, 4) {
+        println!("{}", line);
+    }
+    for e in w.entities() {
+        println!("{:<10} {:<10} {}", e.to_string(), w.show(e), w.location(e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spawn_despawn_and_generations() {
+        let mut w = World::new();
+        let a = w.spawn();
+        let b = w.spawn();
+        w.insert(a, Health::full(5)).unwrap();
+        assert!(w.despawn(a));
+        assert!(!w.despawn(a));
+        assert!(!w.is_alive(a));
+        // the index of `a` is reused, with a new generation
+        let c = w.spawn();
+        assert_eq!(c.index, a.index);
+        assert_ne!(c, a);
+        // `a`'s health went away with it, and the stale `a` cannot reach `c`
+        assert_eq!(w.get::<Health>(c), None);
+        w.insert(c, Health::full(9)).unwrap();
+        assert_eq!(w.get::<Health>(a), None);
+        assert_eq!(w.insert(a, Health::full(1)), Err(EcsError::NoSuchEntity(a)));
+        assert_eq!(w.entities(), [c, b]);
+    }
+
+    #[test]
+    fn components() {
+        let mut w = World::new();
+        let e = w.spawn();
+        assert!(!w.has::<Position>(e));
+        assert_eq!(w.insert(e, Position { x: 1, y: 2 }), Ok(None));
+        assert_eq!(w.insert(e, Position { x: 3, y: 4 }), Ok(Some(Position { x: 1, y: 2 })));
+        w.get_mut::<Position>(e).unwrap().x = 7;
+        assert_eq!(w.get::<Position>(e), Some(&Position { x: 7, y: 4 }));
+        assert_eq!(w.remove::<Position>(e), Some(Position { x: 7, y: 4 }));
+        assert_eq!(w.remove::<Position>(e), None);
+        assert_eq!(w.get_mut::<Name>(e), None);
+        // asking about a type never inserted creates no storage for it
+        assert_eq!(w.storages.len(), 1);
+    }
+
+    #[test]
+    fn queries_select_by_component_set() {
+        let w = sample_world();
+        let names = |es: Vec<Entity>| es.into_iter().map(|e| w.show(e)).collect::<Vec<_>>();
+        assert_eq!(names(w.entities_with::<(Position, Velocity)>()), ["hero"]);
+        assert_eq!(names(w.entities_with::<(Health,)>()), ["hero", "slime", "hp 2/2"]);
+        assert_eq!(names(w.entities_with::<(Name, Velocity)>()), ["hero", "ghost"]);
+        assert_eq!(names(w.entities_with::<(Name, Position, Health, Poisoned)>()), ["slime"]);
+        let xs: Vec<i32> = w.query::<Position>().map(|(_, p)| p.x).collect();
+        assert_eq!(xs, [0, 5, 1]);
+    }
+
+    #[test]
+    fn delegation_order_and_defaults() {
+        let mut w = sample_world();
+        let blob = w.entities()[2];
+        let ghost = w.entities()[3];
+        assert_eq!(w.show(blob), "hp 2/2");
+        assert_eq!(w.location(ghost), "nowhere");
+        // remove the components one by one, and the answers fall through to the default
+        w.insert(blob, Name("blob".into())).unwrap();
+        assert_eq!(w.show(blob), "blob");
+        w.remove::<Name>(blob);
+        w.remove::<Health>(blob);
+        assert_eq!(w.show(blob), format!("entity {}", blob));
+        // a world with no delegates at all answers with the defaults only
+        let mut empty = World::new();
+        let e = empty.spawn();
+        empty.insert(e, Name("x".into())).unwrap();
+        assert_eq!((empty.show(e), empty.location(e)), ("entity #0".to_string(), "nowhere".to_string()));
+    }
+
+    #[test]
+    fn simulation() {
+        let mut w = sample_world();
+        let log = simulate(&mut w, 4);
+        assert_eq!(log, ["tick 3: slime died at (5, 0)"]);
+        let hero = w.entities()[0];
+        assert_eq!(w.location(hero), "(4, 0)");
+        assert_eq!(w.get::<Health>(hero), Some(&Health::full(10)));
+        assert_eq!(w.entities().len(), 3);
+
+        // poison the nameless blob: it dies, and is reported by its (last) health
+        let blob = w.entities()[1];
+        w.insert(blob, Poisoned(2)).unwrap();
+        assert_eq!(simulate(&mut w, 1), ["tick 1: hp 0/2 died at (1, 1)"]);
+        assert!(!w.is_alive(blob));
+    }
+
+    #[test]
+    fn movement_stops_at_the_edge_of_the_world() {
+        let mut w = World::new();
+        let e = w.spawn();
+        w.insert(e, Position { x: i32::MAX - 1, y: i32::MIN + 1 }).unwrap();
+        w.insert(e, Velocity { dx: 5, dy: -5 }).unwrap();
+        movement_system(&mut w);
+        movement_system(&mut w);
+        assert_eq!(w.get::<Position>(e), Some(&Position { x: i32::MAX, y: i32::MIN }));
+    }
+
+    #[test]
+    fn health_bounds() {
+        let mut h = Health::full(5);
+        h.damage(7);
+        assert!(h.is_dead());
+        h.heal(9);
+        assert_eq!(h, Health::full(5));
+        // healing never overflows, however large the numbers
+        let mut h = Health::full(u32::MAX);
+        h.damage(1);
+        h.heal(2);
+        assert_eq!(h, Health::full(u32::MAX));
+    }
+}
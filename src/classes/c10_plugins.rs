@@ -0,0 +1,386 @@
+/// This module turns the `Quack` example of `c10_OOP` into a plugin system:
+///     trait objects       plugins are `Box<dyn Quacker>`: the caller never knows their type
+///     function pointers   the registry stores `fn() -> Box<dyn Quacker>` constructors, by name
+///     run-time selection  `cargo run -- quack parrot 3` picks a plugin by the name on the CLI
+///     dispatch cost       `cargo run --release -- quack --bench` times generic vs `dyn` calls
+/// See
+///         https://doc.rust-lang.org/book/ch17-02-trait-objects.html#trait-objects-perform-dynamic-dispatch
+///         https://doc.rust-lang.org/book/ch19-05-advanced-functions-and-closures.html#function-pointers
+///         https://doc.rust-lang.org/std/hint/fn.black_box.html
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// `c10_OOP::Quack`, grown up: it returns its sound instead of printing it,
+/// and has a cheap arithmetic method for the benchmark
+pub trait Quacker {
+    fn quack(&self) -> String;
+    /// how loud the `step`-th quack is; cheap on purpose, so that the call itself dominates
+    fn loudness(&self, step: u64) -> u64;
+    /// a default method, shared by all plugins
+    fn chorus(&self, times: usize) -> String {
+        vec![self.quack(); times].join(" ")
+    }
+}
+
+pub struct Duck;
+
+impl Quacker for Duck {
+    fn quack(&self) -> String {
+        String::from("quack!")
+    }
+    fn loudness(&self, step: u64) -> u64 {
+        step % 7 + 60
+    }
+}
+
+pub struct Parrot {
+    word: &'static str,
+}
+
+impl Quacker for Parrot {
+    fn quack(&self) -> String {
+        format!("squawk, {}!", self.word)
+    }
+    fn loudness(&self, step: u64) -> u64 {
+        step.wrapping_mul(3) % 11 + 70
+    }
+}
+
+pub struct RubberDuck;
+
+impl Quacker for RubberDuck {
+    fn quack(&self) -> String {
+        String::from("squeak")
+    }
+    fn loudness(&self, step: u64) -> u64 {
+        step & 3
+    }
+    // a plugin may override the default method
+    fn chorus(&self, times: usize) -> String {
+        format!("squ{}eak", "e".repeat(times))
+    }
+}
+
+pub struct Robot;
+
+impl Quacker for Robot {
+    fn quack(&self) -> String {
+        String::from("QUACK.EXE")
+    }
+    fn loudness(&self, step: u64) -> u64 {
+        (step ^ 0x5a) % 13 + 80
+    }
+}
+
+/* ===== Registry =====
+   ========================= */
+
+/// A constructor for a plugin. This is a function POINTER type:
+/// plain `fn`s and closures that capture nothing can both be stored in it
+pub type Factory = fn() -> Box<dyn Quacker>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PluginError {
+    Duplicate(String),
+    Unknown { name: String, available: Vec<String> },
+}
+
+impl fmt::Display for PluginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PluginError::Duplicate(name) => write!(f, "a plugin named {:?} is already registered", name),
+            PluginError::Unknown { name, available } => {
+                write!(f, "no plugin named {:?}; available: {}", name, available.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for PluginError {}
+
+#[derive(Default)]
+pub struct PluginRegistry {
+    // sorted by name, so `names` lists them alphabetically
+    factories: BTreeMap<String, (&'static str, Factory)>,
+}
+
+impl PluginRegistry {
+    pub fn new() -> Self {
+        PluginRegistry::default()
+    }
+
+    /// the registry with every plugin of this module, as set up at startup
+    pub fn with_builtins() -> Self {
+        let mut r = PluginRegistry::new();
+        let builtins: [(&str, &'static str, Factory); 4] = [
+            ("duck", "a plain duck, as in c10_OOP", || Box::new(Duck)),
+            ("parrot", "a RandomBird that is a parrot", || Box::new(Parrot { word: "cracker" })),
+            ("rubber-duck", "squeaks, and overrides `chorus`", || Box::new(RubberDuck)),
+            ("robot", "quacks in capitals", || Box::new(Robot)),
+        ];
+        for (name, about, factory) in builtins {
+            // the names above are distinct, so this cannot fail
+            r.register(name, about, factory).expect("built-in plugin names are unique");
+        }
+        r
+    }
+
+    pub fn register(&mut self, name: &str, about: &'static str, factory: Factory) -> Result<(), PluginError> {
+        if self.factories.contains_key(name) {
+            return Err(PluginError::Duplicate(name.to_string()));
+        }
+        self.factories.insert(name.to_string(), (about, factory));
+        Ok(())
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.factories.keys().map(String::as_str).collect()
+    }
+
+    /// builds a fresh instance of the plugin called `name`
+    pub fn create(&self, name: &str) -> Result<Box<dyn Quacker>, PluginError> {
+        match self.factories.get(name) {
+            Some((_, factory)) => Ok(factory()),
+            None => Err(PluginError::Unknown {
+                name: name.to_string(),
+                available: self.names().iter().map(|s| s.to_string()).collect(),
+            }),
+        }
+    }
+
+    /// one line per plugin: name and description
+    pub fn list(&self) -> String {
+        self.factories
+            .iter()
+            .map(|(name, (about, _))| format!("{:<12} {}\n", name, about))
+            .collect()
+    }
+}
+
+/* ===== Benchmark =====
+   ========================= */
+
+// Three ways to make the same calls.
+// `black_box` hides values from the optimiser: without it, the compiler could see which
+// plugin is used, call `loudness` directly (or fold the whole loop away) and we'd time nothing.
+// `#[inline(never)]` keeps each loop in its own function, so they cannot be merged together.
+
+/// generic: compiled once per plugin type, `loudness` is a direct call and can be inlined
+#[inline(never)]
+fn run_static<Q: Quacker>(q: &Q, calls: u64) -> u64 {
+    let mut total: u64 = 0;
+    for i in 0..calls {
+        total = total.wrapping_add(q.loudness(black_box(i)));
+    }
+    total
+}
+
+/// `dyn`: one compiled copy; every call goes through the v-table of `q`
+#[inline(never)]
+fn run_dyn(q: &dyn Quacker, calls: u64) -> u64 {
+    let mut total: u64 = 0;
+    for i in 0..calls {
+        total = total.wrapping_add(q.loudness(black_box(i)));
+    }
+    total
+}
+
+/// `dyn` over a mix of types: the target of the call changes every time,
+/// which the CPU's branch predictor cannot guess as easily
+#[inline(never)]
+fn run_mixed(plugins: &[Box<dyn Quacker>], calls: u64) -> u64 {
+    let mut total: u64 = 0;
+    let n = plugins.len() as u64;
+    for i in 0..calls {
+        total = total.wrapping_add(plugins[(i % n) as usize].loudness(black_box(i)));
+    }
+    total
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BenchResult {
+    pub calls: u64,
+    pub generic: Duration,
+    pub dynamic: Duration,
+    pub mixed: Duration,
+    // the loops must compute the same thing, or we are comparing apples with oranges
+    pub generic_sum: u64,
+    pub dynamic_sum: u64,
+}
+
+fn time<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+pub fn benchmark(calls: u64) -> BenchResult {
+    let duck = Duck;
+    let (generic_sum, generic) = time(|| run_static(black_box(&duck), calls));
+    let (dynamic_sum, dynamic) = time(|| run_dyn(black_box(&duck as &dyn Quacker), calls));
+    let mixed_plugins: Vec<Box<dyn Quacker>> =
+        vec![Box::new(Duck), Box::new(Robot), Box::new(RubberDuck), Box::new(Parrot { word: "x" })];
+    let (_, mixed) = time(|| run_mixed(black_box(&mixed_plugins), calls));
+    BenchResult { calls, generic, dynamic, mixed, generic_sum, dynamic_sum }
+}
+
+impl fmt::Display for BenchResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let per_call = |d: Duration| d.as_nanos() as f64 / self.calls.max(1) as f64;
+        let base = per_call(self.generic).max(f64::MIN_POSITIVE);
+        writeln!(f, "{} calls each", self.calls)?;
+        for (label, d) in [
+            ("generic (static dispatch)", self.generic),
+            ("&dyn, always a Duck", self.dynamic),
+            ("Box<dyn>, four plugin types", self.mixed),
+        ] {
+            writeln!(
+                f,
+                "{:<28} {:>10.2?} {:>8.3} ns/call {:>7.2}x",
+                label,
+                d,
+                per_call(d),
+                per_call(d) / base
+            )?;
+        }
+        if cfg!(debug_assertions) {
+            writeln!(f, "(debug build: nothing is inlined, rerun with --release for meaningful numbers)")?;
+        }
+        Ok(())
+    }
+}
+
+// QUIZ: with `--release`, which of the three lines is the slowest, and why?
+// And why can the first two be almost equally fast?
+
+
+
+
+
+//
+// The mixed one: the call target changes at every iteration, so the CPU mispredicts
+// the indirect jump, and nothing can be inlined.
+// With a single `Duck` behind the `&dyn`, the indirect call always goes to the same place:
+// the CPU predicts it perfectly and only the missed inlining is left to pay for,
+// which for a function this small costs little more than the call itself
+
+/* ===== Command line =====
+   ========================= */
+
+/// the `quack` subcommand:
+///     quack --list                every registered plugin
+///     quack NAME [TIMES]          the chorus of plugin NAME, TIMES at most `MAX_TIMES`
+///     quack --bench [CALLS]       the dispatch benchmark
+pub fn quackcommand(args: &[String]) -> Result<String, String> {
+    // TIMES becomes the size of a Vec or a String: a huge one would abort on allocation
+    const MAX_TIMES: usize = 1000;
+    const USAGE: &str = "usage: quack --list | quack NAME [TIMES] | quack --bench [CALLS], TIMES <= 1000";
+    let registry = PluginRegistry::with_builtins();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args[..] {
+        ["--list"] => Ok(registry.list()),
+        ["--bench"] => Ok(benchmark(10_000_000).to_string()),
+        ["--bench", calls] => match calls.parse() {
+            Ok(calls) => Ok(benchmark(calls).to_string()),
+            Err(_) => Err(USAGE.to_string()),
+        },
+        [name] => Ok(registry.create(name).map_err(|e| e.to_string())?.chorus(1) + "\n"),
+        [name, times] => {
+            let times: usize = match times.parse() {
+                Ok(times) if times <= MAX_TIMES => times,
+                _ => return Err(USAGE.to_string()),
+            };
+            Ok(registry.create(name).map_err(|e| e.to_string())?.chorus(times) + "\n")
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+pub fn pluginsexample() {
+    let registry = PluginRegistry::with_builtins();
+    print!("{}", registry.list());
+    for name in ["duck", "rubber-duck", "goose"] {
+        match registry.create(name) {
+            Ok(plugin) => println!("{}: {}", name, plugin.chorus(2)),
+            Err(e) => println!("{}", e),
+        }
+    }
+    print!("{}", benchmark(1_000_000));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &[&str]) -> Vec<String> {
+        s.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn builtins_are_registered_by_name() {
+        let r = PluginRegistry::with_builtins();
+        assert_eq!(r.names(), ["duck", "parrot", "robot", "rubber-duck"]);
+        assert_eq!(r.create("duck").unwrap().quack(), "quack!");
+        assert_eq!(r.create("parrot").unwrap().chorus(2), "squawk, cracker! squawk, cracker!");
+        assert_eq!(r.create("rubber-duck").unwrap().chorus(3), "squeeeeak");
+        assert_eq!(r.create("robot").unwrap().chorus(0), "");
+    }
+
+    #[test]
+    fn register_rejects_duplicates_and_accepts_closures() {
+        let mut r = PluginRegistry::with_builtins();
+        assert_eq!(
+            r.register("duck", "again", || Box::new(Robot)),
+            Err(PluginError::Duplicate("duck".to_string()))
+        );
+        // the duck is still the old one
+        assert_eq!(r.create("duck").unwrap().quack(), "quack!");
+        fn goose() -> Box<dyn Quacker> {
+            Box::new(Parrot { word: "honk" })
+        }
+        r.register("goose", "a plain fn works too", goose).unwrap();
+        assert_eq!(r.create("goose").unwrap().quack(), "squawk, honk!");
+        assert!(r.list().contains("goose        a plain fn works too\n"));
+    }
+
+    #[test]
+    fn unknown_plugin_lists_alternatives() {
+        let r = PluginRegistry::with_builtins();
+        let e = r.create("swan").err().unwrap();
+        assert_eq!(
+            e.to_string(),
+            "no plugin named \"swan\"; available: duck, parrot, robot, rubber-duck"
+        );
+    }
+
+    #[test]
+    fn command_line() {
+        assert_eq!(quackcommand(&args(&["duck"])), Ok("quack!\n".to_string()));
+        assert_eq!(quackcommand(&args(&["robot", "2"])), Ok("QUACK.EXE QUACK.EXE\n".to_string()));
+        assert!(quackcommand(&args(&["--list"])).unwrap().starts_with("duck "));
+        assert!(quackcommand(&args(&["swan"])).unwrap_err().contains("available"));
+        assert!(quackcommand(&args(&["duck", "many"])).unwrap_err().starts_with("usage"));
+        assert!(quackcommand(&args(&[])).unwrap_err().starts_with("usage"));
+    }
+
+    #[test]
+    fn command_line_caps_times() {
+        assert_eq!(quackcommand(&args(&["rubber-duck", "1000"])).unwrap().len(), 1000 + "squeak\n".len());
+        for huge in ["1001", "10000000000000000", "18446744073709551615", "18446744073709551616"] {
+            assert!(quackcommand(&args(&["duck", huge])).unwrap_err().starts_with("usage"), "{}", huge);
+        }
+        assert!(quackcommand(&args(&["--bench", "1000"])).unwrap().starts_with("1000 calls each"));
+    }
+
+    #[test]
+    fn benchmark_loops_do_the_same_work() {
+        let r = benchmark(10_000);
+        assert_eq!(r.generic_sum, r.dynamic_sum);
+        assert_eq!(r.generic_sum, (0..10_000u64).map(|i| Duck.loudness(i)).sum::<u64>());
+        let report = r.to_string();
+        assert_eq!(report.lines().filter(|l| l.contains("ns/call")).count(), 3);
+    }
+}
@@ -0,0 +1,726 @@
+/// This module brings together the rectangles of the course:
+/// `Rectangle`, `Square` and `Rhombus` in `c04_structs`, `Rectangle` in `c10_OOP` and in `c11_heap`.
+/// Each of them is a separate struct with integer sides, and they share nothing but
+/// methods that happen to have the same name, like `area`.
+/// Here the common behaviour becomes a trait, `Shape`, implemented by six shapes:
+///     Rectangle, Square, Rhombus, Circle, Triangle, Polygon
+/// with float coordinates, a position and a rotation, so that they can be moved around.
+/// Constructors VALIDATE their input and return a Result: a square with side 0 or a rhombus
+/// with an angle of 180° cannot be built, so every method can assume a proper shape.
+/// See
+///         https://doc.rust-lang.org/book/ch17-02-trait-objects.html
+///         https://en.wikipedia.org/wiki/Shoelace_formula
+
+use std::f64::consts::PI;
+use std::fmt;
+
+/* ========= Points =========
+   ========================== */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+pub fn pt(x: f64, y: f64) -> Point {
+    Point { x, y }
+}
+
+impl Point {
+    pub fn distance(self, other: Point) -> f64 {
+        (self.x - other.x).hypot(self.y - other.y)
+    }
+
+    /// Rotates the point counterclockwise by `degrees` around `center`
+    pub fn rotated(self, center: Point, degrees: f64) -> Point {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let (dx, dy) = (self.x - center.x, self.y - center.y);
+        pt(center.x + dx * cos - dy * sin, center.y + dx * sin + dy * cos)
+    }
+
+    pub fn scaled(self, center: Point, factor: f64) -> Point {
+        pt(center.x + (self.x - center.x) * factor, center.y + (self.y - center.y) * factor)
+    }
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+/// An axis-aligned bounding box: the smallest rectangle, with sides parallel to the axes,
+/// that contains the shape
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl BoundingBox {
+    pub fn width(&self) -> f64 {
+        self.max.x - self.min.x
+    }
+    pub fn height(&self) -> f64 {
+        self.max.y - self.min.y
+    }
+    pub fn contains(&self, p: Point) -> bool {
+        self.min.x - EPS <= p.x && p.x <= self.max.x + EPS && self.min.y - EPS <= p.y && p.y <= self.max.y + EPS
+    }
+
+    fn of(points: &[Point]) -> BoundingBox {
+        let mut bb = BoundingBox { min: points[0], max: points[0] };
+        for p in &points[1..] {
+            bb.min = pt(bb.min.x.min(p.x), bb.min.y.min(p.y));
+            bb.max = pt(bb.max.x.max(p.x), bb.max.y.max(p.y));
+        }
+        bb
+    }
+}
+
+// floats are not exact: `0.1 + 0.2 != 0.3`. Comparisons with points on a border use a tolerance
+const EPS: f64 = 1e-9;
+
+/* ========= Errors =========
+   ========================== */
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShapeError {
+    // a length, or a scale factor, that is zero or negative
+    NotPositive { what: &'static str, value: f64 },
+    // NaN or infinity
+    NotFinite { what: &'static str },
+    // a rhombus angle must be strictly between 0° and 180°
+    AngleOutOfRange(f64),
+    TooFewVertices(usize),
+    // points on a line, or a polygon that crosses itself
+    Degenerate(&'static str),
+}
+
+impl fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShapeError::NotPositive { what, value } => write!(f, "{} must be positive, got {}", what, value),
+            ShapeError::NotFinite { what } => write!(f, "{} must be a finite number", what),
+            ShapeError::AngleOutOfRange(a) => write!(f, "angle must be strictly between 0° and 180°, got {}°", a),
+            ShapeError::TooFewVertices(n) => write!(f, "a polygon needs at least 3 vertices, got {}", n),
+            ShapeError::Degenerate(why) => write!(f, "degenerate shape: {}", why),
+        }
+    }
+}
+
+impl std::error::Error for ShapeError {}
+
+fn finite(what: &'static str, v: f64) -> Result<f64, ShapeError> {
+    if v.is_finite() { Ok(v) } else { Err(ShapeError::NotFinite { what }) }
+}
+
+fn positive(what: &'static str, v: f64) -> Result<f64, ShapeError> {
+    match finite(what, v)? {
+        v if v > 0.0 => Ok(v),
+        v => Err(ShapeError::NotPositive { what, value: v }),
+    }
+}
+
+fn finite_point(what: &'static str, p: Point) -> Result<Point, ShapeError> {
+    finite(what, p.x)?;
+    finite(what, p.y)?;
+    Ok(p)
+}
+
+/* ===== Polygon helpers =====
+   =========================== */
+// Most shapes are polygons in disguise: once we have their corners,
+// area, perimeter, bounding box and containment are the same code for all of them.
+
+// the shoelace formula: positive if the vertices are counterclockwise
+fn signed_area(v: &[Point]) -> f64 {
+    let n = v.len();
+    (0..n).map(|i| v[i].x * v[(i + 1) % n].y - v[(i + 1) % n].x * v[i].y).sum::<f64>() / 2.0
+}
+
+fn polygon_perimeter(v: &[Point]) -> f64 {
+    let n = v.len();
+    (0..n).map(|i| v[i].distance(v[(i + 1) % n])).sum()
+}
+
+fn on_segment(p: Point, a: Point, b: Point) -> bool {
+    let cross = (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x);
+    cross.abs() <= EPS * a.distance(b).max(1.0) && BoundingBox::of(&[a, b]).contains(p)
+}
+
+// ray casting: a horizontal ray from `p` crosses the border an odd number of times
+// if and only if `p` is inside. Works for non-convex polygons too.
+fn polygon_contains(v: &[Point], p: Point) -> bool {
+    let n = v.len();
+    let mut inside = false;
+    for i in 0..n {
+        let (a, b) = (v[i], v[(i + 1) % n]);
+        // points on the border count as inside
+        if on_segment(p, a, b) {
+            return true;
+        }
+        if (a.y > p.y) != (b.y > p.y) {
+            let x_cross = a.x + (p.y - a.y) * (b.x - a.x) / (b.y - a.y);
+            if p.x < x_cross {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+fn centroid_of(v: &[Point]) -> Point {
+    let n = v.len() as f64;
+    pt(v.iter().map(|p| p.x).sum::<f64>() / n, v.iter().map(|p| p.y).sum::<f64>() / n)
+}
+
+// do the segments ab and cd cross (touching at an end counts)?
+fn segments_intersect(a: Point, b: Point, c: Point, d: Point) -> bool {
+    let orient = |p: Point, q: Point, r: Point| {
+        let v = (q.x - p.x) * (r.y - p.y) - (q.y - p.y) * (r.x - p.x);
+        if v.abs() <= EPS { 0 } else { v.signum() as i8 }
+    };
+    let (o1, o2, o3, o4) = (orient(a, b, c), orient(a, b, d), orient(c, d, a), orient(c, d, b));
+    (o1 != o2 && o3 != o4)
+        || (o1 == 0 && on_segment(c, a, b))
+        || (o2 == 0 && on_segment(d, a, b))
+        || (o3 == 0 && on_segment(a, c, d))
+        || (o4 == 0 && on_segment(b, c, d))
+}
+
+/* ========= The trait =========
+   ============================= */
+// `scale` and `rotate` take `&mut self` and return a Result, with nothing in it:
+// if they returned `Self`, the trait could not be used as `dyn Shape`
+// (see object safety in `c08_traits`). With `&mut self`, a `Vec<Box<dyn Shape>>` works.
+pub trait Shape: fmt::Debug {
+    fn name(&self) -> &'static str;
+    fn area(&self) -> f64;
+    fn perimeter(&self) -> f64;
+    fn bounding_box(&self) -> BoundingBox;
+    /// Points on the border count as inside
+    fn contains_point(&self, p: Point) -> bool;
+    /// Scales the shape by `factor` around its center; the factor must be positive
+    fn scale(&mut self, factor: f64) -> Result<(), ShapeError>;
+    /// Rotates the shape counterclockwise by `degrees` around its center; the angle must be finite
+    fn rotate(&mut self, degrees: f64) -> Result<(), ShapeError>;
+
+    // a default method: every shape gets it for free
+    fn describe(&self) -> String {
+        format!("{} with area {:.2} and perimeter {:.2}", self.name(), self.area(), self.perimeter())
+    }
+}
+
+/* ======= Rectangle =======
+   ========================= */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rectangle {
+    center: Point,
+    width: f64,
+    height: f64,
+    // degrees, counterclockwise
+    rotation: f64,
+}
+
+impl Rectangle {
+    pub fn new(center: Point, width: f64, height: f64) -> Result<Rectangle, ShapeError> {
+        Ok(Rectangle {
+            center: finite_point("center", center)?,
+            width: positive("width", width)?,
+            height: positive("height", height)?,
+            rotation: 0.0,
+        })
+    }
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+    pub fn height(&self) -> f64 {
+        self.height
+    }
+    pub fn corners(&self) -> Vec<Point> {
+        let (w, h) = (self.width / 2.0, self.height / 2.0);
+        let c = self.center;
+        [pt(c.x - w, c.y - h), pt(c.x + w, c.y - h), pt(c.x + w, c.y + h), pt(c.x - w, c.y + h)]
+            .iter()
+            .map(|p| p.rotated(c, self.rotation))
+            .collect()
+    }
+}
+
+impl Shape for Rectangle {
+    fn name(&self) -> &'static str {
+        "rectangle"
+    }
+    fn area(&self) -> f64 {
+        self.width * self.height
+    }
+    fn perimeter(&self) -> f64 {
+        2.0 * (self.width + self.height)
+    }
+    fn bounding_box(&self) -> BoundingBox {
+        BoundingBox::of(&self.corners())
+    }
+    fn contains_point(&self, p: Point) -> bool {
+        polygon_contains(&self.corners(), p)
+    }
+    fn scale(&mut self, factor: f64) -> Result<(), ShapeError> {
+        let factor = positive("scale factor", factor)?;
+        // a huge factor can overflow to infinity, a tiny one can round to zero:
+        // both are checked before anything changes
+        let width = positive("scaled width", self.width * factor)?;
+        let height = positive("scaled height", self.height * factor)?;
+        self.width = width;
+        self.height = height;
+        Ok(())
+    }
+    fn rotate(&mut self, degrees: f64) -> Result<(), ShapeError> {
+        self.rotation = (self.rotation + finite("angle", degrees)? % 360.0) % 360.0;
+        Ok(())
+    }
+}
+
+/* ======== Square =========
+   ========================= */
+// A square IS a rectangle: we reuse it, instead of repeating its code.
+// Rust has no inheritance (see `c10_OOP`): we use composition and delegate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Square {
+    inner: Rectangle,
+}
+
+impl Square {
+    pub fn new(center: Point, side: f64) -> Result<Square, ShapeError> {
+        positive("side", side)?;
+        Ok(Square { inner: Rectangle::new(center, side, side)? })
+    }
+    pub fn side(&self) -> f64 {
+        self.inner.width
+    }
+    pub fn corners(&self) -> Vec<Point> {
+        self.inner.corners()
+    }
+}
+
+impl Shape for Square {
+    fn name(&self) -> &'static str {
+        "square"
+    }
+    fn area(&self) -> f64 {
+        self.inner.area()
+    }
+    fn perimeter(&self) -> f64 {
+        self.inner.perimeter()
+    }
+    fn bounding_box(&self) -> BoundingBox {
+        self.inner.bounding_box()
+    }
+    fn contains_point(&self, p: Point) -> bool {
+        self.inner.contains_point(p)
+    }
+    fn scale(&mut self, factor: f64) -> Result<(), ShapeError> {
+        self.inner.scale(factor)
+    }
+    fn rotate(&mut self, degrees: f64) -> Result<(), ShapeError> {
+        self.inner.rotate(degrees)
+    }
+}
+
+/* ======== Rhombus ========
+   ========================= */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rhombus {
+    center: Point,
+    side: f64,
+    // degrees, in (0, 90]: the obtuse angle is 180 - acute_angle
+    acute_angle: f64,
+    rotation: f64,
+}
+
+impl Rhombus {
+    /// `angle` is any inner angle, acute or obtuse: 60° and 120° give the same rhombus
+    pub fn new(center: Point, side: f64, angle: f64) -> Result<Rhombus, ShapeError> {
+        let angle = finite("angle", angle)?;
+        if angle <= 0.0 || angle >= 180.0 {
+            return Err(ShapeError::AngleOutOfRange(angle));
+        }
+        Ok(Rhombus {
+            center: finite_point("center", center)?,
+            side: positive("side", side)?,
+            acute_angle: angle.min(180.0 - angle),
+            rotation: 0.0,
+        })
+    }
+    pub fn acute_angle(&self) -> f64 {
+        self.acute_angle
+    }
+    // the diagonals cross at the center, at right angles
+    pub fn corners(&self) -> Vec<Point> {
+        let half = (self.acute_angle / 2.0).to_radians();
+        // the long diagonal is opposite the obtuse angles
+        let (dx, dy) = (self.side * half.cos(), self.side * half.sin());
+        let c = self.center;
+        [pt(c.x + dx, c.y), pt(c.x, c.y + dy), pt(c.x - dx, c.y), pt(c.x, c.y - dy)]
+            .iter()
+            .map(|p| p.rotated(c, self.rotation))
+            .collect()
+    }
+}
+
+impl Shape for Rhombus {
+    fn name(&self) -> &'static str {
+        "rhombus"
+    }
+    fn area(&self) -> f64 {
+        self.side * self.side * self.acute_angle.to_radians().sin()
+    }
+    fn perimeter(&self) -> f64 {
+        4.0 * self.side
+    }
+    fn bounding_box(&self) -> BoundingBox {
+        BoundingBox::of(&self.corners())
+    }
+    fn contains_point(&self, p: Point) -> bool {
+        polygon_contains(&self.corners(), p)
+    }
+    fn scale(&mut self, factor: f64) -> Result<(), ShapeError> {
+        let factor = positive("scale factor", factor)?;
+        self.side = positive("scaled side", self.side * factor)?;
+        Ok(())
+    }
+    fn rotate(&mut self, degrees: f64) -> Result<(), ShapeError> {
+        self.rotation = (self.rotation + finite("angle", degrees)? % 360.0) % 360.0;
+        Ok(())
+    }
+}
+
+/* ======== Circle =========
+   ========================= */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Circle {
+    center: Point,
+    radius: f64,
+}
+
+impl Circle {
+    pub fn new(center: Point, radius: f64) -> Result<Circle, ShapeError> {
+        Ok(Circle { center: finite_point("center", center)?, radius: positive("radius", radius)? })
+    }
+}
+
+impl Shape for Circle {
+    fn name(&self) -> &'static str {
+        "circle"
+    }
+    fn area(&self) -> f64 {
+        PI * self.radius * self.radius
+    }
+    fn perimeter(&self) -> f64 {
+        2.0 * PI * self.radius
+    }
+    fn bounding_box(&self) -> BoundingBox {
+        let (c, r) = (self.center, self.radius);
+        BoundingBox { min: pt(c.x - r, c.y - r), max: pt(c.x + r, c.y + r) }
+    }
+    fn contains_point(&self, p: Point) -> bool {
+        self.center.distance(p) <= self.radius + EPS
+    }
+    fn scale(&mut self, factor: f64) -> Result<(), ShapeError> {
+        let factor = positive("scale factor", factor)?;
+        self.radius = positive("scaled radius", self.radius * factor)?;
+        Ok(())
+    }
+    // a circle rotated around its center is the same circle, but a NaN angle is still an error
+    fn rotate(&mut self, degrees: f64) -> Result<(), ShapeError> {
+        finite("angle", degrees)?;
+        Ok(())
+    }
+}
+
+/* ======= Polygons =========
+   ========================== */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    vertices: Vec<Point>,
+}
+
+impl Polygon {
+    /// The vertices in order, clockwise or counterclockwise.
+    /// The border must not cross itself, and the area must not be zero.
+    pub fn new(vertices: Vec<Point>) -> Result<Polygon, ShapeError> {
+        if vertices.len() < 3 {
+            return Err(ShapeError::TooFewVertices(vertices.len()));
+        }
+        for p in &vertices {
+            finite_point("vertex", *p)?;
+        }
+        if signed_area(&vertices).abs() <= EPS {
+            return Err(ShapeError::Degenerate("the vertices are on a line"));
+        }
+        // every pair of edges that do not share a vertex must not touch
+        let n = vertices.len();
+        for i in 0..n {
+            for j in i + 1..n {
+                let adjacent = j == i + 1 || (i == 0 && j == n - 1);
+                if !adjacent
+                    && segments_intersect(vertices[i], vertices[(i + 1) % n], vertices[j], vertices[(j + 1) % n])
+                {
+                    return Err(ShapeError::Degenerate("the border crosses itself"));
+                }
+            }
+        }
+        Ok(Polygon { vertices })
+    }
+
+    pub fn vertices(&self) -> &[Point] {
+        &self.vertices
+    }
+
+    pub fn corners(&self) -> Vec<Point> {
+        self.vertices.clone()
+    }
+
+    // the moved vertices go through `new` again: far away vertices can overflow,
+    // and tiny scale factors can make them collapse onto each other.
+    // On error, `self` is left as it was
+    fn map_vertices(&mut self, f: impl Fn(Point, Point) -> Point) -> Result<(), ShapeError> {
+        let c = centroid_of(&self.vertices);
+        *self = Polygon::new(self.vertices.iter().map(|v| f(*v, c)).collect())?;
+        Ok(())
+    }
+}
+
+impl Shape for Polygon {
+    fn name(&self) -> &'static str {
+        "polygon"
+    }
+    fn area(&self) -> f64 {
+        signed_area(&self.vertices).abs()
+    }
+    fn perimeter(&self) -> f64 {
+        polygon_perimeter(&self.vertices)
+    }
+    fn bounding_box(&self) -> BoundingBox {
+        BoundingBox::of(&self.vertices)
+    }
+    fn contains_point(&self, p: Point) -> bool {
+        polygon_contains(&self.vertices, p)
+    }
+    fn scale(&mut self, factor: f64) -> Result<(), ShapeError> {
+        let factor = positive("scale factor", factor)?;
+        self.map_vertices(|v, c| v.scaled(c, factor))
+    }
+    fn rotate(&mut self, degrees: f64) -> Result<(), ShapeError> {
+        let degrees = finite("angle", degrees)?;
+        self.map_vertices(|v, c| v.rotated(c, degrees))
+    }
+}
+
+// A triangle is the simplest polygon: it has its own type so that it can be built
+// from exactly three points, checked by the compiler, and shares the polygon code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Triangle {
+    inner: Polygon,
+}
+
+impl Triangle {
+    pub fn new(a: Point, b: Point, c: Point) -> Result<Triangle, ShapeError> {
+        Ok(Triangle { inner: Polygon::new(vec![a, b, c])? })
+    }
+    pub fn corners(&self) -> Vec<Point> {
+        self.inner.corners()
+    }
+}
+
+impl Shape for Triangle {
+    fn name(&self) -> &'static str {
+        "triangle"
+    }
+    fn area(&self) -> f64 {
+        self.inner.area()
+    }
+    fn perimeter(&self) -> f64 {
+        self.inner.perimeter()
+    }
+    fn bounding_box(&self) -> BoundingBox {
+        self.inner.bounding_box()
+    }
+    fn contains_point(&self, p: Point) -> bool {
+        self.inner.contains_point(p)
+    }
+    fn scale(&mut self, factor: f64) -> Result<(), ShapeError> {
+        self.inner.scale(factor)
+    }
+    fn rotate(&mut self, degrees: f64) -> Result<(), ShapeError> {
+        self.inner.rotate(degrees)
+    }
+}
+
+pub fn shapesexample() -> Result<(), ShapeError> {
+    // dynamic dispatch, as in `c10_OOP`: one Vec, six types
+    let mut shapes: Vec<Box<dyn Shape>> = vec![
+        Box::new(Rectangle::new(pt(0.0, 0.0), 4.0, 2.0)?),
+        Box::new(Square::new(pt(5.0, 5.0), 2.0)?),
+        Box::new(Rhombus::new(pt(0.0, 5.0), 2.0, 60.0)?),
+        Box::new(Circle::new(pt(-5.0, 0.0), 1.0)?),
+        Box::new(Triangle::new(pt(0.0, 0.0), pt(3.0, 0.0), pt(0.0, 4.0))?),
+        Box::new(Polygon::new(vec![pt(0.0, 0.0), pt(4.0, 0.0), pt(4.0, 4.0), pt(2.0, 1.0), pt(0.0, 4.0)])?),
+    ];
+    for s in shapes.iter_mut() {
+        println!("{}", s.describe());
+        s.rotate(45.0)?;
+        s.scale(2.0)?;
+        let bb = s.bounding_box();
+        println!("    rotated and doubled: area {:.2}, box {:.2}x{:.2}", s.area(), bb.width(), bb.height());
+    }
+    let total: f64 = shapes.iter().map(|s| s.area()).sum();
+    println!("total area {:.2}", total);
+    // invalid shapes cannot be built
+    println!("{:?}", Rhombus::new(pt(0.0, 0.0), 1.0, 180.0));
+    println!("{:?}", Square::new(pt(0.0, 0.0), 0.0));
+    println!("{:?}", Triangle::new(pt(0.0, 0.0), pt(1.0, 1.0), pt(2.0, 2.0)));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn areas_and_perimeters() {
+        let r = Rectangle::new(pt(1.0, 1.0), 4.0, 2.0).unwrap();
+        assert!(close(r.area(), 8.0) && close(r.perimeter(), 12.0));
+        let s = Square::new(pt(0.0, 0.0), 3.0).unwrap();
+        assert!(close(s.area(), 9.0) && close(s.perimeter(), 12.0));
+        let rh = Rhombus::new(pt(0.0, 0.0), 2.0, 30.0).unwrap();
+        assert!(close(rh.area(), 2.0) && close(rh.perimeter(), 8.0));
+        let c = Circle::new(pt(0.0, 0.0), 1.0).unwrap();
+        assert!(close(c.area(), PI) && close(c.perimeter(), 2.0 * PI));
+        let t = Triangle::new(pt(0.0, 0.0), pt(3.0, 0.0), pt(0.0, 4.0)).unwrap();
+        assert!(close(t.area(), 6.0) && close(t.perimeter(), 12.0));
+    }
+
+    #[test]
+    fn rhombus_corners_match_its_area() {
+        // the polygon made of the corners has the same area as the formula
+        for angle in [1.0, 30.0, 60.0, 90.0, 120.0, 179.0] {
+            let rh = Rhombus::new(pt(2.0, -1.0), 3.0, angle).unwrap();
+            assert!(close(signed_area(&rh.corners()).abs(), rh.area()), "{}", angle);
+            assert!(close(polygon_perimeter(&rh.corners()), rh.perimeter()));
+        }
+        // 90° is a square
+        let rh = Rhombus::new(pt(0.0, 0.0), 2.0, 90.0).unwrap();
+        assert!(close(rh.area(), Square::new(pt(0.0, 0.0), 2.0).unwrap().area()));
+        // obtuse and acute give the same rhombus
+        assert_eq!(Rhombus::new(pt(0.0, 0.0), 1.0, 120.0), Rhombus::new(pt(0.0, 0.0), 1.0, 60.0));
+    }
+
+    #[test]
+    fn degenerate_shapes_are_rejected() {
+        let o = pt(0.0, 0.0);
+        assert_eq!(Square::new(o, 0.0), Err(ShapeError::NotPositive { what: "side", value: 0.0 }));
+        assert_eq!(
+            Rectangle::new(o, 1.0, -2.0),
+            Err(ShapeError::NotPositive { what: "height", value: -2.0 })
+        );
+        assert_eq!(Circle::new(o, f64::NAN), Err(ShapeError::NotFinite { what: "radius" }));
+        assert_eq!(Circle::new(pt(f64::INFINITY, 0.0), 1.0), Err(ShapeError::NotFinite { what: "center" }));
+        assert_eq!(Rhombus::new(o, 1.0, 0.0), Err(ShapeError::AngleOutOfRange(0.0)));
+        assert_eq!(Rhombus::new(o, 1.0, 180.0), Err(ShapeError::AngleOutOfRange(180.0)));
+        assert_eq!(Rhombus::new(o, 1.0, -30.0), Err(ShapeError::AngleOutOfRange(-30.0)));
+        assert!(matches!(Rhombus::new(o, 0.0, 60.0), Err(ShapeError::NotPositive { .. })));
+        assert!(matches!(Triangle::new(o, pt(1.0, 1.0), pt(3.0, 3.0)), Err(ShapeError::Degenerate(_))));
+        assert!(matches!(Triangle::new(o, o, pt(1.0, 0.0)), Err(ShapeError::Degenerate(_))));
+        assert_eq!(Polygon::new(vec![o, pt(1.0, 0.0)]), Err(ShapeError::TooFewVertices(2)));
+        // a bow tie: its two triangles have opposite orientation, the area sums to 0 ...
+        let bow_tie = vec![o, pt(2.0, 2.0), pt(2.0, 0.0), pt(0.0, 2.0)];
+        assert!(matches!(Polygon::new(bow_tie), Err(ShapeError::Degenerate(_))));
+        // ... this one does not, but it crosses itself all the same
+        let crossing = vec![o, pt(4.0, 0.0), pt(4.0, 4.0), pt(1.0, -1.0), pt(0.0, 4.0)];
+        assert_eq!(Polygon::new(crossing), Err(ShapeError::Degenerate("the border crosses itself")));
+    }
+
+    #[test]
+    fn scaling_needs_a_positive_factor() {
+        let mut s = Square::new(pt(0.0, 0.0), 1.0).unwrap();
+        assert!(matches!(s.scale(0.0), Err(ShapeError::NotPositive { .. })));
+        assert!(s.scale(-1.0).is_err());
+        assert!(close(s.area(), 1.0), "a failed scale changes nothing");
+        s.scale(3.0).unwrap();
+        assert!(close(s.area(), 9.0));
+    }
+
+    #[test]
+    fn non_finite_angles_and_results_are_rejected() {
+        let mut shapes: Vec<Box<dyn Shape>> = vec![
+            Box::new(Square::new(pt(0.0, 0.0), 2.0).unwrap()),
+            Box::new(Rhombus::new(pt(0.0, 0.0), 2.0, 60.0).unwrap()),
+            Box::new(Circle::new(pt(0.0, 0.0), 2.0).unwrap()),
+            Box::new(Triangle::new(pt(0.0, 0.0), pt(2.0, 0.0), pt(0.0, 2.0)).unwrap()),
+        ];
+        for s in shapes.iter_mut() {
+            for bad in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+                assert_eq!(s.rotate(bad), Err(ShapeError::NotFinite { what: "angle" }), "{}", s.name());
+            }
+            // the factor is fine, the result is not
+            assert!(matches!(s.scale(f64::MAX), Err(ShapeError::NotFinite { .. })), "{}", s.name());
+            assert!(s.contains_point(pt(0.1, 0.1)) && !s.contains_point(pt(3.0, 3.0)), "{} unchanged", s.name());
+        }
+        let mut far = Triangle::new(pt(-1.5e308, 0.0), pt(1.5e308, 0.0), pt(0.0, 1.5e308)).unwrap();
+        let before = far.clone();
+        assert!(far.rotate(90.0).is_err());
+        assert_eq!(far, before);
+    }
+
+    #[test]
+    fn contains_point_inside_border_outside() {
+        let r = Rectangle::new(pt(0.0, 0.0), 4.0, 2.0).unwrap();
+        assert!(r.contains_point(pt(0.0, 0.0)));
+        assert!(r.contains_point(pt(2.0, 1.0)), "a corner is on the border");
+        assert!(r.contains_point(pt(0.0, -1.0)), "a point on an edge");
+        assert!(!r.contains_point(pt(2.1, 0.0)));
+        let c = Circle::new(pt(1.0, 1.0), 1.0).unwrap();
+        assert!(c.contains_point(pt(2.0, 1.0)) && !c.contains_point(pt(2.0, 2.0)));
+        // a non convex polygon: the notch at the top is outside
+        let arrow = Polygon::new(vec![pt(0.0, 0.0), pt(4.0, 0.0), pt(4.0, 4.0), pt(2.0, 1.0), pt(0.0, 4.0)]).unwrap();
+        assert!(arrow.contains_point(pt(1.0, 1.0)));
+        assert!(!arrow.contains_point(pt(2.0, 3.0)));
+        assert!(arrow.contains_point(pt(2.0, 1.0)));
+    }
+
+    #[test]
+    fn rotation_keeps_area_and_moves_the_box() {
+        let mut r = Rectangle::new(pt(0.0, 0.0), 4.0, 2.0).unwrap();
+        r.rotate(90.0).unwrap();
+        let bb = r.bounding_box();
+        assert!(close(bb.width(), 2.0) && close(bb.height(), 4.0));
+        assert!(close(r.area(), 8.0));
+        assert!(r.contains_point(pt(0.0, 1.9)) && !r.contains_point(pt(1.9, 0.0)));
+
+        let mut s = Square::new(pt(0.0, 0.0), 2.0).unwrap();
+        s.rotate(45.0).unwrap();
+        let bb = s.bounding_box();
+        assert!(close(bb.width(), 2.0 * 2f64.sqrt()));
+        assert!(s.contains_point(pt(1.4, 0.0)) && !s.contains_point(pt(0.9, 0.9)));
+
+        let mut t = Triangle::new(pt(0.0, 0.0), pt(3.0, 0.0), pt(0.0, 3.0)).unwrap();
+        let before = t.perimeter();
+        t.rotate(33.0).unwrap();
+        t.scale(2.0).unwrap();
+        assert!(close(t.area(), 18.0) && close(t.perimeter(), 2.0 * before));
+    }
+
+    #[test]
+    fn shapes_as_trait_objects() {
+        let shapes: Vec<Box<dyn Shape>> = vec![
+            Box::new(Square::new(pt(0.0, 0.0), 1.0).unwrap()),
+            Box::new(Circle::new(pt(0.0, 0.0), 1.0).unwrap()),
+        ];
+        let names: Vec<&str> = shapes.iter().map(|s| s.name()).collect();
+        assert_eq!(names, vec!["square", "circle"]);
+        assert_eq!(shapes[0].describe(), "square with area 1.00 and perimeter 4.00");
+    }
+}
@@ -0,0 +1,428 @@
+/// This module grows the `Show` trait of `c10_OOP` into a small alternative to `Debug`:
+///     Show for std types   every primitive, plus `Vec`, `Option`, tuples, `HashMap`, `Box` and `&`
+///     blanket impls        `impl<T: Show> Show for Vec<T>`: one impl for infinitely many types
+///     Doc                  the STRUCTURE of a shown value, not just its text
+///     Printer              lays a `Doc` out within a width: compact, expanded, or whatever fits
+/// To make your own type printable, implement `show`, and override `doc` if it has parts
+/// (see `Point` at the bottom of this file).
+/// See
+///         https://doc.rust-lang.org/book/ch10-02-traits.html#using-trait-bounds-to-conditionally-implement-methods
+///         https://doc.rust-lang.org/std/fmt/trait.Debug.html
+///         https://homepages.inf.ed.ac.uk/wadler/papers/prettier/prettier.pdf
+
+use std::collections::HashMap;
+
+use crate::classes::c10_OOP::Show;
+
+/* ===== Doc =====
+   ========================= */
+
+/// How a value is built out of smaller values, so a printer can decide where to break lines.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Doc {
+    /// never broken
+    Text(String),
+    /// `open item, item, ... close`, e.g. `[1, 2]` or `Some(x)`;
+    /// `spaced` puts spaces inside the brackets on one line: `Point { x: 1 }`
+    Group { open: String, items: Vec<Doc>, close: String, spaced: bool },
+    /// `key: value`, as in maps and structs
+    Entry(Box<Doc>, Box<Doc>),
+}
+
+impl Doc {
+    pub fn text(s: impl Into<String>) -> Doc {
+        Doc::Text(s.into())
+    }
+
+    pub fn group(open: &str, items: Vec<Doc>, close: &str) -> Doc {
+        Doc::Group { open: open.to_string(), items, close: close.to_string(), spaced: false }
+    }
+
+    pub fn entry(key: Doc, value: Doc) -> Doc {
+        Doc::Entry(Box::new(key), Box::new(value))
+    }
+
+    /// a struct-like value: `Name { field: value, ... }`
+    pub fn record(name: &str, fields: Vec<(&str, Doc)>) -> Doc {
+        let items = fields.into_iter().map(|(f, d)| Doc::entry(Doc::text(f), d)).collect();
+        Doc::Group { open: format!("{} {{", name), items, close: String::from("}"), spaced: true }
+    }
+
+    /// everything on one line
+    pub fn compact(&self) -> String {
+        match self {
+            Doc::Text(s) => s.clone(),
+            Doc::Group { open, items, close, .. } if items.is_empty() => format!("{}{}", open, close),
+            Doc::Group { open, items, close, spaced } => {
+                let inner: Vec<String> = items.iter().map(Doc::compact).collect();
+                let pad = if *spaced { " " } else { "" };
+                format!("{}{}{}{}{}", open, pad, inner.join(", "), pad, close)
+            }
+            Doc::Entry(k, v) => format!("{}: {}", k.compact(), v.compact()),
+        }
+    }
+}
+
+/* ===== Show for std types =====
+   ========================= */
+
+// `c10_OOP` already shows i32, f64 and String; here come the rest of the primitives,
+// described in the same way. A macro writes the (almost identical) impls for us.
+// This is allowed by the orphan rule because the TRAIT is ours, even if the types are not
+macro_rules! show_number {
+    ($($t:ty => $what:expr),* $(,)?) => {
+        $(impl Show for $t {
+            fn show(&self) -> String {
+                format!("{} {}", $what, self)
+            }
+        })*
+    };
+}
+
+show_number! {
+    i8 => "one-byte signed",
+    i16 => "two-byte signed",
+    i64 => "eight-byte signed",
+    i128 => "sixteen-byte signed",
+    isize => "pointer-sized signed",
+    u8 => "one-byte unsigned",
+    u16 => "two-byte unsigned",
+    u32 => "four-byte unsigned",
+    u64 => "eight-byte unsigned",
+    u128 => "sixteen-byte unsigned",
+    usize => "pointer-sized unsigned",
+    f32 => "four-byte float",
+    bool => "boolean",
+    char => "character",
+}
+
+// `str` has no size known at compile time, but a trait can still be implemented for it:
+// it is then used through a pointer, `&str` or `Box<str>`
+impl Show for str {
+    fn show(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Show for () {
+    fn show(&self) -> String {
+        String::from("unit")
+    }
+}
+
+// Blanket impls: for EVERY `T` that is `Show`, these types are `Show` too.
+// `?Sized` lifts the default requirement that `T` has a known size,
+// which is what makes `Box<dyn Show>` and `&str` `Show` as well
+impl<T: Show + ?Sized> Show for &T {
+    fn show(&self) -> String {
+        (**self).show()
+    }
+    fn doc(&self) -> Doc {
+        (**self).doc()
+    }
+}
+
+impl<T: Show + ?Sized> Show for Box<T> {
+    fn show(&self) -> String {
+        (**self).show()
+    }
+    fn doc(&self) -> Doc {
+        (**self).doc()
+    }
+}
+
+// containers override `doc` and derive `show` from it, so the two always agree
+impl<T: Show> Show for Vec<T> {
+    fn show(&self) -> String {
+        self.doc().compact()
+    }
+    fn doc(&self) -> Doc {
+        Doc::group("[", self.iter().map(Show::doc).collect(), "]")
+    }
+}
+
+impl<T: Show> Show for Option<T> {
+    fn show(&self) -> String {
+        self.doc().compact()
+    }
+    fn doc(&self) -> Doc {
+        match self {
+            Some(x) => Doc::group("Some(", vec![x.doc()], ")"),
+            None => Doc::text("None"),
+        }
+    }
+}
+
+// a `HashMap` iterates in no particular order; sorting the entries by their text
+// makes the output the same on every run, which tests (and humans) need
+impl<K: Show, V: Show, S> Show for HashMap<K, V, S> {
+    fn show(&self) -> String {
+        self.doc().compact()
+    }
+    fn doc(&self) -> Doc {
+        let mut entries: Vec<(String, Doc)> =
+            self.iter().map(|(k, v)| (k.show(), Doc::entry(k.doc(), v.doc()))).collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        Doc::group("{", entries.into_iter().map(|(_, d)| d).collect(), "}")
+    }
+}
+
+// one impl per tuple size, written by a macro: `$name` are the type parameters,
+// `$idx` the field numbers (`self.0`, `self.1`, ...)
+macro_rules! show_tuple {
+    ($(($($name:ident . $idx:tt),+)),* $(,)?) => {
+        $(impl<$($name: Show),+> Show for ($($name,)+) {
+            fn show(&self) -> String {
+                self.doc().compact()
+            }
+            fn doc(&self) -> Doc {
+                Doc::group("(", vec![$(self.$idx.doc()),+], ")")
+            }
+        })*
+    };
+}
+
+show_tuple! {
+    (A.0, B.1),
+    (A.0, B.1, C.2),
+    (A.0, B.1, C.2, D.3),
+    (A.0, B.1, C.2, D.3, E.4),
+    (A.0, B.1, C.2, D.3, E.4, F.5),
+}
+
+/* ===== Printer =====
+   ========================= */
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// every value on one line, cut at the width with `...`
+    Compact,
+    /// every group broken, one item per line
+    Expanded,
+    /// groups stay on one line when they fit in the width, and are broken otherwise
+    Fit,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Printer {
+    pub width: usize,
+    pub indent: usize,
+    pub mode: Mode,
+}
+
+impl Default for Printer {
+    fn default() -> Self {
+        Printer { width: 80, indent: 4, mode: Mode::Fit }
+    }
+}
+
+// text width in characters, not bytes: `é` is one column
+fn width_of(s: &str) -> usize {
+    s.chars().count()
+}
+
+impl Printer {
+    pub fn new(width: usize, indent: usize, mode: Mode) -> Printer {
+        Printer { width, indent, mode }
+    }
+
+    pub fn pretty(&self, value: &dyn Show) -> String {
+        let doc = value.doc();
+        if self.mode == Mode::Compact {
+            return self.cut(doc.compact());
+        }
+        let mut out = String::new();
+        self.layout(&doc, 0, 0, 0, &mut out);
+        out
+    }
+
+    /// a heterogeneous vector, laid out as one list
+    pub fn pretty_all(&self, values: &[Box<dyn Show>]) -> String {
+        // `&[Box<dyn Show>]` is not a `Vec`, but `Vec<&Box<dyn Show>>` is `Show`
+        // thanks to the blanket impls above
+        let refs: Vec<&Box<dyn Show>> = values.iter().collect();
+        self.pretty(&refs)
+    }
+
+    fn cut(&self, line: String) -> String {
+        if width_of(&line) <= self.width {
+            return line;
+        }
+        // the ellipsis must fit too: below 3 columns it gets shorter
+        let dots = self.width.min(3);
+        let mut cut: String = line.chars().take(self.width - dots).collect();
+        cut.push_str(&".".repeat(dots));
+        cut
+    }
+
+    // `used` is how many columns of the current line are already taken,
+    // `trailing` how many will follow on it (the `,` after an item)
+    fn layout(&self, doc: &Doc, depth: usize, used: usize, trailing: usize, out: &mut String) {
+        match doc {
+            Doc::Text(s) => out.push_str(s),
+            Doc::Entry(k, v) => {
+                let key = format!("{}: ", k.compact());
+                out.push_str(&key);
+                self.layout(v, depth, used + width_of(&key), trailing, out);
+            }
+            Doc::Group { open, items, close, .. } => {
+                let flat = doc.compact();
+                let fits = used + width_of(&flat) + trailing <= self.width;
+                if items.is_empty() || (self.mode == Mode::Fit && fits) {
+                    out.push_str(&flat);
+                    return;
+                }
+                let inner = " ".repeat((depth + 1) * self.indent);
+                out.push_str(open);
+                out.push('\n');
+                for item in items {
+                    out.push_str(&inner);
+                    self.layout(item, depth + 1, width_of(&inner), 1, out);
+                    out.push_str(",\n");
+                }
+                out.push_str(&" ".repeat(depth * self.indent));
+                out.push_str(close);
+            }
+        }
+    }
+}
+
+/* ===== Extending it yourself =====
+   ========================= */
+
+// a type of our own: `show` for one line, `doc` to tell the printer about its parts
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Show for Point {
+    fn show(&self) -> String {
+        self.doc().compact()
+    }
+    fn doc(&self) -> Doc {
+        Doc::record("Point", vec![("x", self.x.doc()), ("y", self.y.doc())])
+    }
+}
+
+// EXERCISE: implement `Show` for `BTreeMap` and `HashSet`, and for `c10_OOP::Foo` as a record
+
+pub fn showexample() {
+    let mut scores = HashMap::new();
+    scores.insert("blue".to_string(), vec![10u8, 50]);
+    scores.insert("yellow".to_string(), vec![]);
+    let values: Vec<Box<dyn Show>> = vec![
+        Box::new(42),
+        Box::new("hello"),
+        Box::new(Some((true, 'x'))),
+        Box::new(None::<u8>),
+        Box::new(scores),
+        Box::new(Point { x: 1, y: -2 }),
+    ];
+    for mode in [Mode::Compact, Mode::Fit, Mode::Expanded] {
+        println!("{:?}:", mode);
+        println!("{}", Printer::new(60, 2, mode).pretty_all(&values));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn primitives() {
+        assert_eq!(7u8.show(), "one-byte unsigned 7");
+        assert_eq!((-1i64).show(), "eight-byte signed -1");
+        assert_eq!(1.5f32.show(), "four-byte float 1.5");
+        assert_eq!(true.show(), "boolean true");
+        assert_eq!('z'.show(), "character z");
+        assert_eq!("hi".show(), "hi");
+        assert_eq!(().show(), "unit");
+        // the impls from c10_OOP
+        assert_eq!(3.show(), "four-byte signed 3");
+        assert_eq!(String::from("s").show(), "s");
+    }
+
+    #[test]
+    fn containers() {
+        assert_eq!(vec![1u8, 2].show(), "[one-byte unsigned 1, one-byte unsigned 2]");
+        assert_eq!(Vec::<u8>::new().show(), "[]");
+        assert_eq!(Some("x").show(), "Some(x)");
+        assert_eq!(None::<i8>.show(), "None");
+        assert_eq!(("a", true).show(), "(a, boolean true)");
+        assert_eq!(("a", "b", "c", "d", "e", "f").show(), "(a, b, c, d, e, f)");
+        let mut m = HashMap::new();
+        m.insert("b", vec![Some("x")]);
+        m.insert("a", vec![]);
+        assert_eq!(m.show(), "{a: [], b: [Some(x)]}");
+        assert_eq!(Point { x: 1, y: 2 }.show(), "Point { x: four-byte signed 1, y: four-byte signed 2 }");
+        let boxed: Box<dyn Show> = Box::new(vec!["a"]);
+        assert_eq!(boxed.show(), "[a]");
+    }
+
+    fn sample() -> Vec<Box<dyn Show>> {
+        vec![Box::new("one"), Box::new(vec![("k", "v"), ("kk", "vv")]), Box::new(None::<u8>)]
+    }
+
+    #[test]
+    fn expanded_breaks_every_group() {
+        let p = Printer::new(80, 2, Mode::Expanded);
+        assert_eq!(
+            p.pretty_all(&sample()),
+            "[\n  one,\n  [\n    (\n      k,\n      v,\n    ),\n    (\n      kk,\n      vv,\n    ),\n  ],\n  None,\n]"
+        );
+    }
+
+    #[test]
+    fn fit_breaks_only_what_does_not_fit() {
+        // the whole thing is 31 columns wide
+        let flat = "[one, [(k, v), (kk, vv)], None]";
+        assert_eq!(Printer::new(40, 2, Mode::Fit).pretty_all(&sample()), flat);
+        // with its indentation and comma, the inner list takes 2 + 18 + 1 columns: it fits in 21, not in 20
+        assert_eq!(
+            Printer::new(21, 2, Mode::Fit).pretty_all(&sample()),
+            "[\n  one,\n  [(k, v), (kk, vv)],\n  None,\n]"
+        );
+        assert_eq!(
+            Printer::new(20, 2, Mode::Fit).pretty_all(&sample()),
+            "[\n  one,\n  [\n    (k, v),\n    (kk, vv),\n  ],\n  None,\n]"
+        );
+    }
+
+    #[test]
+    fn fit_lines_stay_within_width_when_possible() {
+        let mut m = HashMap::new();
+        m.insert("key", vec![Some(1u8); 4]);
+        m.insert("other", vec![]);
+        // `        Some(one-byte unsigned 1),` is exactly 34 columns
+        let p = Printer::new(34, 4, Mode::Fit);
+        let out = p.pretty(&m);
+        assert!(out.lines().all(|l| width_of(l) <= 34), "{}", out);
+        assert_eq!(
+            out,
+            "{\n    key: [\n        Some(one-byte unsigned 1),\n        Some(one-byte unsigned 1),\n        \
+             Some(one-byte unsigned 1),\n        Some(one-byte unsigned 1),\n    ],\n    other: [],\n}"
+        );
+    }
+
+    #[test]
+    fn compact_cuts_at_width() {
+        let p = Printer::new(12, 2, Mode::Compact);
+        assert_eq!(p.pretty_all(&sample()), "[one, [(k...");
+        assert_eq!(p.pretty(&"ééé"), "ééé");
+        // never wider than asked, not even the ellipsis
+        assert_eq!(Printer::new(3, 2, Mode::Compact).pretty(&"abcd"), "...");
+        assert_eq!(Printer::new(2, 2, Mode::Compact).pretty(&"abc"), "..");
+        assert_eq!(Printer::new(1, 2, Mode::Compact).pretty(&"abc"), ".");
+        assert_eq!(Printer::new(0, 2, Mode::Compact).pretty(&"abc"), "");
+    }
+
+    #[test]
+    fn records_expand_like_groups() {
+        let p = Printer::new(10, 2, Mode::Fit);
+        assert_eq!(
+            p.pretty(&Point { x: 1, y: 2 }),
+            "Point {\n  x: four-byte signed 1,\n  y: four-byte signed 2,\n}"
+        );
+    }
+}
@@ -0,0 +1,695 @@
+/// This module grows `c10_OOP::AveragedCollection` into a full statistics container.
+/// Like there, all fields are private and every cached value is kept in sync by `add` and `remove`;
+/// unlike there, nothing is recomputed from scratch:
+///     count, mean, variance   Welford's online algorithm, O(1)
+///     min, max                a sorted multiset (`BTreeMap` value -> count), O(log n)
+///     percentiles             an order-statistic tree (a treap), O(log n) expected
+///     median                  two heaps, the lower half and the upper half, O(log n)
+///     mode                    a `BTreeSet` of (count, value) pairs, O(log n)
+/// See
+///         https://doc.rust-lang.org/book/ch17-01-what-is-oo.html#encapsulation-that-hides-implementation-details
+///         https://en.wikipedia.org/wiki/Algorithms_for_calculating_variance#Welford's_online_algorithm
+///         https://en.wikipedia.org/wiki/Percentile#The_nearest-rank_method
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap};
+use std::fmt;
+use std::hash::Hash;
+
+/// The values a `Stats` can hold: totally ordered (for the heaps and the sorted multiset),
+/// hashable (for the lazy deletions) and convertible to `f64` (for mean and variance).
+// `f64` itself is not `Ord` (NaN is not comparable), so it is not a `Sample`
+pub trait Sample: Copy + Ord + Hash + fmt::Display {
+    fn to_f64(self) -> f64;
+}
+
+// one `impl` per integer type, written once by a macro
+macro_rules! impl_sample {
+    ($($t:ty),*) => {
+        $(impl Sample for $t {
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+        })*
+    };
+}
+impl_sample!(i8, i16, i32, i64, u8, u16, u32, u64, usize, isize);
+
+/* ===== Median: two heaps =====
+   ========================= */
+
+// `low` is a max-heap with the smaller half, `high` a min-heap with the larger half,
+// and `low` holds the extra element when the count is odd: the median is on top of them.
+// A `BinaryHeap` can only remove its top, so any other removal is LAZY:
+// the value is recorded in `deleted` and only thrown away once it surfaces at a top.
+// `low_len` and `high_len` count the live elements, which is what the balancing looks at
+#[derive(Debug, Clone)]
+struct Median<T: Sample> {
+    low: BinaryHeap<T>,
+    high: BinaryHeap<Reverse<T>>,
+    deleted: HashMap<T, usize>,
+    low_len: usize,
+    high_len: usize,
+}
+
+impl<T: Sample> Median<T> {
+    fn new() -> Self {
+        Median {
+            low: BinaryHeap::new(),
+            high: BinaryHeap::new(),
+            deleted: HashMap::new(),
+            low_len: 0,
+            high_len: 0,
+        }
+    }
+
+    fn add(&mut self, value: T) {
+        match self.low.peek() {
+            Some(&top) if value > top => {
+                self.high.push(Reverse(value));
+                self.high_len += 1;
+            }
+            _ => {
+                self.low.push(value);
+                self.low_len += 1;
+            }
+        }
+        self.rebalance();
+    }
+
+    // `value` must be present: `Stats::remove` checks that first
+    fn remove(&mut self, value: T) {
+        *self.deleted.entry(value).or_insert(0) += 1;
+        // every live element of `low` is <= its top, every live element of `high` is >= it,
+        // and the tops are always live, so the comparison tells which half `value` is in
+        let top = *self.low.peek().expect("a present value leaves `low` non-empty");
+        if value <= top {
+            self.low_len -= 1;
+            if value == top {
+                self.prune_low();
+            }
+        } else {
+            self.high_len -= 1;
+            if self.high.peek() == Some(&Reverse(value)) {
+                self.prune_high();
+            }
+        }
+        self.rebalance();
+    }
+
+    // pops deleted values off the top, so that the top is live again
+    fn prune_low(&mut self) {
+        while let Some(&top) = self.low.peek() {
+            match self.deleted.get_mut(&top) {
+                Some(n) if *n > 0 => {
+                    *n -= 1;
+                    self.low.pop();
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn prune_high(&mut self) {
+        while let Some(&Reverse(top)) = self.high.peek() {
+            match self.deleted.get_mut(&top) {
+                Some(n) if *n > 0 => {
+                    *n -= 1;
+                    self.high.pop();
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn rebalance(&mut self) {
+        if self.low_len > self.high_len + 1 {
+            let top = self.low.pop().unwrap();
+            self.high.push(Reverse(top));
+            self.low_len -= 1;
+            self.high_len += 1;
+            self.prune_low();
+        } else if self.low_len < self.high_len {
+            let Reverse(top) = self.high.pop().unwrap();
+            self.low.push(top);
+            self.high_len -= 1;
+            self.low_len += 1;
+            self.prune_high();
+        }
+        // the deleted values that never surfaced are forgotten once both halves are empty
+        if self.low_len == 0 {
+            *self = Median::new();
+        }
+    }
+
+    fn median(&self) -> Option<f64> {
+        let lo = self.low.peek()?.to_f64();
+        if self.low_len > self.high_len {
+            Some(lo)
+        } else {
+            let Reverse(hi) = self.high.peek()?;
+            Some((lo + hi.to_f64()) / 2.0)
+        }
+    }
+}
+
+/* ===== Percentiles: an order-statistic tree =====
+   ========================= */
+
+// A search tree whose nodes also know how many values their subtree holds (`size`).
+// The k-th smallest value is then found going down a single path:
+// compare k with the size of the left subtree, and go left, stop, or go right.
+// A plain search tree fed sorted values degenerates into a list, so this one is a TREAP:
+// each node also gets a random priority, and the tree is kept a max-heap on priorities.
+// The shape is then the one of a tree built in random order: O(log n) deep, expected.
+// Each distinct value has one node, with its `count`: `size` adds up counts, not nodes
+type Tree<T> = Option<Box<Node<T>>>;
+
+#[derive(Debug, Clone)]
+struct Node<T> {
+    value: T,
+    count: usize,
+    size: usize,
+    priority: u64,
+    left: Tree<T>,
+    right: Tree<T>,
+}
+
+fn size<T>(t: &Tree<T>) -> usize {
+    t.as_ref().map_or(0, |n| n.size)
+}
+
+fn fix_size<T>(n: &mut Node<T>) {
+    n.size = n.count + size(&n.left) + size(&n.right);
+}
+
+// splits `t` into the values smaller than `value`, and the others
+fn split<T: Sample>(t: Tree<T>, value: T) -> (Tree<T>, Tree<T>) {
+    match t {
+        None => (None, None),
+        Some(mut n) if n.value < value => {
+            let (less, rest) = split(n.right.take(), value);
+            n.right = less;
+            fix_size(&mut n);
+            (Some(n), rest)
+        }
+        Some(mut n) => {
+            let (less, rest) = split(n.left.take(), value);
+            n.left = rest;
+            fix_size(&mut n);
+            (less, Some(n))
+        }
+    }
+}
+
+// joins two trees, all the values of `a` being smaller than those of `b`:
+// the root with the higher priority stays on top
+fn merge<T>(a: Tree<T>, b: Tree<T>) -> Tree<T> {
+    match (a, b) {
+        (None, t) | (t, None) => t,
+        (Some(mut a), Some(b)) if a.priority > b.priority => {
+            a.right = merge(a.right.take(), Some(b));
+            fix_size(&mut a);
+            Some(a)
+        }
+        (Some(a), Some(mut b)) => {
+            b.left = merge(Some(a), b.left.take());
+            fix_size(&mut b);
+            Some(b)
+        }
+    }
+}
+
+// one more occurrence of `value`, if it already has a node; `false` if it has none
+fn increment<T: Sample>(t: &mut Tree<T>, value: T) -> bool {
+    let n = match t {
+        Some(n) => n,
+        None => return false,
+    };
+    let found = match value.cmp(&n.value) {
+        Ordering::Less => increment(&mut n.left, value),
+        Ordering::Greater => increment(&mut n.right, value),
+        Ordering::Equal => {
+            n.count += 1;
+            true
+        }
+    };
+    if found {
+        n.size += 1;
+    }
+    found
+}
+
+// one less occurrence of `value`, which must be present: the node goes when its count is 0
+fn decrement<T: Sample>(t: &mut Tree<T>, value: T) {
+    let n = match t {
+        Some(n) => n,
+        None => return,
+    };
+    n.size -= 1;
+    match value.cmp(&n.value) {
+        Ordering::Less => decrement(&mut n.left, value),
+        Ordering::Greater => decrement(&mut n.right, value),
+        Ordering::Equal => {
+            n.count -= 1;
+            if n.count == 0 {
+                let (left, right) = (n.left.take(), n.right.take());
+                *t = merge(left, right);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct OrderTree<T> {
+    root: Tree<T>,
+    // state of the pseudo-random priorities
+    seed: u64,
+}
+
+impl<T: Sample> OrderTree<T> {
+    fn new() -> Self {
+        OrderTree { root: None, seed: 0x2545_f491_4f6c_dd1d }
+    }
+
+    // xorshift: a few shifts give numbers random enough for priorities, without a dependency
+    fn next_priority(&mut self) -> u64 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        self.seed
+    }
+
+    fn add(&mut self, value: T) {
+        if !increment(&mut self.root, value) {
+            let priority = self.next_priority();
+            let node = Box::new(Node { value, count: 1, size: 1, priority, left: None, right: None });
+            let (less, rest) = split(self.root.take(), value);
+            self.root = merge(merge(less, Some(node)), rest);
+        }
+    }
+
+    // `value` must be present: `Stats::remove` checks that first
+    fn remove(&mut self, value: T) {
+        decrement(&mut self.root, value);
+    }
+
+    // the value at position `k` (from 0) in increasing order
+    fn select(&self, mut k: usize) -> Option<T> {
+        let mut t = self.root.as_deref();
+        while let Some(n) = t {
+            let left = size(&n.left);
+            if k < left {
+                t = n.left.as_deref();
+            } else if k < left + n.count {
+                return Some(n.value);
+            } else {
+                k -= left + n.count;
+                t = n.right.as_deref();
+            }
+        }
+        None
+    }
+}
+
+/* ===== Stats =====
+   ========================= */
+
+#[derive(Debug, Clone)]
+pub struct Stats<T: Sample> {
+    // the sorted multiset: min and max
+    counts: BTreeMap<T, usize>,
+    // the same multiset, indexed by position: percentiles
+    order: OrderTree<T>,
+    // (how often, which value): the last pair is the mode;
+    // `Reverse` makes the SMALLEST value win among equally frequent ones
+    by_count: BTreeSet<(usize, Reverse<T>)>,
+    median: Median<T>,
+    // Welford: running mean and sum of squared distances from it
+    len: usize,
+    mean: f64,
+    m2: f64,
+}
+
+impl<T: Sample> Default for Stats<T> {
+    fn default() -> Self {
+        Stats::new()
+    }
+}
+
+impl<T: Sample> Stats<T> {
+    pub fn new() -> Self {
+        Stats {
+            counts: BTreeMap::new(),
+            order: OrderTree::new(),
+            by_count: BTreeSet::new(),
+            median: Median::new(),
+            len: 0,
+            mean: 0.0,
+            m2: 0.0,
+        }
+    }
+
+    pub fn add(&mut self, value: T) {
+        let count = self.counts.entry(value).or_insert(0);
+        self.by_count.remove(&(*count, Reverse(value)));
+        *count += 1;
+        self.by_count.insert((*count, Reverse(value)));
+        self.median.add(value);
+        self.order.add(value);
+
+        // Welford: the new mean moves by delta/n; m2 grows by delta times the distance to the NEW mean
+        self.len += 1;
+        let x = value.to_f64();
+        let delta = x - self.mean;
+        self.mean += delta / self.len as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    /// removes one occurrence of `value`; `false` if there was none
+    pub fn remove(&mut self, value: T) -> bool {
+        let count = match self.counts.get_mut(&value) {
+            Some(c) => c,
+            None => return false,
+        };
+        self.by_count.remove(&(*count, Reverse(value)));
+        *count -= 1;
+        if *count == 0 {
+            self.counts.remove(&value);
+        } else {
+            self.by_count.insert((*count, Reverse(value)));
+        }
+        self.median.remove(value);
+        self.order.remove(value);
+
+        // Welford backwards: undo the two updates of `add`, in reverse order
+        self.len -= 1;
+        if self.len <= 1 {
+            // with one value (or none) left the answer is known exactly:
+            // start afresh instead of carrying the rounding errors over
+            self.mean = self.min().map_or(0.0, T::to_f64);
+            self.m2 = 0.0;
+        } else {
+            let x = value.to_f64();
+            let delta = x - self.mean;
+            self.mean -= delta / self.len as f64;
+            self.m2 -= delta * (x - self.mean);
+            // rounding can push a sum of squares a hair below zero
+            self.m2 = self.m2.max(0.0);
+        }
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn min(&self) -> Option<T> {
+        self.counts.keys().next().copied()
+    }
+
+    pub fn max(&self) -> Option<T> {
+        self.counts.keys().next_back().copied()
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        if self.is_empty() { None } else { Some(self.mean) }
+    }
+
+    /// the middle value, or the average of the two middle ones
+    pub fn median(&self) -> Option<f64> {
+        self.median.median()
+    }
+
+    /// population variance: the mean squared distance from the mean
+    pub fn variance(&self) -> Option<f64> {
+        if self.is_empty() { None } else { Some(self.m2 / self.len as f64) }
+    }
+
+    /// sample variance, dividing by n - 1: needs at least two values
+    pub fn sample_variance(&self) -> Option<f64> {
+        if self.len < 2 { None } else { Some(self.m2 / (self.len - 1) as f64) }
+    }
+
+    pub fn std_dev(&self) -> Option<f64> {
+        self.variance().map(f64::sqrt)
+    }
+
+    /// the most frequent value and how often it occurs; the smallest one on ties
+    pub fn mode(&self) -> Option<(T, usize)> {
+        self.by_count.iter().next_back().map(|&(count, Reverse(value))| (value, count))
+    }
+
+    /// nearest-rank percentile: the smallest value with at least `p`% of the values at or below it.
+    /// `p` is clamped to 0..=100; `percentile(0.0)` is the minimum.
+    pub fn percentile(&self, p: f64) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        let p = if p.is_nan() { 0.0 } else { p.clamp(0.0, 100.0) };
+        let rank = ((p / 100.0 * self.len as f64).ceil() as usize).max(1);
+        // `rank` counts from 1, positions from 0
+        self.order.select(rank - 1)
+    }
+
+    /// all values, in increasing order
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        self.counts
+            .iter()
+            .flat_map(|(&value, &count)| std::iter::repeat_n(value, count))
+    }
+}
+
+impl<T: Sample> FromIterator<T> for Stats<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut s = Stats::new();
+        s.extend(iter);
+        s
+    }
+}
+
+impl<T: Sample> Extend<T> for Stats<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for v in iter {
+            self.add(v);
+        }
+    }
+}
+
+impl<T: Sample> fmt::Display for Stats<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // every statistic is `Some` exactly when the container is not empty
+        let (min, max, mean, median, sd, (mode, times)) = match (
+            self.min(),
+            self.max(),
+            self.mean(),
+            self.median(),
+            self.std_dev(),
+            self.mode(),
+        ) {
+            (Some(a), Some(b), Some(c), Some(d), Some(e), Some(g)) => (a, b, c, d, e, g),
+            _ => return writeln!(f, "count     0"),
+        };
+        writeln!(f, "count     {}", self.len())?;
+        writeln!(f, "min       {}", min)?;
+        writeln!(f, "max       {}", max)?;
+        writeln!(f, "mean      {:.3}", mean)?;
+        writeln!(f, "median    {:.3}", median)?;
+        writeln!(f, "std dev   {:.3}", sd)?;
+        writeln!(f, "mode      {} (x{})", mode, times)?;
+        for p in [25.0, 75.0, 90.0, 99.0] {
+            if let Some(v) = self.percentile(p) {
+                writeln!(f, "p{:<9}{}", p, v)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn statsexample() {
+    let mut s: Stats<i32> = [4, 8, 15, 16, 23, 42, 8].into_iter().collect();
+    print!("{}", s);
+    s.remove(42);
+    s.add(1);
+    println!("after replacing 42 with 1: median {:?}, mode {:?}", s.median(), s.mode());
+    println!("{}", Stats::<u8>::new());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    // the same statistics, recomputed from scratch on a plain sorted `Vec`
+    struct Naive {
+        min: i32,
+        max: i32,
+        mean: f64,
+        median: f64,
+        variance: f64,
+        mode: (i32, usize),
+    }
+
+    // `v` must be sorted
+    fn naive(v: &[i32]) -> Option<Naive> {
+        if v.is_empty() {
+            return None;
+        }
+        let n = v.len();
+        let mean = v.iter().map(|&x| x as f64).sum::<f64>() / n as f64;
+        let variance = v.iter().map(|&x| (x as f64 - mean).powi(2)).sum::<f64>() / n as f64;
+        let median = if n % 2 == 1 {
+            v[n / 2] as f64
+        } else {
+            (v[n / 2 - 1] as f64 + v[n / 2] as f64) / 2.0
+        };
+        let mut mode = (v[0], 0);
+        let mut i = 0;
+        while i < n {
+            let j = i + v[i..].iter().take_while(|&&x| x == v[i]).count();
+            // strictly greater: on ties the earlier, smaller value stays
+            if j - i > mode.1 {
+                mode = (v[i], j - i);
+            }
+            i = j;
+        }
+        Some(Naive { min: v[0], max: v[n - 1], mean, median, variance, mode })
+    }
+
+    fn naive_percentile(v: &[i32], p: f64) -> i32 {
+        let rank = ((p / 100.0 * v.len() as f64).ceil() as usize).max(1);
+        v[rank - 1]
+    }
+
+    // floating point cannot do better than a relative error of about 1e-16 of the magnitudes
+    // involved, so the tolerance scales with the data: `x` for the mean, `x * x` for the variance
+    fn close(a: f64, b: f64, scale: f64) -> bool {
+        (a - b).abs() <= 1e-9 * (1.0 + scale)
+    }
+
+    fn check(s: &Stats<i32>, values: &[i32]) {
+        assert_eq!(s.len(), values.len());
+        let mut sorted = values.to_vec();
+        sorted.sort();
+        match naive(&sorted) {
+            None => {
+                assert!(s.is_empty());
+                assert_eq!((s.min(), s.max(), s.mode(), s.percentile(50.0)), (None, None, None, None));
+                assert_eq!((s.mean(), s.median(), s.variance()), (None, None, None));
+            }
+            Some(n) => {
+                assert_eq!(s.min(), Some(n.min));
+                assert_eq!(s.max(), Some(n.max));
+                assert_eq!(s.median(), Some(n.median));
+                assert_eq!(s.mode(), Some(n.mode));
+                let x = (n.min as f64).abs().max((n.max as f64).abs());
+                assert!(close(s.mean().unwrap(), n.mean, x), "mean {:?} vs {}", s.mean(), n.mean);
+                let var = s.variance().unwrap();
+                assert!(close(var, n.variance, x * x), "variance {} vs {}", var, n.variance);
+                for p in [0.0, 1.0, 25.0, 50.0, 90.0, 99.9, 100.0] {
+                    assert_eq!(s.percentile(p), Some(naive_percentile(&sorted, p)), "p{}", p);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn small_example() {
+        let s: Stats<i32> = [2, 4, 4, 4, 5, 5, 7, 9].into_iter().collect();
+        assert_eq!(s.mean(), Some(5.0));
+        assert_eq!(s.variance(), Some(4.0));
+        assert_eq!(s.std_dev(), Some(2.0));
+        assert_eq!(s.sample_variance(), Some(32.0 / 7.0));
+        assert_eq!(s.median(), Some(4.5));
+        assert_eq!(s.mode(), Some((4, 3)));
+        assert_eq!(s.percentile(50.0), Some(4));
+        assert_eq!(s.percentile(100.0), Some(9));
+        assert_eq!(s.percentile(250.0), Some(9));
+        assert_eq!(s.percentile(-5.0), Some(2));
+        assert_eq!(s.iter().collect::<Vec<_>>(), [2, 4, 4, 4, 5, 5, 7, 9]);
+    }
+
+    #[test]
+    fn remove_missing_changes_nothing() {
+        let mut s: Stats<u8> = [3, 1].into_iter().collect();
+        assert!(!s.remove(2));
+        assert_eq!((s.len(), s.median()), (2, Some(2.0)));
+        assert!(s.remove(3) && s.remove(1));
+        assert!(!s.remove(1));
+        assert!(s.is_empty());
+        assert_eq!(s.sample_variance(), None);
+    }
+
+    #[test]
+    fn mode_ties_pick_smallest() {
+        let mut s: Stats<i64> = [7, 3, 7, 3, 10].into_iter().collect();
+        assert_eq!(s.mode(), Some((3, 2)));
+        s.remove(3);
+        assert_eq!(s.mode(), Some((7, 2)));
+        s.remove(7);
+        assert_eq!(s.mode(), Some((3, 1)));
+    }
+
+    #[test]
+    fn cached_values_never_drift() {
+        let mut rng = rand::thread_rng();
+        for round in 0..10 {
+            let mut s = Stats::new();
+            let mut values: Vec<i32> = Vec::new();
+            // small ranges give many duplicates, large ones few
+            let range = if round % 2 == 0 { 10 } else { 100_000 };
+            for _ in 0..1_000 {
+                if values.is_empty() || rng.gen_bool(0.6) {
+                    let v = rng.gen_range(-range..=range);
+                    s.add(v);
+                    values.push(v);
+                } else {
+                    let v = values.swap_remove(rng.gen_range(0..values.len()));
+                    assert!(s.remove(v));
+                }
+                check(&s, &values);
+            }
+            // and all the way down to empty
+            while let Some(v) = values.pop() {
+                assert!(s.remove(v));
+                check(&s, &values);
+            }
+        }
+    }
+
+    #[test]
+    fn order_tree_stays_shallow_on_sorted_input() {
+        fn depth<T>(t: &Tree<T>) -> usize {
+            t.as_ref().map_or(0, |n| 1 + depth(&n.left).max(depth(&n.right)))
+        }
+        // sorted input turns a plain search tree into a list, 100_000 deep
+        let s: Stats<u32> = (0..100_000).collect();
+        assert!(depth(&s.order.root) < 100, "depth {}", depth(&s.order.root));
+        assert_eq!(size(&s.order.root), 100_000);
+        assert_eq!(s.percentile(50.0), Some(49_999));
+    }
+
+    #[test]
+    fn display_report() {
+        let s: Stats<u32> = [1, 2, 2, 3].into_iter().collect();
+        assert_eq!(
+            s.to_string(),
+            "count     4\n\
+             min       1\n\
+             max       3\n\
+             mean      2.000\n\
+             median    2.000\n\
+             std dev   0.707\n\
+             mode      2 (x2)\n\
+             p25       1\n\
+             p75       2\n\
+             p90       3\n\
+             p99       3\n"
+        );
+        assert_eq!(Stats::<u32>::new().to_string(), "count     0\n");
+    }
+}
@@ -0,0 +1,330 @@
+/// This module continues the Cell section of `c11_heap`
+/// There, `cellexamplee` and `foo(&Cell<u32>)` only call `get` and `set`.
+/// Here we build a few small, practical types on top of interior mutability,
+/// all of them usable through a SHARED reference `&self`:
+///     Counter         a counter that can be bumped through `&self`
+///     Lazy<T>         a value computed the first time it is read
+///     Dirty<T>        a value that remembers whether it changed since the last check
+///     Observable<T>   a value that calls registered closures when it is set
+/// See
+///         https://doc.rust-lang.org/std/cell/index.html
+
+use std::cell::{Cell, OnceCell, RefCell};
+use std::fmt;
+use std::ops::Deref;
+
+/* ======= Cell vs RefCell =======
+   =============================== */
+// Recall from `c11_heap`:
+//      Cell<T>     get / set by VALUE (get needs T: Copy), no borrow, no runtime check,
+//                  zero overhead, never panics
+//      RefCell<T>  borrow / borrow_mut give REFERENCES (any T), a runtime borrow counter,
+//                  panics on a conflicting borrow
+// So the rule of thumb when picking one for a field is:
+//  - small Copy data (counters, flags, indices)       -> Cell
+//  - anything we need to look into or mutate in place -> RefCell
+// Each type below uses both, and says why.
+
+/* ======== Counter ========
+   ========================= */
+// The simplest one: `usize` is Copy and we only ever read/write it whole,
+// so a Cell is enough. `NaiveRcWithCell` in `c11_heap` does exactly this.
+pub struct Counter {
+    count: Cell<usize>,
+}
+
+impl Counter {
+    pub fn new() -> Counter {
+        Counter { count: Cell::new(0) }
+    }
+    // note `&self`: whoever can see the counter can bump it
+    pub fn incr(&self) -> usize {
+        self.count.set(self.count.get() + 1);
+        self.count.get()
+    }
+    pub fn get(&self) -> usize {
+        self.count.get()
+    }
+    // `Cell::replace` returns the old value: read and reset in one go
+    pub fn reset(&self) -> usize {
+        self.count.replace(0)
+    }
+}
+
+/* ========= Lazy ==========
+   ========================= */
+// A value that is expensive to compute and maybe never needed:
+// we store the recipe, and cook it on the first `get`.
+//
+// QUIZ: why not store the value in a RefCell<Option<T>>?
+
+
+
+
+
+//
+// Because `get` wants to return a plain `&T` that lives as long as `&self`,
+// but out of a RefCell we can only get a `Ref<T>` guard that lives as long as the guard.
+// `OnceCell<T>` is the cell for this case: it can be written ONCE through `&self`,
+// and after that it only hands out shared references, so no guard is needed.
+// The recipe instead is moved out on first use: `Cell<Option<F>>` + `take()`
+// works for any F, Copy or not, because `take` swaps in a `None` instead of copying.
+pub struct Lazy<T, F = fn() -> T> {
+    value: OnceCell<T>,
+    init: Cell<Option<F>>,
+}
+
+impl<T, F: FnOnce() -> T> Lazy<T, F> {
+    pub fn new(init: F) -> Lazy<T, F> {
+        Lazy { value: OnceCell::new(), init: Cell::new(Some(init)) }
+    }
+
+    pub fn get(&self) -> &T {
+        self.value.get_or_init(|| match self.init.take() {
+            Some(f) => f(),
+            // only reachable if `init` itself calls `get` on the same Lazy
+            None => panic!("Lazy value accessed while being initialised"),
+        })
+    }
+
+    pub fn is_initialised(&self) -> bool {
+        self.value.get().is_some()
+    }
+}
+
+// so that a Lazy<T> can be used like a &T, as in the Deref section of `c11_heap`
+impl<T, F: FnOnce() -> T> Deref for Lazy<T, F> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.get()
+    }
+}
+
+/* ========= Dirty =========
+   ========================= */
+// A value plus a "changed since the last check" flag:
+// think of a document that needs saving, or a cached rendering that needs redrawing.
+// The value is in a RefCell, so that it can be any type and be modified in place;
+// the flag is a bool, so a Cell.
+pub struct Dirty<T> {
+    value: RefCell<T>,
+    dirty: Cell<bool>,
+}
+
+impl<T> Dirty<T> {
+    // a fresh value counts as clean: nobody changed it yet
+    pub fn new(value: T) -> Dirty<T> {
+        Dirty { value: RefCell::new(value), dirty: Cell::new(false) }
+    }
+
+    /// Modifies the value in place and marks it as changed
+    pub fn update(&self, f: impl FnOnce(&mut T)) {
+        f(&mut self.value.borrow_mut());
+        self.dirty.set(true);
+    }
+
+    /// Reads the value without touching the flag
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(&self.value.borrow())
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.get()
+    }
+
+    /// Returns whether the value changed since the last call, and clears the flag
+    pub fn check(&self) -> bool {
+        self.dirty.replace(false)
+    }
+}
+
+impl<T: PartialEq> Dirty<T> {
+    /// Replaces the value; setting an equal value is not a change
+    pub fn set(&self, value: T) {
+        let mut current = self.value.borrow_mut();
+        if *current != value {
+            *current = value;
+            self.dirty.set(true);
+        }
+    }
+}
+
+impl<T: Clone> Dirty<T> {
+    pub fn get(&self) -> T {
+        self.value.borrow().clone()
+    }
+}
+
+/* ====== Observable =======
+   ========================= */
+// A value that notifies "observers" (closures) every time it is set:
+// the observer pattern, as in GUI toolkits.
+// The value is Copy, and we only replace it whole: Cell.
+// The observers are a Vec of boxed closures, which is not Copy and which we push into:
+// RefCell.
+pub struct Observable<T: Copy> {
+    value: Cell<T>,
+    observers: RefCell<Vec<Observer<T>>>,
+}
+
+// an observer of a value of type `T`, called with `(old, new)`
+type Observer<T> = Box<dyn Fn(T, T)>;
+
+impl<T: Copy + PartialEq> Observable<T> {
+    pub fn new(value: T) -> Observable<T> {
+        Observable { value: Cell::new(value), observers: RefCell::new(vec![]) }
+    }
+
+    pub fn get(&self) -> T {
+        self.value.get()
+    }
+
+    /// Registers `f`, which will be called with `(old, new)` on every change
+    pub fn subscribe(&self, f: impl Fn(T, T) + 'static) {
+        self.observers.borrow_mut().push(Box::new(f));
+    }
+
+    /// Sets the value, notifying the observers if it changed
+    pub fn set(&self, new: T) {
+        let old = self.value.replace(new);
+        if old != new {
+            // QUIZ: what happens if an observer calls `subscribe` on this same Observable?
+
+
+
+
+
+            //
+            // `subscribe` asks for `borrow_mut` while this loop holds a `borrow`:
+            // RefCell panics with "already borrowed". This is the price of runtime checks:
+            // the compiler accepted the program, the RefCell caught the conflict.
+            // An observer calling `set` instead is fine: it only re-enters through `borrow`,
+            // and shared borrows can be nested.
+            for observer in self.observers.borrow().iter() {
+                observer(old, new);
+            }
+        }
+    }
+}
+
+impl<T: Copy + fmt::Debug> fmt::Debug for Observable<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Observable({:?}, {} observers)", self.value.get(), self.observers.borrow().len())
+    }
+}
+
+pub fn cellsexample() {
+    let c = Counter::new();
+    c.incr();
+    c.incr();
+    println!("counter: {}", c.get());
+
+    let calls = Counter::new();
+    let lazy = Lazy::new(|| {
+        calls.incr();
+        (1..=20u64).product::<u64>()
+    });
+    println!("initialised? {}", lazy.is_initialised());
+    println!("20! = {}", *lazy);
+    println!("20! = {}", lazy.get());
+    // QUIZ: how many times did the closure run?
+    println!("computed {} time(s)", calls.get());
+
+    let doc = Dirty::new(String::from("draft"));
+    println!("dirty after creation? {}", doc.check());
+    doc.update(|s| s.push_str(" v2"));
+    println!("dirty after update? {} -> {}", doc.check(), doc.get());
+    println!("dirty after check? {}", doc.check());
+
+    // the observers share a log with us: Rc<RefCell<..>> as in `c11_heap::rc_plus_refcell`
+    let log = std::rc::Rc::new(RefCell::new(Vec::new()));
+    let temperature = Observable::new(20);
+    let l = std::rc::Rc::clone(&log);
+    temperature.subscribe(move |old, new| l.borrow_mut().push(format!("{} -> {}", old, new)));
+    temperature.set(21);
+    temperature.set(21);
+    temperature.set(25);
+    println!("{:?} logged {:?}", temperature, log.borrow());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn counter_through_shared_refs() {
+        let c = Counter::new();
+        let r1 = &c;
+        let r2 = &c;
+        r1.incr();
+        r2.incr();
+        assert_eq!(c.get(), 2);
+        assert_eq!(c.reset(), 2);
+        assert_eq!(c.get(), 0);
+    }
+
+    #[test]
+    fn lazy_computes_once_and_only_when_read() {
+        let calls = Counter::new();
+        let lazy = Lazy::new(|| {
+            calls.incr();
+            String::from("expensive")
+        });
+        assert!(!lazy.is_initialised());
+        assert_eq!(calls.get(), 0);
+        assert_eq!(lazy.get(), "expensive");
+        assert_eq!(lazy.len(), 9);
+        assert_eq!(calls.get(), 1);
+        assert!(lazy.is_initialised());
+    }
+
+    #[test]
+    fn lazy_with_a_plain_function() {
+        fn answer() -> i32 { 42 }
+        let lazy: Lazy<i32> = Lazy::new(answer);
+        assert_eq!(*lazy, 42);
+    }
+
+    #[test]
+    fn dirty_tracks_changes_since_last_check() {
+        let d = Dirty::new(vec![1]);
+        assert!(!d.check());
+        d.set(vec![1]);
+        assert!(!d.is_dirty(), "setting an equal value is not a change");
+        d.update(|v| v.push(2));
+        assert!(d.is_dirty());
+        assert!(d.check());
+        assert!(!d.check());
+        assert_eq!(d.with(|v| v.len()), 2);
+        d.set(vec![]);
+        assert!(d.check());
+        assert_eq!(d.get(), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn observable_notifies_on_change_only() {
+        let seen = Rc::new(RefCell::new(vec![]));
+        let o = Observable::new(1);
+        let s = Rc::clone(&seen);
+        o.subscribe(move |old, new| s.borrow_mut().push((old, new)));
+        let count = Rc::new(Cell::new(0));
+        let c = Rc::clone(&count);
+        o.subscribe(move |_, _| c.set(c.get() + 1));
+        o.set(2);
+        o.set(2);
+        o.set(3);
+        assert_eq!(*seen.borrow(), vec![(1, 2), (2, 3)]);
+        assert_eq!(count.get(), 2);
+        assert_eq!(o.get(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "already borrowed")]
+    fn subscribing_from_an_observer_panics() {
+        let o = Rc::new(Observable::new(0));
+        let o2 = Rc::clone(&o);
+        o.subscribe(move |_, _| o2.subscribe(|_, _| {}));
+        o.set(1);
+    }
+}
@@ -57,7 +57,7 @@ use std::mem;
 // define 2 simple structs: Point and Rectangle
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy)]
-struct Point {
+pub(crate) struct Point {
     x: f64,
     y: f64,
 }
@@ -68,11 +68,11 @@ struct Rectangle {
     bottom_right: Point,
 }
 /// a function returning the origin point
-fn origin() -> Point {
+pub(crate) fn origin() -> Point {
     Point { x: 0.0, y: 0.0 }
 }
 /// a function returning the origin point, but boxed
-fn boxed_origin() -> Box<Point> {
+pub(crate) fn boxed_origin() -> Box<Point> {
     // Allocate this point on the heap, and return a pointer to it
     Box::new(Point { x: 0.0, y: 0.0 })
 }
@@ -155,7 +155,7 @@ pub fn example_box_long() {
 // To implement this data structure, we can first define the following enum.
 //
 
-enum List {
+pub(crate) enum List {
     // comment, uncomment
     // Cons(i32, List),
     Cons(i32, Box<List>),
@@ -199,13 +199,14 @@ pub fn recursivetypes(){
 use std::ops::Deref;
 
 // define our own box, for arbitrary stuff of type T,
+// (ignore the Option for now, we'll need it for the Drop section)
 struct MyBox<T>{
-    el : T,
+    el : Option<T>,
     idx : i32
 }
 impl<T> MyBox<T> {
     fn new(x: T) -> MyBox<T> {
-        MyBox{el:x,idx:0}
+        MyBox{el:Some(x),idx:0}
     }
 }
 
@@ -239,7 +240,8 @@ impl<T> Deref for MyBox<T> {
     // type Target = i32;
 
     fn deref(&self) -> &Self::Target {
-        &self.el
+        // the box is only ever emptied by `into_inner`, which consumes it
+        self.el.as_ref().unwrap()
         // &self.idx
     }
 }
@@ -321,6 +323,59 @@ pub fn example_drop() {
 //   to the pool so that it can be reused later.
 // - You are logging information.
 
+// Our MyBox can get a Drop implementation too.
+// Only the boxes with a number (`idx` other than 0) announce it,
+// so that the boxes of the other examples stay quiet
+impl<T> MyBox<T> {
+    fn numbered(x: T, idx: i32) -> MyBox<T> {
+        MyBox{el:Some(x),idx}
+    }
+}
+impl<T> Drop for MyBox<T> {
+    fn drop(&mut self) {
+        if self.idx != 0 && self.el.is_some() {
+            println!("Dropping MyBox number {}", self.idx);
+        }
+    }
+}
+// Now, it is reasonable to want the content back out of a box, consuming the box:
+// `Box` does this with `*b`, we'll write a method for it.
+// The obvious implementation, with `el : T`, would be
+//      fn into_inner(self) -> T { self.el }
+// DNC: error[E0509]: cannot move out of type `MyBox<T>`, which implements the `Drop` trait
+// QUIZ: why does Rust forbid this?
+
+
+
+
+
+//
+// `drop` takes `&mut self` and runs when the box goes out of scope,
+// so it expects all of the fields to still be there.
+// If we moved `el` out, `drop` would run on a half-empty struct.
+// This is why `el` is an Option: we `take` the content, leaving a `None` behind,
+// and `drop` knows that an empty box has nothing to say.
+impl<T> MyBox<T> {
+    fn into_inner(mut self) -> T {
+        self.el.take().unwrap()
+    }
+}
+
+pub fn example_mybox_drop() {
+    let _a = MyBox::numbered(String::from("kept"), 1);
+    let b = MyBox::numbered(String::from("taken"), 2);
+    let inner = b.into_inner();
+    println!("into_inner gives {}", inner);
+    // QUIZ: how many times is "Dropping MyBox" printed by this function?
+
+
+
+
+
+    //
+    // Once: for `_a`, at the end of the function. `b` was emptied by `into_inner`.
+}
+
 // start here 22 oct
 
 /* ========== Rc ===========
@@ -456,6 +511,30 @@ pub fn implitictderef() {
 // the * operator on immutable references,
 // you can use the DerefMut trait to override
 // the * operator on mutable references.
+use std::ops::DerefMut;
+
+impl<T> DerefMut for MyBox<T> {
+    // no `type Target` here: DerefMut reuses the one of Deref,
+    // indeed `trait DerefMut: Deref`
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.el.as_mut().unwrap()
+    }
+}
+
+// an auxiliary function that takes an  &mut str  argument
+fn shout(name: &mut str) {
+    name.make_ascii_uppercase();
+}
+
+pub fn implicitderefmut() {
+    let mut m = MyBox::new(String::from("Rust"));
+    // &mut MyBox<String> --deref_mut--> &mut String --deref_mut--> &mut str
+    shout(&mut m);
+    // method calls are coerced too: `push_str` is a method of String, not of MyBox
+    m.push_str(" again");
+    // and a &mut can always be used where a & is expected
+    hello(&m);
+}
 
 // Rust does deref coercion when it finds types and
 // trait implementations in three cases:
@@ -477,6 +556,190 @@ pub fn implitictderef() {
 // that mutable reference must be the only reference to that data
 // (otherwise, the program wouldn’t compile).
 
+// Since MyBox behaves like a pointer to T, it should be usable wherever
+// the standard library expects "something that gives me a T".
+// These are the conversion traits that `Box` implements, let's do the same
+// NOTE: we do not `use std::borrow::Borrow` at the top of this file:
+// its `borrow` method would clash with `RefCell::borrow` on the `Rc<RefCell<..>>`s below.
+use std::fmt;
+
+// cheap reference-to-reference conversion: `&MyBox<T>` to `&T`
+impl<T> AsRef<T> for MyBox<T> {
+    fn as_ref(&self) -> &T {
+        // deref coercion at work: `self` is a `&MyBox<T>`
+        self
+    }
+}
+// like AsRef, but with the promise that Eq, Ord and Hash of the borrowed `T`
+// behave like the ones of the owner: this is what HashMap uses for lookups
+impl<T> std::borrow::Borrow<T> for MyBox<T> {
+    fn borrow(&self) -> &T {
+        self
+    }
+}
+// value-to-value conversion: `let b : MyBox<i32> = 5.into();`
+impl<T> From<T> for MyBox<T> {
+    fn from(x: T) -> MyBox<T> {
+        MyBox::new(x)
+    }
+}
+// printing a MyBox prints its content, exactly as `Box` does
+impl<T: fmt::Debug> fmt::Debug for MyBox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+impl<T: fmt::Display> fmt::Display for MyBox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+pub fn mybox_conversions() {
+    let b: MyBox<i32> = 5.into();
+    println!("Display {} and Debug {:?}", b, b);
+    let s = MyBox::from(String::from("owned"));
+    let r: &String = s.as_ref();
+    println!("as_ref gives {}", r);
+    let inner: String = s.into_inner();
+    println!("into_inner gives {}", inner);
+}
+
+/* ========== Cow ==========
+   ========================= */
+// The standard library has one more smart pointer: `Cow`, for Clone-On-Write
+//      https://doc.rust-lang.org/std/borrow/enum.Cow.html
+// A Cow either BORROWS a value or OWNS it.
+// Reading goes through Deref in both cases;
+// the first time we need to WRITE, a borrowed Cow clones the value and becomes owned.
+// So we pay for the clone only when we really have to modify.
+// The std Cow works with `ToOwned` (so that a Cow<str> can own a String);
+// our version sticks to `Clone`.
+pub enum MyCow<'a, T: Clone> {
+    Borrowed(&'a T),
+    Owned(T),
+}
+
+impl<'a, T: Clone> MyCow<'a, T> {
+    pub fn is_owned(&self) -> bool {
+        match self {
+            MyCow::Borrowed(_) => false,
+            MyCow::Owned(_) => true,
+        }
+    }
+    // here is the "on write" part: we get a `&mut T`, cloning first if needed
+    pub fn to_mut(&mut self) -> &mut T {
+        if let MyCow::Borrowed(b) = *self {
+            *self = MyCow::Owned(b.clone());
+        }
+        match self {
+            MyCow::Owned(o) => o,
+            MyCow::Borrowed(_) => unreachable!(),
+        }
+    }
+    pub fn into_owned(self) -> T {
+        match self {
+            MyCow::Borrowed(b) => b.clone(),
+            MyCow::Owned(o) => o,
+        }
+    }
+}
+
+impl<'a, T: Clone> Deref for MyCow<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        match self {
+            MyCow::Borrowed(b) => b,
+            MyCow::Owned(o) => o,
+        }
+    }
+}
+
+// A typical use: a function that USUALLY returns its input unchanged,
+// and only sometimes needs to build a new value
+fn without_spaces(input: &String) -> MyCow<String> {
+    let mut res = MyCow::Borrowed(input);
+    if input.contains(' ') {
+        // only now we pay for a new String
+        res.to_mut().retain(|c| c != ' ');
+    }
+    res
+}
+
+pub fn cowexample() {
+    let clean = String::from("nospaces");
+    let dirty = String::from("some spaces here");
+    let c1 = without_spaces(&clean);
+    let c2 = without_spaces(&dirty);
+    // QUIZ: which one is owned?
+    println!("{} is owned: {}", *c1, c1.is_owned());
+    println!("{} is owned: {}", *c2, c2.is_owned());
+}
+
+#[cfg(test)]
+mod smartpointer_tests {
+    use super::*;
+    use std::borrow::Borrow;
+
+    #[test]
+    fn mut_mybox_coerces_to_mut_str() {
+        let mut m = MyBox::new(String::from("rust"));
+        shout(&mut m);
+        assert_eq!(*m, "RUST");
+        let s: &mut str = &mut m;
+        s.make_ascii_lowercase();
+        assert_eq!(m.as_str(), "rust");
+    }
+
+    #[test]
+    fn into_inner_gives_back_the_content() {
+        let counted = Rc::new(0);
+        let b = MyBox::new(Rc::clone(&counted));
+        assert_eq!(Rc::strong_count(&counted), 2);
+        let inner = b.into_inner();
+        // the box is gone, but the content is still alive
+        assert_eq!(Rc::strong_count(&counted), 2);
+        drop(inner);
+        assert_eq!(Rc::strong_count(&counted), 1);
+    }
+
+    #[test]
+    fn dropping_mybox_drops_the_content() {
+        let counted = Rc::new(0);
+        {
+            let _b = MyBox::numbered(Rc::clone(&counted), 1);
+            assert_eq!(Rc::strong_count(&counted), 2);
+        }
+        assert_eq!(Rc::strong_count(&counted), 1);
+    }
+
+    #[test]
+    fn conversions_and_formatting() {
+        let b: MyBox<i32> = 7.into();
+        assert_eq!(*b.as_ref(), 7);
+        let r: &i32 = b.borrow();
+        assert_eq!(*r, 7);
+        assert_eq!(format!("{} {:?}", b, b), "7 7");
+        let s = MyBox::from(String::from("x"));
+        assert_eq!(format!("{:?}", s), "\"x\"");
+    }
+
+    #[test]
+    fn cow_clones_only_on_write() {
+        let clean = String::from("nospaces");
+        let c = without_spaces(&clean);
+        assert!(!c.is_owned());
+        assert_eq!(*c, "nospaces");
+
+        let dirty = String::from("a b c");
+        let c = without_spaces(&dirty);
+        assert!(c.is_owned());
+        assert_eq!(c.into_owned(), "abc");
+        // the original is untouched
+        assert_eq!(dirty, "a b c");
+    }
+}
+
 
 
 /* ========== Arc ==========
@@ -1191,6 +1454,8 @@ pub fn cellexamplee() {
 // Put succinctly,
 //      Cell has Copy semantics and provides values
 //      RefCell has move semantics and provides references.
+// For some practical types built on both (a lazy value, a change-tracking value,
+// an observable value) see `c11_cells`.
 
 // we define our Rc with Cell
 struct NaiveRcWithCell<T> {
@@ -1250,6 +1515,7 @@ pub mod par{
         for _ in 0..10 {
             let counter = Arc::clone(&counter);
             let handle = thread::spawn(move || {
+                // why does `lock` return a Result? see `c14_concurrency::poisoning`
                 let mut num = counter.lock().unwrap();
                 println!("I am thread number {}",num);
                 *num += 1;
@@ -1261,6 +1527,30 @@ pub mod par{
         }
         println!("Result: {}", *counter.lock().unwrap());
     }
+
+    // The same counter, but with 3 threads that are reused for the 10 increments,
+    // see `c11_threadpool` for how the pool works
+    pub fn arcmutex_pool() {
+        use crate::classes::c11_threadpool::ThreadPool;
+
+        let counter = Arc::new(Mutex::new(0));
+        let pool = ThreadPool::new(3);
+        let mut handles = vec![];
+
+        for _ in 0..10 {
+            let counter = Arc::clone(&counter);
+            let handle = pool.execute(move || {
+                let mut num = counter.lock().unwrap();
+                println!("I am job number {}",num);
+                *num += 1;
+            });
+            handles.push(handle);
+        }
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        println!("Result: {}", *counter.lock().unwrap());
+    }
 }
 
 // A RefCell will stay “locked” until the pointer you received falls out of scope,
@@ -0,0 +1,308 @@
+/// This module is a companion to `c11_heap`:
+///     it lets us SEE the heap allocations that `Box`, `Vec` & co. perform
+/// In `c11_heap` we keep saying "this value is moved to the heap",
+/// but nothing in the output proves it.
+/// Here we wrap the system allocator with a counting one,
+/// so that any block of code can report how many bytes it allocated.
+/// See
+///         https://doc.rust-lang.org/std/alloc/trait.GlobalAlloc.html
+///         https://doc.rust-lang.org/std/alloc/struct.System.html
+///
+/// The counting allocator is OPT-IN: it is only installed when the crate is built with
+///         cargo run --features heapalloc
+///         cargo test --features heapalloc
+/// (see the `[features]` section of `Cargo.toml`).
+/// Without the feature, everything here still compiles, but reports nothing.
+
+/* ===== The allocator =====
+   ========================= */
+// Every heap allocation in a Rust program goes through ONE global allocator,
+// which is something that implements the `GlobalAlloc` trait.
+// By default this is `System`, i.e., malloc/free on Linux.
+// We can replace it with our own, by annotating a static with
+//      #[global_allocator]
+// Our allocator does not manage memory itself: it forwards every call to `System`
+// and only keeps some statistics on the side.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+// The statistics are kept PER THREAD.
+// QUIZ: why not a single global counter?
+
+
+
+
+
+//
+// Because `cargo test` runs tests in parallel threads, and the test runner itself
+// allocates: with a global counter, a block would be charged for allocations
+// made by other threads at the same time.
+// With thread-local counters, a block only sees its own allocations.
+//
+// NOTE: the allocator is called by `thread_local!` machinery too,
+// so the counters must not allocate themselves:
+// `Cell<usize>` with a `const` initialiser needs no heap and no destructor.
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    static ALLOCATED_BYTES: Cell<usize> = const { Cell::new(0) };
+    static CURRENT_BYTES: Cell<usize> = const { Cell::new(0) };
+    static PEAK_BYTES: Cell<usize> = const { Cell::new(0) };
+}
+
+/// A `GlobalAlloc` that forwards to `System` and counts what goes through it
+pub struct CountingAlloc;
+
+impl CountingAlloc {
+    fn record_alloc(size: usize) {
+        // `try_with` instead of `with`: during thread teardown the thread-locals
+        // may already be gone, and the allocator must never panic
+        let _ = ALLOCATIONS.try_with(|c| c.set(c.get() + 1));
+        let _ = ALLOCATED_BYTES.try_with(|c| c.set(c.get() + size));
+        let _ = CURRENT_BYTES.try_with(|cur| {
+            let now = cur.get() + size;
+            cur.set(now);
+            let _ = PEAK_BYTES.try_with(|peak| {
+                if now > peak.get() {
+                    peak.set(now);
+                }
+            });
+        });
+    }
+    fn record_dealloc(size: usize) {
+        // memory allocated by a thread can be freed by another one,
+        // so the current count of a thread may go "below zero": we saturate
+        let _ = CURRENT_BYTES.try_with(|c| c.set(c.get().saturating_sub(size)));
+    }
+}
+
+// `GlobalAlloc` is an `unsafe trait`: the compiler cannot check that we return
+// valid memory, so we promise it. Here the promise is easy to keep,
+// because all the real work is done by `System`.
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            Self::record_alloc(layout.size());
+        }
+        ptr
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        Self::record_dealloc(layout.size());
+    }
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            Self::record_alloc(layout.size());
+        }
+        ptr
+    }
+    // a `realloc` is counted as a new allocation of the new size
+    // followed by the deallocation of the old one
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            Self::record_alloc(new_size);
+            Self::record_dealloc(layout.size());
+        }
+        new_ptr
+    }
+}
+
+// Here is where the allocator is installed, only when the feature is on.
+// There can be only one #[global_allocator] in the whole program.
+#[cfg(feature = "heapalloc")]
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Whether the counting allocator is installed in this build
+pub fn counting_enabled() -> bool {
+    cfg!(feature = "heapalloc")
+}
+
+/* ===== Measuring a scope =====
+   ============================= */
+
+/// What a block of code did to the heap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AllocStats {
+    /// number of calls to `alloc` (and `realloc`)
+    pub allocations: usize,
+    /// total bytes requested, freed or not
+    pub bytes: usize,
+    /// the highest number of live bytes reached above the level at the start of the block
+    pub peak_bytes: usize,
+}
+
+impl std::fmt::Display for AllocStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "this block allocated {} bytes in {} allocations (peak {} bytes)",
+               self.bytes, self.allocations, self.peak_bytes)
+    }
+}
+
+/// A snapshot of the counters of the current thread, taken when a scope starts.
+// NOTE: the peak is reset when a scope starts, so scopes should not be nested:
+// an inner scope would hide the peak of the outer one
+pub struct AllocScope {
+    allocations: usize,
+    bytes: usize,
+    current: usize,
+}
+
+impl AllocScope {
+    pub fn start() -> AllocScope {
+        let current = CURRENT_BYTES.with(|c| c.get());
+        PEAK_BYTES.with(|p| p.set(current));
+        AllocScope {
+            allocations: ALLOCATIONS.with(|c| c.get()),
+            bytes: ALLOCATED_BYTES.with(|c| c.get()),
+            current,
+        }
+    }
+    pub fn stats(&self) -> AllocStats {
+        AllocStats {
+            allocations: ALLOCATIONS.with(|c| c.get()) - self.allocations,
+            bytes: ALLOCATED_BYTES.with(|c| c.get()) - self.bytes,
+            peak_bytes: PEAK_BYTES.with(|p| p.get()).saturating_sub(self.current),
+        }
+    }
+}
+
+/// Runs `f`, then returns its result together with what it did to the heap
+pub fn measure<T>(f: impl FnOnce() -> T) -> (T, AllocStats) {
+    let scope = AllocScope::start();
+    let res = f();
+    let stats = scope.stats();
+    (res, stats)
+}
+
+/// Runs `f` and prints "this block allocated N bytes in M allocations"
+pub fn report<T>(label: &str, f: impl FnOnce() -> T) -> T {
+    let (res, stats) = measure(f);
+    if counting_enabled() {
+        println!("[{}] {}", label, stats);
+    } else {
+        println!("[{}] heap counting disabled, rebuild with `--features heapalloc`", label);
+    }
+    res
+}
+
+/* ===== Examples =====
+   ==================== */
+// We reuse the types and functions of `c11_heap`, and count what they allocate
+use crate::classes::c11_heap::{self, boxed_origin, origin, List};
+use crate::classes::c11_heap::List::{Cons, Nil};
+
+fn boxed_list(n: i32) -> List {
+    let mut l = Nil;
+    for i in (0..n).rev() {
+        l = Cons(i, Box::new(l));
+    }
+    l
+}
+
+pub fn example_origin_vs_boxed() {
+    // QUIZ: how many allocations for each of these?
+    // 0 and 0 | 0 and 1 | 1 and 1
+    let p = report("origin()", origin);
+    let b = report("boxed_origin()", boxed_origin);
+    println!("{:?} {:?}", p, b);
+
+    //
+    // `origin` lives entirely on the stack: zero allocations.
+    // `boxed_origin` performs exactly one allocation of 16 bytes: the two f64 of the Point
+    // what remains on the stack is the 8 bytes of the pointer
+}
+
+pub fn example_heap_examples() {
+    // the examples of `c11_heap`, run again with the counter on
+    // QUIZ: how many allocations does `example_box` perform? and `example_box_long`?
+
+
+
+
+
+    //
+    // `example_box`: one, the 4 bytes of the `5`.
+    // `example_box_long`: four. The boxed rectangle (32 bytes), the boxed point (16 bytes),
+    // and the box in a box counts twice: the inner `Box<Point>` (16 bytes)
+    // and the outer box that holds its pointer (8 bytes).
+    // `point`, `rectangle` and `unboxed_point` live on the stack and cost nothing.
+    // (printing may add allocations of its own: the first time stdout is used, it allocates a 1024-byte buffer)
+    report("example_box()", c11_heap::example_box);
+    report("example_box_long()", c11_heap::example_box_long);
+}
+
+pub fn example_list_vs_vec() {
+    // a cons list of 1000 elements, each one in its own Box
+    let l = report("Box<List> of 1000", || boxed_list(1000));
+    // a Vec of 1000 elements, allocated in one go
+    let v = report("Vec of 1000, with_capacity", || {
+        let mut v = Vec::with_capacity(1000);
+        for i in 0..1000 { v.push(i); }
+        v
+    });
+    // a Vec of 1000 elements, grown one push at a time
+    let w = report("Vec of 1000, pushed", || {
+        let mut v = Vec::new();
+        for i in 0..1000 { v.push(i); }
+        v
+    });
+    // QUIZ: which one allocates the most bytes? and which one the most times?
+
+
+
+
+
+    //
+    // The list does 1000 allocations: one per Box.
+    // The Vec with capacity does only one.
+    // The pushed Vec does a handful (~10): it doubles its capacity every time it is full,
+    // so it requests more bytes overall than it finally keeps
+    drop((l, v, w));
+}
+
+#[cfg(all(test, feature = "heapalloc"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stack_values_do_not_allocate() {
+        let (_, stats) = measure(origin);
+        assert_eq!(stats.allocations, 0);
+        assert_eq!(stats.bytes, 0);
+    }
+
+    #[test]
+    fn boxed_point_allocates_once() {
+        let (b, stats) = measure(boxed_origin);
+        assert_eq!(stats.allocations, 1);
+        assert_eq!(stats.bytes, std::mem::size_of::<c11_heap::Point>());
+        drop(b);
+    }
+
+    #[test]
+    fn list_allocates_once_per_node() {
+        let (l, stats) = measure(|| boxed_list(100));
+        assert_eq!(stats.allocations, 100);
+        drop(l);
+        let (v, stats) = measure(|| Vec::<i32>::with_capacity(100));
+        assert_eq!(stats.allocations, 1);
+        assert_eq!(stats.bytes, 100 * std::mem::size_of::<i32>());
+        drop(v);
+    }
+
+    #[test]
+    fn peak_counts_live_bytes_only() {
+        let (_, stats) = measure(|| {
+            for _ in 0..10 {
+                let v: Vec<u8> = Vec::with_capacity(64);
+                drop(v);
+            }
+        });
+        assert_eq!(stats.bytes, 640);
+        assert_eq!(stats.peak_bytes, 64);
+    }
+}
@@ -0,0 +1,255 @@
+/// This module continues `c11_heap::par`
+/// There, `arcmutex` spawns one thread per increment: 10 increments, 10 threads.
+/// Threads are not free: each one needs its own stack and a call into the OS.
+/// A THREAD POOL spawns a fixed number of threads once,
+/// and then feeds them jobs through a queue.
+/// See
+///         https://doc.rust-lang.org/book/ch20-02-multithreaded.html
+///         https://doc.rust-lang.org/std/sync/mpsc/index.html
+///
+/// The pieces we use are all from previous classes:
+///     Box<dyn FnOnce()>       a job is a trait object (c10_OOP) of a closure (c12_fp)
+///     Arc<Mutex<..>>          the workers share the receiving end of the queue (c11_heap)
+///     Drop                    shutting the pool down when it goes out of scope (c11_heap)
+
+use std::any::Any;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// A job is any closure that can be called once and sent to another thread.
+// QUIZ: why do we need `'static`?
+
+
+
+
+
+//
+// The job will run on a worker thread at some unknown later time,
+// so it cannot borrow anything from the stack of the thread that submitted it.
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// A fixed-size pool of worker threads fed through a `mpsc` channel
+pub struct ThreadPool {
+    workers: Vec<Worker>,
+    // the Option is there only so that `drop` can take the sender out and drop it first
+    sender: Option<mpsc::Sender<Job>>,
+    // whether `drop` reports each worker it shuts down
+    verbose: bool,
+}
+
+struct Worker {
+    id: usize,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Worker {
+    fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<Job>>>) -> Worker {
+        let thread = thread::spawn(move || loop {
+            // the lock is held only while waiting for a job, not while running it:
+            // the temporary `MutexGuard` is dropped at the end of this statement
+            let message = receiver.lock().unwrap().recv();
+            match message {
+                Ok(job) => job(),
+                // `recv` fails only when the sender is gone and the queue is empty:
+                // this is our shutdown signal
+                Err(_) => break,
+            }
+        });
+        Worker { id, thread: Some(thread) }
+    }
+}
+
+impl ThreadPool {
+    /// Creates a pool with `size` worker threads.
+    /// Panics if `size` is zero: a pool without workers would never run anything.
+    pub fn new(size: usize) -> ThreadPool {
+        assert!(size > 0, "a ThreadPool needs at least one thread");
+        let (sender, receiver) = mpsc::channel();
+        // mpsc means "multiple producer, SINGLE consumer":
+        // the receiver cannot be cloned, so the workers share it behind an Arc<Mutex<..>>
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..size)
+            .map(|id| Worker::new(id, Arc::clone(&receiver)))
+            .collect();
+        ThreadPool { workers, sender: Some(sender), verbose: false }
+    }
+
+    /// Makes the pool print a line for every worker it shuts down, when dropped
+    pub fn verbose(mut self, on: bool) -> ThreadPool {
+        self.verbose = on;
+        self
+    }
+
+    pub fn size(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Queues `f` for execution and returns a handle to wait for its result
+    pub fn execute<F, T>(&self, f: F) -> JobHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        // every job gets its own one-shot channel for the result
+        let (result_sender, result_receiver) = mpsc::channel();
+        let job: Job = Box::new(move || {
+            // a panic inside a job must not kill the worker thread,
+            // otherwise the pool would silently shrink:
+            // `catch_unwind` stops the panic here and turns it into an `Err`
+            let result = panic::catch_unwind(AssertUnwindSafe(f));
+            // the handle may have been dropped already, in that case nobody cares
+            let _ = result_sender.send(result);
+        });
+        self.sender.as_ref().unwrap().send(job).unwrap();
+        JobHandle { receiver: result_receiver }
+    }
+}
+
+// Graceful shutdown: dropping the pool
+//  1 closes the queue, so that workers stop once it is EMPTY,
+//  2 waits for every worker to finish.
+// Jobs that were still queued are therefore run, not lost.
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        for worker in &mut self.workers {
+            if let Some(thread) = worker.thread.take() {
+                // workers catch job panics, so joining them cannot fail
+                thread.join().unwrap();
+            }
+            if self.verbose {
+                println!("worker {} shut down", worker.id);
+            }
+        }
+    }
+}
+
+/// The result of a job that did not produce a value
+#[derive(Debug, PartialEq)]
+pub enum JobError {
+    /// the job panicked, with this message
+    Panicked(String),
+    /// the job was dropped before it could run
+    Lost,
+}
+
+impl fmt::Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JobError::Panicked(msg) => write!(f, "job panicked: {}", msg),
+            JobError::Lost => write!(f, "job was dropped before running"),
+        }
+    }
+}
+
+/// Returned by `ThreadPool::execute`, like `thread::spawn` returns a `JoinHandle`
+pub struct JobHandle<T> {
+    receiver: mpsc::Receiver<thread::Result<T>>,
+}
+
+impl<T> JobHandle<T> {
+    /// Blocks until the job is done
+    pub fn join(self) -> Result<T, JobError> {
+        match self.receiver.recv() {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(payload)) => Err(JobError::Panicked(panic_message(payload))),
+            Err(_) => Err(JobError::Lost),
+        }
+    }
+}
+
+// The payload of a panic is a `Box<dyn Any>`: usually a &str or a String,
+// depending on whether `panic!` was given a literal or a format string
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        String::from("<unknown panic payload>")
+    }
+}
+
+pub fn poolexample() {
+    let pool = ThreadPool::new(3).verbose(true);
+    // 10 jobs, but only 3 threads
+    let handles: Vec<JobHandle<u64>> = (1..=10)
+        .map(|n| pool.execute(move || (1..=n).product()))
+        .collect();
+    // a job that panics
+    let bad = pool.execute(|| -> u64 { panic!("I am a bad job") });
+    for (n, h) in (1..=10).zip(handles) {
+        println!("{}! = {}", n, h.join().unwrap());
+    }
+    println!("the bad job: {}", bad.join().unwrap_err());
+    // QUIZ: is the pool still usable after a job panicked?
+    println!("still working: {:?}", pool.execute(|| 42).join());
+    // the pool is dropped here: what do we see?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn results_do_not_depend_on_scheduling() {
+        let pool = ThreadPool::new(4);
+        let handles: Vec<_> = (0..100u64).map(|i| pool.execute(move || i * i)).collect();
+        let results: Vec<u64> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        // handles are joined in submission order, whatever the order of execution
+        assert_eq!(results, (0..100u64).map(|i| i * i).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn shared_counter_reaches_the_total() {
+        let pool = ThreadPool::new(3);
+        let counter = Arc::new(Mutex::new(0));
+        let handles: Vec<_> = (0..10)
+            .map(|_| {
+                let counter = Arc::clone(&counter);
+                pool.execute(move || *counter.lock().unwrap() += 1)
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+        assert_eq!(*counter.lock().unwrap(), 10);
+    }
+
+    #[test]
+    fn a_panicking_job_does_not_kill_the_pool() {
+        // a single worker: if it died, the next job would never run
+        let pool = ThreadPool::new(1);
+        let bad = pool.execute(|| -> i32 { panic!("boom {}", 1) });
+        assert_eq!(bad.join(), Err(JobError::Panicked(String::from("boom 1"))));
+        assert_eq!(pool.execute(|| 5).join(), Ok(5));
+    }
+
+    #[test]
+    fn dropping_the_pool_runs_queued_jobs() {
+        let done = Arc::new(AtomicUsize::new(0));
+        let handles: Vec<_>;
+        {
+            let pool = ThreadPool::new(1);
+            handles = (0..5)
+                .map(|_| {
+                    let done = Arc::clone(&done);
+                    pool.execute(move || {
+                        thread::sleep(Duration::from_millis(10));
+                        done.fetch_add(1, Ordering::SeqCst);
+                    })
+                })
+                .collect();
+            // most jobs are still in the queue when the pool is dropped here
+        }
+        assert_eq!(done.load(Ordering::SeqCst), 5);
+        for h in handles {
+            assert_eq!(h.join(), Ok(()));
+        }
+    }
+}
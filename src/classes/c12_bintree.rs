@@ -0,0 +1,342 @@
+/// This module is the Rust counterpart of the binary trees of `primers/FP.md`:
+///         datatype 'a bintr = LEAF of 'a
+///                           | NODE of 'a bintr * 'a bintr
+///         val tree = NODE(NODE(LEAF 1,LEAF 4),LEAF 7);
+/// becomes
+///         enum BinTree<T> { Leaf(T), Node(Box<BinTree<T>>, Box<BinTree<T>>) }
+///         let tree = node(node(leaf(1), leaf(4)), leaf(7));
+/// and prints exactly as ML does. On top of it we write the usual functions on trees:
+///     depth, size, leaves     by recursion, as in the primer
+///     map, fold               higher-order functions, see `c12_fp`
+///     pre/in/post-order       iterators, see `c12_fp::iterators`
+///     FromIterator            so that a tree can be `collect`ed
+/// See
+///         https://doc.rust-lang.org/std/iter/trait.FromIterator.html
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BinTree<T> {
+    Leaf(T),
+    // the Boxes: see the recursive types in `c11_heap`
+    Node(Box<BinTree<T>>, Box<BinTree<T>>),
+}
+
+use BinTree::{Leaf, Node};
+
+// the ML constructors, as functions
+pub fn leaf<T>(value: T) -> BinTree<T> {
+    Leaf(value)
+}
+pub fn node<T>(left: BinTree<T>, right: BinTree<T>) -> BinTree<T> {
+    Node(Box::new(left), Box::new(right))
+}
+
+/* ===== Recursive functions =====
+   =============================== */
+// Each is one line per variant, like the ML definitions by cases:
+//      fun size (LEAF _)     = 1
+//      |   size (NODE(l, r)) = 1 + size l + size r
+impl<T> BinTree<T> {
+    /// Number of nodes, leaves included
+    pub fn size(&self) -> usize {
+        match self {
+            Leaf(_) => 1,
+            Node(l, r) => 1 + l.size() + r.size(),
+        }
+    }
+
+    /// Number of edges on the longest path from the root to a leaf: a lone leaf has depth 0
+    pub fn depth(&self) -> usize {
+        match self {
+            Leaf(_) => 0,
+            Node(l, r) => 1 + l.depth().max(r.depth()),
+        }
+    }
+
+    /// The values, left to right
+    pub fn leaves(&self) -> impl Iterator<Item = &T> {
+        self.preorder().filter_map(|t| match t {
+            Leaf(v) => Some(v),
+            Node(..) => None,
+        })
+    }
+
+    // QUIZ: what is the type of `map` in ML?
+    //      (’a -> ’b) -> ’a bintr -> ’b bintr
+    // the shape stays, the values change
+    pub fn map<U, F: Fn(&T) -> U>(&self, f: F) -> BinTree<U> {
+        self.map_ref(&f)
+    }
+    // the recursion passes `&F`, so that `f` is not moved into the first recursive call
+    fn map_ref<U, F: Fn(&T) -> U>(&self, f: &F) -> BinTree<U> {
+        match self {
+            Leaf(v) => Leaf(f(v)),
+            Node(l, r) => node(l.map_ref(f), r.map_ref(f)),
+        }
+    }
+
+    /// The fold of a tree: replaces every `LEAF` with `leaf_fn` and every `NODE` with `node_fn`.
+    /// `size`, `depth` and most functions on trees are folds:
+    ///     size  = fold(|_| 1, |l, r| 1 + l + r)
+    ///     depth = fold(|_| 0, |l, r| 1 + max(l, r))
+    pub fn fold<R, L, N>(&self, leaf_fn: L, node_fn: N) -> R
+    where
+        L: Fn(&T) -> R,
+        N: Fn(R, R) -> R,
+    {
+        self.fold_ref(&leaf_fn, &node_fn)
+    }
+    fn fold_ref<R, L, N>(&self, leaf_fn: &L, node_fn: &N) -> R
+    where
+        L: Fn(&T) -> R,
+        N: Fn(R, R) -> R,
+    {
+        match self {
+            Leaf(v) => leaf_fn(v),
+            Node(l, r) => node_fn(l.fold_ref(leaf_fn, node_fn), r.fold_ref(leaf_fn, node_fn)),
+        }
+    }
+
+    /// The tree seen in a mirror: every left and right swapped
+    pub fn mirror(&self) -> BinTree<T>
+    where
+        T: Clone,
+    {
+        match self {
+            Leaf(v) => Leaf(v.clone()),
+            Node(l, r) => node(r.mirror(), l.mirror()),
+        }
+    }
+}
+
+/* ======== Traversals ========
+   ============================ */
+// The three classic orders, on a node with children l and r:
+//      pre-order   the node, then l, then r
+//      in-order    l, then the node, then r
+//      post-order  l, then r, then the node
+// They differ only for the Nodes: Leaves come out left to right in all three.
+// The iterators yield whole subtrees (`&BinTree<T>`), so that the Nodes are visible too.
+//
+// An iterator cannot use recursion: `next` must return after each item.
+// We keep our own stack instead. Each entry is a subtree plus a flag saying
+// whether its children were already pushed: when they were, it is the node's turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    Pre,
+    In,
+    Post,
+}
+
+pub struct Traversal<'a, T> {
+    order: Order,
+    stack: Vec<(&'a BinTree<T>, bool)>,
+}
+
+impl<'a, T> Iterator for Traversal<'a, T> {
+    type Item = &'a BinTree<T>;
+
+    fn next(&mut self) -> Option<&'a BinTree<T>> {
+        loop {
+            let (tree, expanded) = self.stack.pop()?;
+            match tree {
+                Node(l, r) if !expanded => {
+                    // a stack is last-in first-out: we push in REVERSE order
+                    let (l, r) = (&**l, &**r);
+                    match self.order {
+                        Order::Pre => self.stack.extend([(r, false), (l, false), (tree, true)]),
+                        Order::In => self.stack.extend([(r, false), (tree, true), (l, false)]),
+                        Order::Post => self.stack.extend([(tree, true), (r, false), (l, false)]),
+                    }
+                }
+                // a Leaf, or a Node whose turn has come
+                _ => return Some(tree),
+            }
+        }
+    }
+}
+
+impl<T> BinTree<T> {
+    pub fn traverse(&self, order: Order) -> Traversal<'_, T> {
+        Traversal { order, stack: vec![(self, false)] }
+    }
+    pub fn preorder(&self) -> Traversal<'_, T> {
+        self.traverse(Order::Pre)
+    }
+    pub fn inorder(&self) -> Traversal<'_, T> {
+        self.traverse(Order::In)
+    }
+    pub fn postorder(&self) -> Traversal<'_, T> {
+        self.traverse(Order::Post)
+    }
+}
+
+/* ======= FromIterator =======
+   ============================ */
+// `collect()` builds a balanced tree with the items as leaves, left to right.
+// A BinTree always has at least one leaf: like ML's `hd []`, collecting nothing is an error.
+// QUIZ: why panic, instead of returning an Option?
+
+
+
+
+
+//
+// `FromIterator::from_iter` must return `Self`, there is no room for an error.
+// Callers that may have no items can check first, or use `BinTree::try_from_iter`.
+impl<T> FromIterator<T> for BinTree<T> {
+    /// # Panics
+    /// If the iterator is empty.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        BinTree::try_from_iter(iter).expect("cannot build a BinTree from an empty iterator")
+    }
+}
+
+impl<T> BinTree<T> {
+    pub fn try_from_iter<I: IntoIterator<Item = T>>(iter: I) -> Option<BinTree<T>> {
+        let items: Vec<T> = iter.into_iter().collect();
+        if items.is_empty() {
+            None
+        } else {
+            Some(BinTree::balanced(items))
+        }
+    }
+
+    // the left half gets the extra item: [1, 4, 7] is NODE(NODE(LEAF 1,LEAF 4),LEAF 7)
+    fn balanced(mut items: Vec<T>) -> BinTree<T> {
+        if items.len() == 1 {
+            return Leaf(items.pop().unwrap());
+        }
+        let right = items.split_off(items.len().div_ceil(2));
+        node(BinTree::balanced(items), BinTree::balanced(right))
+    }
+}
+
+/* ========= Display ==========
+   ============================ */
+// the ML notation: `NODE(NODE(LEAF 1,LEAF 4),LEAF 7)`
+impl<T: fmt::Display> fmt::Display for BinTree<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Leaf(v) => write!(f, "LEAF {}", v),
+            Node(l, r) => write!(f, "NODE({},{})", l, r),
+        }
+    }
+}
+
+pub fn bintreeexample() {
+    // val tree = NODE(NODE(LEAF 1,LEAF 4),LEAF 7);
+    let tree = node(node(leaf(1), leaf(4)), leaf(7));
+    println!("val tree = {}", tree);
+    let collected: BinTree<i32> = vec![1, 4, 7].into_iter().collect();
+    println!("collected: {}", collected);
+    println!("size {}, depth {}", tree.size(), tree.depth());
+    println!("leaves: {:?}", tree.leaves().collect::<Vec<_>>());
+    println!("map (fn x => x * 10): {}", tree.map(|x| x * 10));
+    println!("sum by fold: {}", tree.fold(|v| *v, |l, r| l + r));
+    println!("mirror: {}", tree.mirror());
+    // a node is printed as the sum of its leaves, to tell the nodes apart
+    let label = |t: &BinTree<i32>| match t {
+        Leaf(v) => v.to_string(),
+        Node(..) => format!("[{}]", t.fold(|v| *v, |l, r| l + r)),
+    };
+    for order in [Order::Pre, Order::In, Order::Post] {
+        let visited: Vec<String> = tree.traverse(order).map(label).collect();
+        println!("{:?}-order: {}", order, visited.join(" "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn primer_tree() -> BinTree<i32> {
+        node(node(leaf(1), leaf(4)), leaf(7))
+    }
+
+    // each node is labelled with the sum of its leaves, so the traversals can be compared
+    fn labels(it: Traversal<'_, i32>) -> Vec<i32> {
+        it.map(|t| t.fold(|v| *v, |l, r| l + r)).collect()
+    }
+
+    #[test]
+    fn display_matches_ml() {
+        assert_eq!(primer_tree().to_string(), "NODE(NODE(LEAF 1,LEAF 4),LEAF 7)");
+        assert_eq!(leaf("a").to_string(), "LEAF a");
+    }
+
+    #[test]
+    fn collect_builds_a_balanced_tree() {
+        let tree: BinTree<i32> = [1, 4, 7].into_iter().collect();
+        assert_eq!(tree, primer_tree());
+        let big: BinTree<u32> = (0..1000).collect();
+        assert_eq!(big.leaves().copied().collect::<Vec<_>>(), (0..1000).collect::<Vec<_>>());
+        // balanced: 1000 leaves need ceil(log2(1000)) = 10 levels
+        assert_eq!(big.depth(), 10);
+        assert_eq!(BinTree::<i32>::try_from_iter(vec![]), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "empty iterator")]
+    fn collect_nothing_panics() {
+        let _: BinTree<i32> = std::iter::empty().collect();
+    }
+
+    #[test]
+    fn size_depth_leaves() {
+        let t = primer_tree();
+        assert_eq!(t.size(), 5);
+        assert_eq!(t.depth(), 2);
+        assert_eq!(leaf(0).depth(), 0);
+        assert_eq!(t.leaves().collect::<Vec<_>>(), vec![&1, &4, &7]);
+    }
+
+    #[test]
+    fn size_and_depth_are_folds() {
+        let t: BinTree<i32> = (0..37).collect();
+        assert_eq!(t.fold(|_| 1, |l, r| 1 + l + r), t.size());
+        assert_eq!(t.fold(|_| 0, |l: usize, r| 1 + l.max(r)), t.depth());
+    }
+
+    #[test]
+    fn map_keeps_the_shape() {
+        let t = primer_tree().map(|x| x.to_string() + "!");
+        assert_eq!(t.to_string(), "NODE(NODE(LEAF 1!,LEAF 4!),LEAF 7!)");
+        assert_eq!(t.size(), 5);
+    }
+
+    #[test]
+    fn traversal_orders() {
+        let t = primer_tree();
+        // nodes: [12] is the root, [5] the left child
+        assert_eq!(labels(t.preorder()), vec![12, 5, 1, 4, 7]);
+        assert_eq!(labels(t.inorder()), vec![1, 5, 4, 12, 7]);
+        assert_eq!(labels(t.postorder()), vec![1, 4, 5, 7, 12]);
+        assert_eq!(labels(leaf(3).inorder()), vec![3]);
+    }
+
+    #[test]
+    fn mirror_reverses_the_leaves() {
+        let t: BinTree<i32> = (1..=6).collect();
+        let m = t.mirror();
+        assert_eq!(m.leaves().copied().collect::<Vec<_>>(), vec![6, 5, 4, 3, 2, 1]);
+        assert_eq!(m.mirror(), t);
+    }
+
+    #[test]
+    fn deep_trees_traverse_without_recursion() {
+        // a "comb": every right child is a leaf, depth 100_000
+        let mut t = leaf(0u32);
+        for i in 1..100_000 {
+            t = node(t, leaf(i));
+        }
+        assert_eq!(t.postorder().count(), 199_999);
+        assert_eq!(t.leaves().last(), Some(&99_999));
+        // dropping it recursively would overflow the stack: take it apart by hand
+        let mut current = t;
+        while let Node(l, _) = current {
+            current = *l;
+        }
+    }
+}
@@ -0,0 +1,611 @@
+/// This module is the Rust counterpart of the ML list functions of `primers/FP.md`:
+///         hd, last, length, length_acc, map, foldr
+/// each written three ways:
+///     naive       recursion exactly as in ML: `length (h::t) = 1 + length t`
+///     acc         with an accumulator, tail recursive: `length_acc t (a+1)`
+///     loop/iter   what a compiler does with a tail call: a loop; or the std iterators
+/// and over two kinds of lists:
+///     slices      `&[T]`, matched with slice patterns `[h, t @ ..]`
+///     cons        an owned `List<T> = Nil | Cons(T, Box<List<T>>)`, the ML list itself
+/// IMPORTANT: unlike ML, Rust does NOT promise tail call elimination.
+/// A tail-recursive function may still use one stack frame per call: in debug builds it always
+/// does, in release builds LLVM often (not always) turns it into a loop.
+/// So the Rust way to write the accumulator version is the loop, or an iterator.
+/// `benchmark` shows the stack and time of each variant, and the tests show which ones overflow.
+/// See
+///         https://doc.rust-lang.org/reference/patterns.html#slice-patterns
+
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+/* ======= Stack probe =======
+   =========================== */
+// To see how much stack a function uses, every variant calls `probe()`, which records the
+// lowest address of a local variable seen so far. The stack grows downwards, so
+// (address at the start) - (lowest address) = bytes of stack used.
+thread_local! {
+    static LOWEST: Cell<usize> = const { Cell::new(usize::MAX) };
+}
+
+#[inline(never)]
+fn probe() {
+    let marker = 0u8;
+    // `black_box` stops the compiler from optimising the local away
+    let addr = std::hint::black_box(&marker) as *const u8 as usize;
+    LOWEST.with(|l| {
+        if addr < l.get() {
+            l.set(addr)
+        }
+    });
+}
+
+/// Runs `f`, returning its result, the time it took and the bytes of stack it used
+pub fn measure<R>(f: impl FnOnce() -> R) -> (R, Duration, usize) {
+    let marker = 0u8;
+    let base = std::hint::black_box(&marker) as *const u8 as usize;
+    LOWEST.with(|l| l.set(base));
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    let lowest = LOWEST.with(|l| l.get());
+    (result, elapsed, base.saturating_sub(lowest))
+}
+
+/* ========= Slices ==========
+   =========================== */
+pub mod slices {
+    use super::probe;
+
+    //      fun hd []     = raise Empty
+    //      |   hd (h::t) = h;
+    // no exceptions in Rust: the empty case returns None
+    pub fn hd<T>(l: &[T]) -> Option<&T> {
+        match l {
+            [] => None,
+            [h, ..] => Some(h),
+        }
+    }
+
+    //      fun last []     = raise Empty
+    //      |   last [x]    = x
+    //      |   last (h::t) = last t;
+    // already tail recursive in ML; in Rust, one frame per element anyway
+    pub fn last<T>(l: &[T]) -> Option<&T> {
+        probe();
+        match l {
+            [] => None,
+            [x] => Some(x),
+            [_, t @ ..] => last(t),
+        }
+    }
+    pub fn last_iter<T>(l: &[T]) -> Option<&T> {
+        probe();
+        l.iter().last()
+    }
+
+    //      fun length []     = 0
+    //      |   length (h::t) = 1 + length t;
+    // after the recursive call returns there is still a `1 +` to do: the frame must stay
+    pub fn length<T>(l: &[T]) -> usize {
+        probe();
+        match l {
+            [] => 0,
+            [_, t @ ..] => 1 + length(t),
+        }
+    }
+
+    //      fun length_acc []     a = a
+    //      |   length_acc (h::t) a = length_acc t (a+1);
+    // the recursive call is the LAST thing done: a tail call
+    pub fn length_acc<T>(l: &[T], a: usize) -> usize {
+        probe();
+        match l {
+            [] => a,
+            [_, t @ ..] => length_acc(t, a + 1),
+        }
+    }
+
+    // the tail call turned into a loop by hand: the arguments become mutable variables,
+    // and "calling again" becomes "assigning them and going back to the top"
+    pub fn length_loop<T>(l: &[T]) -> usize {
+        probe();
+        let (mut l, mut a) = (l, 0);
+        loop {
+            match l {
+                [] => return a,
+                [_, t @ ..] => {
+                    l = t;
+                    a += 1;
+                }
+            }
+        }
+    }
+
+    //      fun map f []     = []
+    //      |   map f (h::t) = (f h)::(map f t);
+    // ML conses at the FRONT in O(1); a Vec pushes at the BACK in O(1),
+    // so we recurse on all but the last element instead
+    pub fn map<T, U>(f: &impl Fn(&T) -> U, l: &[T]) -> Vec<U> {
+        probe();
+        match l {
+            [] => Vec::new(),
+            [init @ .., last] => {
+                let mut v = map(f, init);
+                v.push(f(last));
+                v
+            }
+        }
+    }
+    // with an accumulator: the Vec being built is the accumulator
+    pub fn map_acc<T, U>(f: &impl Fn(&T) -> U, l: &[T], mut acc: Vec<U>) -> Vec<U> {
+        probe();
+        match l {
+            [] => acc,
+            [h, t @ ..] => {
+                acc.push(f(h));
+                map_acc(f, t, acc)
+            }
+        }
+    }
+    pub fn map_iter<T, U>(f: &impl Fn(&T) -> U, l: &[T]) -> Vec<U> {
+        probe();
+        l.iter().map(f).collect()
+    }
+
+    //      fun foldr f b []     = b
+    //      |   foldr f b (h::t) = f(h, foldr f b t);
+    // `foldr (op +) 0 [1,2,3]` = 1 + (2 + (3 + 0)): the innermost operation is the LAST element,
+    // so we cannot start computing before reaching the end
+    pub fn foldr<T, B>(f: &impl Fn(&T, B) -> B, b: B, l: &[T]) -> B {
+        probe();
+        match l {
+            [] => b,
+            [h, t @ ..] => f(h, foldr(f, b, t)),
+        }
+    }
+    // ... unless we walk the list from the end: then the accumulator works
+    pub fn foldr_acc<T, B>(f: &impl Fn(&T, B) -> B, acc: B, l: &[T]) -> B {
+        probe();
+        match l {
+            [] => acc,
+            [init @ .., last] => foldr_acc(f, f(last, acc), init),
+        }
+    }
+    pub fn foldr_iter<T, B>(f: &impl Fn(&T, B) -> B, b: B, l: &[T]) -> B {
+        probe();
+        l.iter().rfold(b, |acc, x| f(x, acc))
+    }
+}
+
+/* ======= Cons lists ========
+   =========================== */
+pub mod cons {
+    use super::probe;
+
+    //      datatype 'a list = nil | :: of 'a * 'a list
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum List<T> {
+        Nil,
+        Cons(T, Box<List<T>>),
+    }
+
+    use List::{Cons, Nil};
+
+    // QUIZ: a list of a million elements goes out of scope. What happens?
+
+
+
+
+
+    //
+    // The automatic drop is recursive: dropping a Cons drops its Box, which drops the next Cons...
+    // a million nested calls, and the stack overflows, without any code of ours being recursive.
+    // So we take the list apart with a loop: we detach the tail of each node, leaving a Nil
+    // in its place, and dropping a Cons whose tail is Nil recurses only once.
+    // Note that we cannot MOVE the fields out of a type that implements Drop
+    // (error[E0509], as for `MyBox::into_inner` in `c11_heap`): `mem::replace` swaps them instead.
+    impl<T> Drop for List<T> {
+        fn drop(&mut self) {
+            let mut tail = match self {
+                Cons(_, t) => std::mem::replace(&mut **t, Nil),
+                Nil => return,
+            };
+            while let Cons(_, t) = &mut tail {
+                let next = std::mem::replace(&mut **t, Nil);
+                // the old `tail` is dropped here, its own tail is already Nil
+                tail = next;
+            }
+        }
+    }
+
+    // ML's `h :: t`
+    pub fn cons<T>(h: T, t: List<T>) -> List<T> {
+        Cons(h, Box::new(t))
+    }
+
+    // `collect()` builds the list back to front, with a loop, as we can only add at the front
+    impl<T> FromIterator<T> for List<T> {
+        fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+            let items: Vec<T> = iter.into_iter().collect();
+            let mut list = Nil;
+            for x in items.into_iter().rev() {
+                list = cons(x, list);
+            }
+            list
+        }
+    }
+
+    pub struct Iter<'a, T> {
+        next: &'a List<T>,
+    }
+
+    impl<'a, T> Iterator for Iter<'a, T> {
+        type Item = &'a T;
+        fn next(&mut self) -> Option<&'a T> {
+            match self.next {
+                Nil => None,
+                Cons(h, t) => {
+                    self.next = t;
+                    Some(h)
+                }
+            }
+        }
+    }
+
+    impl<T> List<T> {
+        pub fn iter(&self) -> Iter<'_, T> {
+            Iter { next: self }
+        }
+    }
+
+    pub fn hd<T>(l: &List<T>) -> Option<&T> {
+        match l {
+            Nil => None,
+            Cons(h, _) => Some(h),
+        }
+    }
+
+    pub fn last<T>(l: &List<T>) -> Option<&T> {
+        probe();
+        match l {
+            Nil => None,
+            // a nested pattern: a Cons whose tail is Nil, i.e., `[x]`
+            Cons(x, t) if matches!(**t, Nil) => Some(x),
+            Cons(_, t) => last(t),
+        }
+    }
+    pub fn last_iter<T>(l: &List<T>) -> Option<&T> {
+        probe();
+        l.iter().last()
+    }
+
+    pub fn length<T>(l: &List<T>) -> usize {
+        probe();
+        match l {
+            Nil => 0,
+            Cons(_, t) => 1 + length(t),
+        }
+    }
+    pub fn length_acc<T>(l: &List<T>, a: usize) -> usize {
+        probe();
+        match l {
+            Nil => a,
+            Cons(_, t) => length_acc(t, a + 1),
+        }
+    }
+    pub fn length_loop<T>(l: &List<T>) -> usize {
+        probe();
+        let (mut l, mut a) = (l, 0);
+        while let Cons(_, t) = l {
+            l = t;
+            a += 1;
+        }
+        a
+    }
+
+    // exactly the ML definition: (f h)::(map f t)
+    pub fn map<T, U>(f: &impl Fn(&T) -> U, l: &List<T>) -> List<U> {
+        probe();
+        match l {
+            Nil => Nil,
+            Cons(h, t) => cons(f(h), map(f, t)),
+        }
+    }
+    // with an accumulator the result comes out reversed, as in ML: `rev` at the end
+    pub fn map_acc<T, U>(f: &impl Fn(&T) -> U, l: &List<T>, acc: List<U>) -> List<U> {
+        probe();
+        match l {
+            Nil => rev(acc),
+            Cons(h, t) => map_acc(f, t, cons(f(h), acc)),
+        }
+    }
+    pub fn map_iter<T, U>(f: &impl Fn(&T) -> U, l: &List<T>) -> List<U> {
+        probe();
+        l.iter().map(f).collect()
+    }
+
+    // reverses a list by relinking its nodes: no allocation, no recursion
+    pub fn rev<T>(mut l: List<T>) -> List<T> {
+        let mut reversed = Nil;
+        loop {
+            match &mut l {
+                Nil => return reversed,
+                Cons(_, t) => {
+                    // the first node now points to `reversed`, and its old tail is `rest` ...
+                    let rest = std::mem::replace(&mut **t, reversed);
+                    // ... then the node becomes `reversed`, and `rest` is what is left to do
+                    reversed = std::mem::replace(&mut l, rest);
+                }
+            }
+        }
+    }
+
+    pub fn foldr<T, B>(f: &impl Fn(&T, B) -> B, b: B, l: &List<T>) -> B {
+        probe();
+        match l {
+            Nil => b,
+            Cons(h, t) => f(h, foldr(f, b, t)),
+        }
+    }
+    // a cons list cannot be walked backwards: we collect references first
+    pub fn foldr_iter<T, B>(f: &impl Fn(&T, B) -> B, b: B, l: &List<T>) -> B {
+        probe();
+        l.iter().collect::<Vec<_>>().into_iter().rfold(b, |acc, x| f(x, acc))
+    }
+}
+
+/* ======= Benchmark =========
+   =========================== */
+/// Runs every variant on lists of `n` elements and prints time and stack used.
+/// The recursive variants need a big stack: we run them on a thread with `stack_mb` MiB,
+/// the main thread usually has 8 MiB.
+pub fn benchmark(n: usize, stack_mb: usize) {
+    let handle = std::thread::Builder::new()
+        .stack_size(stack_mb << 20)
+        .spawn(move || {
+            let v: Vec<u64> = (0..n as u64).collect();
+            let l: cons::List<u64> = v.iter().copied().collect();
+            let add = |x: &u64, acc: u64| x + acc;
+            let double = |x: &u64| x * 2;
+            // a macro, so that each row can call a different function with a different type
+            macro_rules! row {
+                ($name:expr, $e:expr) => {{
+                    let (result, time, stack) = measure(|| $e);
+                    println!("{:<22} {:>12} {:>10.2?} {:>12}", $name, result, time, stack);
+                }};
+            }
+            println!("n = {}, stack = {} MiB", n, stack_mb);
+            println!("{:<22} {:>12} {:>10} {:>12}", "function", "result", "time", "stack bytes");
+            row!("slices::last", *slices::last(&v).unwrap());
+            row!("slices::last_iter", *slices::last_iter(&v).unwrap());
+            row!("slices::length", slices::length(&v));
+            row!("slices::length_acc", slices::length_acc(&v, 0));
+            row!("slices::length_loop", slices::length_loop(&v));
+            row!("slices::map", slices::map(&double, &v).len());
+            row!("slices::map_acc", slices::map_acc(&double, &v, Vec::new()).len());
+            row!("slices::map_iter", slices::map_iter(&double, &v).len());
+            row!("slices::foldr", slices::foldr(&add, 0, &v));
+            row!("slices::foldr_acc", slices::foldr_acc(&add, 0, &v));
+            row!("slices::foldr_iter", slices::foldr_iter(&add, 0, &v));
+            row!("cons::last", *cons::last(&l).unwrap());
+            row!("cons::last_iter", *cons::last_iter(&l).unwrap());
+            row!("cons::length", cons::length(&l));
+            row!("cons::length_acc", cons::length_acc(&l, 0));
+            row!("cons::length_loop", cons::length_loop(&l));
+            row!("cons::map", cons::length_loop(&cons::map(&double, &l)));
+            row!("cons::map_acc", cons::length_loop(&cons::map_acc(&double, &l, cons::List::Nil)));
+            row!("cons::map_iter", cons::length_loop(&cons::map_iter(&double, &l)));
+            row!("cons::foldr", cons::foldr(&add, 0, &l));
+            row!("cons::foldr_iter", cons::foldr_iter(&add, 0, &l));
+        })
+        .unwrap();
+    handle.join().unwrap();
+}
+
+pub fn mllistsexample() {
+    use cons::{cons, List};
+    // (2::4::[])
+    let l: List<i32> = cons(2, cons(4, List::Nil));
+    println!("hd {:?}, last {:?}, length {}", cons::hd(&l), cons::last(&l), cons::length(&l));
+    // map (fn x => x+1) 1::2::3::[]
+    let l: List<i32> = (1..=3).collect();
+    println!("{:?}", cons::map(&|x| x + 1, &l));
+    // compare debug and release: `cargo run --release`
+    benchmark(100_000, 512);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::cons::List;
+    use super::*;
+
+    // 64 KiB: a few thousand frames at most
+    const SMALL_STACK: usize = 64 << 10;
+    const N: usize = 1_000_000;
+
+    fn on_small_stack<R: Send + 'static>(f: impl FnOnce() -> R + Send + 'static) -> R {
+        std::thread::Builder::new().stack_size(SMALL_STACK).spawn(f).unwrap().join().unwrap()
+    }
+
+    // A stack overflow is not a panic: it aborts the whole process, test harness included.
+    // So we run the overflowing code in a CHILD process: the test binary itself, asked to run
+    // only this one test, with an environment variable telling it that it is the child.
+    const CHILD: &str = "AP_CLASS_MLLISTS_CHILD";
+
+    fn expect_overflow(test: &str, f: impl FnOnce() + Send + 'static) {
+        if std::env::var_os(CHILD).is_some() {
+            on_small_stack(f);
+            return;
+        }
+        // the test names do not include the crate name, `module_path!` does
+        let path = module_path!().split_once("::").unwrap().1;
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([&format!("{}::{}", path, test), "--exact", "--nocapture", "--test-threads=1"])
+            .env(CHILD, "1")
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{} did not overflow", test);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("overflowed its stack"), "{}", stderr);
+    }
+
+    #[test]
+    fn same_results_everywhere() {
+        let v: Vec<i64> = (1..=100).collect();
+        let l: List<i64> = v.iter().copied().collect();
+        let add = |x: &i64, acc: i64| x + acc;
+        // foldr with a non associative operation: the order matters
+        let sub = |x: &i64, acc: i64| x - acc;
+        let sq = |x: &i64| x * x;
+
+        assert_eq!(slices::hd(&v), Some(&1));
+        assert_eq!(cons::hd(&l), Some(&1));
+        for last in [slices::last(&v), slices::last_iter(&v), cons::last(&l), cons::last_iter(&l)] {
+            assert_eq!(last, Some(&100));
+        }
+        for len in [
+            slices::length(&v),
+            slices::length_acc(&v, 0),
+            slices::length_loop(&v),
+            cons::length(&l),
+            cons::length_acc(&l, 0),
+            cons::length_loop(&l),
+        ] {
+            assert_eq!(len, 100);
+        }
+        let squares: Vec<i64> = v.iter().map(sq).collect();
+        assert_eq!(slices::map(&sq, &v), squares);
+        assert_eq!(slices::map_acc(&sq, &v, Vec::new()), squares);
+        assert_eq!(slices::map_iter(&sq, &v), squares);
+        let squares: List<i64> = squares.into_iter().collect();
+        assert_eq!(cons::map(&sq, &l), squares);
+        assert_eq!(cons::map_acc(&sq, &l, List::Nil), squares);
+        assert_eq!(cons::map_iter(&sq, &l), squares);
+
+        // 1 - (2 - (3 - ... (100 - 0)))
+        let expected = v.iter().rev().fold(0, |acc, x| x - acc);
+        assert_eq!(expected, -50);
+        for f in [&add as &dyn Fn(&i64, i64) -> i64, &sub] {
+            let want = v.iter().rev().fold(0, |acc, x| f(x, acc));
+            assert_eq!(slices::foldr(&f, 0, &v), want);
+            assert_eq!(slices::foldr_acc(&f, 0, &v), want);
+            assert_eq!(slices::foldr_iter(&f, 0, &v), want);
+            assert_eq!(cons::foldr(&f, 0, &l), want);
+            assert_eq!(cons::foldr_iter(&f, 0, &l), want);
+        }
+    }
+
+    #[test]
+    fn empty_lists() {
+        let v: Vec<u8> = vec![];
+        let l: List<u8> = List::Nil;
+        assert_eq!(slices::hd(&v), None);
+        assert_eq!(slices::last(&v), None);
+        assert_eq!(cons::hd(&l), None);
+        assert_eq!(cons::last(&l), None);
+        assert_eq!(cons::length(&l), 0);
+        assert_eq!(cons::rev(l), List::Nil);
+    }
+
+    #[test]
+    fn rev_reverses() {
+        let l: List<u8> = (1..=4).collect();
+        assert_eq!(cons::rev(l), (1..=4).rev().collect());
+    }
+
+    // The naive variants are guaranteed to overflow only in debug builds:
+    // in release, LLVM can turn even `1 + length(t)` into a loop.
+
+    // the loop and iterator variants run in constant stack, whatever the length
+    #[test]
+    fn loops_and_iterators_do_not_overflow() {
+        let total = on_small_stack(|| {
+            let v = vec![1u64; N];
+            let l: List<u64> = v.iter().copied().collect();
+            assert_eq!(slices::length_loop(&v), N);
+            assert_eq!(slices::last_iter(&v), Some(&1));
+            assert_eq!(slices::map_iter(&|x| x + 1, &v).len(), N);
+            assert_eq!(cons::length_loop(&l), N);
+            assert_eq!(cons::length_loop(&cons::map_iter(&|x| x + 1, &l)), N);
+            // not `l.clone()`: the derived Clone, like PartialEq and Debug, is recursive too
+            assert_eq!(cons::length_loop(&cons::rev(v.iter().copied().collect())), N);
+            // and the list is dropped here, without overflowing thanks to our Drop
+            slices::foldr_iter(&|x, acc| x + acc, 0, &v) + cons::foldr_iter(&|x, acc| x + acc, 0, &l)
+        });
+        assert_eq!(total, 2 * N as u64);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn naive_slice_length_overflows() {
+        expect_overflow("naive_slice_length_overflows", || {
+            slices::length(&vec![0u8; N]);
+        });
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn naive_slice_foldr_overflows() {
+        expect_overflow("naive_slice_foldr_overflows", || {
+            slices::foldr(&|x: &u64, acc: u64| x + acc, 0, &vec![1; N]);
+        });
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn naive_cons_length_overflows() {
+        expect_overflow("naive_cons_length_overflows", || {
+            let l: List<u8> = std::iter::repeat_n(0, N).collect();
+            cons::length(&l);
+        });
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn naive_cons_map_overflows() {
+        expect_overflow("naive_cons_map_overflows", || {
+            let l: List<u8> = std::iter::repeat_n(0, N).collect();
+            cons::map(&|x| x + 1, &l);
+        });
+    }
+
+    // Tail recursion is not enough in Rust: without optimisations (debug builds, as `cargo test`)
+    // the accumulator version overflows just like the naive one.
+    // In release builds it usually becomes a loop, but the language guarantees nothing.
+    #[cfg(debug_assertions)]
+    #[test]
+    fn tail_recursion_is_not_guaranteed() {
+        expect_overflow("tail_recursion_is_not_guaranteed", || {
+            slices::length_acc(&vec![0u8; N], 0);
+        });
+    }
+
+    // ... but in release builds the accumulator versions below do become loops, with this compiler:
+    // the same `N` that overflows above runs fine on the same small stack.
+    // Not all of them: `slices::map_acc` still overflows. And if a future compiler
+    // stops doing it for these, this test will tell us
+    #[cfg(not(debug_assertions))]
+    #[test]
+    fn tail_recursion_becomes_a_loop_when_optimised() {
+        let total = on_small_stack(|| {
+            let v = vec![1u64; N];
+            let l: List<u64> = v.iter().copied().collect();
+            slices::length_acc(&v, 0)
+                + slices::foldr_acc(&|x, acc| x + acc, 0, &v) as usize
+                + cons::length_acc(&l, 0)
+                + cons::length_loop(&cons::map_acc(&|x| x + 1, &l, List::Nil))
+        });
+        assert_eq!(total, 4 * N);
+    }
+
+    // like the tests above, only meaningful without optimisations:
+    // in release builds `1 + length(t)` may become a loop and use no more stack than `length_loop`
+    #[cfg(debug_assertions)]
+    #[test]
+    fn probe_measures_stack() {
+        let v = vec![0u8; 1000];
+        let (_, _, recursive) = measure(|| slices::length(&v));
+        let (_, _, looping) = measure(|| slices::length_loop(&v));
+        assert!(recursive > 10 * looping, "recursive {} vs loop {}", recursive, looping);
+    }
+}
@@ -0,0 +1,738 @@
+/// This module shows some KEY concepts of Rust related to
+///     concurrency
+/// `c11_heap` stopped at `Arc` and `Arc<Mutex<..>>`.
+/// Here is the rest of the toolbox of `std::sync`, one runnable example each:
+///     Condvar             a bounded producer/consumer queue
+///     RwLock              a read-mostly cache
+///     Atomics             a lock-free counter, and what `Ordering` means
+///     Barrier             threads computing in lock-step phases
+///     scoped threads      threads that borrow data from the stack
+/// See
+///         https://doc.rust-lang.org/book/ch16-00-concurrency.html
+///         https://marabos.nl/atomics/         (Rust Atomics and Locks, free online)
+///
+/// Every example returns a `Throughput`, so that approaches can be compared.
+/// NOTE: run the comparisons with `cargo run --release`,
+/// in debug builds the numbers mostly measure the lack of optimisations.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Barrier, Condvar, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How many operations were done, and in how long
+#[derive(Debug, Clone, Copy)]
+pub struct Throughput {
+    pub label: &'static str,
+    pub ops: u64,
+    pub elapsed: Duration,
+}
+
+impl Throughput {
+    fn measure(label: &'static str, ops: u64, start: Instant) -> Throughput {
+        Throughput { label, ops, elapsed: start.elapsed() }
+    }
+    pub fn ops_per_sec(&self) -> f64 {
+        self.ops as f64 / self.elapsed.as_secs_f64().max(1e-9)
+    }
+}
+
+impl fmt::Display for Throughput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:<32} {:>10} ops in {:>10.3?}  = {:>14.0} ops/s",
+               self.label, self.ops, self.elapsed, self.ops_per_sec())
+    }
+}
+
+/* ======== Condvar ========
+   ========================= */
+// A Mutex protects data, but it cannot make a thread WAIT for a condition
+// ("the queue is not empty any more") without spinning on the lock.
+// A Condvar (condition variable) does exactly that:
+//      wait(guard)     releases the lock and sleeps, re-acquires it when woken up
+//      notify_one()    wakes up one waiting thread
+//      notify_all()    wakes up all of them
+// A Condvar is always used together with a Mutex,
+// and a woken-up thread must re-check its condition: wake-ups can be SPURIOUS.
+
+/// A FIFO queue with a maximum capacity:
+/// `push` blocks when full, `pop` blocks when empty
+pub struct BoundedQueue<T> {
+    state: Mutex<QueueState<T>>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    capacity: usize,
+}
+
+struct QueueState<T> {
+    items: VecDeque<T>,
+    closed: bool,
+}
+
+impl<T> BoundedQueue<T> {
+    pub fn new(capacity: usize) -> BoundedQueue<T> {
+        assert!(capacity > 0, "a BoundedQueue needs a positive capacity");
+        BoundedQueue {
+            state: Mutex::new(QueueState { items: VecDeque::with_capacity(capacity), closed: false }),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            capacity,
+        }
+    }
+
+    /// Blocks while the queue is full.
+    /// Returns the item back if the queue has been closed.
+    pub fn push(&self, item: T) -> Result<(), T> {
+        let mut state = self.state.lock().unwrap();
+        // `while`, not `if`: this is the re-check against spurious wake-ups
+        while state.items.len() == self.capacity && !state.closed {
+            state = self.not_full.wait(state).unwrap();
+        }
+        if state.closed {
+            return Err(item);
+        }
+        state.items.push_back(item);
+        self.not_empty.notify_one();
+        Ok(())
+    }
+
+    /// Blocks while the queue is empty.
+    /// Returns `None` once the queue is closed AND drained.
+    pub fn pop(&self) -> Option<T> {
+        let mut state = self.state.lock().unwrap();
+        // `wait_while` is the same loop as in `push`, packaged by the library
+        state = self.not_empty
+            .wait_while(state, |s| s.items.is_empty() && !s.closed)
+            .unwrap();
+        let item = state.items.pop_front();
+        if item.is_some() {
+            self.not_full.notify_one();
+        }
+        item
+    }
+
+    /// No more pushes: wakes up everybody so that consumers can finish
+    pub fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.not_empty.notify_all();
+        self.not_full.notify_all();
+    }
+}
+
+/// `producers` threads push `items_each` numbers, `consumers` threads sum them up
+pub fn producer_consumer(producers: u64, consumers: u64, items_each: u64, capacity: usize) -> (u64, Throughput) {
+    let queue = Arc::new(BoundedQueue::new(capacity));
+    let start = Instant::now();
+
+    let producer_handles: Vec<_> = (0..producers)
+        .map(|p| {
+            let queue = Arc::clone(&queue);
+            thread::spawn(move || {
+                for i in 0..items_each {
+                    queue.push(p * items_each + i).unwrap();
+                }
+            })
+        })
+        .collect();
+    let consumer_handles: Vec<_> = (0..consumers)
+        .map(|_| {
+            let queue = Arc::clone(&queue);
+            thread::spawn(move || {
+                let mut sum = 0;
+                while let Some(x) = queue.pop() {
+                    sum += x;
+                }
+                sum
+            })
+        })
+        .collect();
+
+    for h in producer_handles {
+        h.join().unwrap();
+    }
+    // only now the consumers can be told that nothing else is coming
+    queue.close();
+    let total = consumer_handles.into_iter().map(|h| h.join().unwrap()).sum();
+    (total, Throughput::measure("Condvar bounded queue", producers * items_each, start))
+}
+
+/* ======== RwLock =========
+   ========================= */
+// A Mutex gives access to ONE thread at a time, even if all of them only read.
+// A RwLock mirrors the borrowing rules at runtime, across threads:
+//      many readers (`read()`)   OR   one writer (`write()`)
+// So it pays off when reads are much more frequent than writes, like in a cache.
+
+/// A memoising cache of an expensive function, shared between threads
+pub struct Cache {
+    map: RwLock<HashMap<u64, u64>>,
+    compute: fn(u64) -> u64,
+}
+
+impl Cache {
+    pub fn new(compute: fn(u64) -> u64) -> Cache {
+        Cache { map: RwLock::new(HashMap::new()), compute }
+    }
+
+    pub fn get(&self, key: u64) -> u64 {
+        // fast path: a shared read lock, many threads can be here at once
+        if let Some(v) = self.map.read().unwrap().get(&key) {
+            return *v;
+        }
+        // slow path: compute OUTSIDE of any lock, then take the write lock to store.
+        // QUIZ: two threads may both miss and compute the same key. Is that a bug?
+
+
+
+
+        //
+        // No: they compute the same value, and `entry` keeps the first one stored.
+        // It is only wasted work, which is cheaper than holding the write lock while computing.
+        let v = (self.compute)(key);
+        *self.map.write().unwrap().entry(key).or_insert(v)
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.read().unwrap().len()
+    }
+}
+
+// an expensive function for the cache: the Collatz stopping time
+fn collatz_steps(mut n: u64) -> u64 {
+    let mut steps = 0;
+    while n > 1 {
+        n = if n % 2 == 0 { n / 2 } else { 3 * n + 1 };
+        steps += 1;
+    }
+    steps
+}
+
+/// `threads` threads each do `lookups` lookups over only `keys` distinct keys
+pub fn read_mostly_cache(threads: u64, lookups: u64, keys: u64) -> (u64, Throughput) {
+    let cache = Arc::new(Cache::new(collatz_steps));
+    let start = Instant::now();
+    let handles: Vec<_> = (0..threads)
+        .map(|t| {
+            let cache = Arc::clone(&cache);
+            thread::spawn(move || {
+                (0..lookups).map(|i| cache.get(1 + (i * 7 + t) % keys)).sum::<u64>()
+            })
+        })
+        .collect();
+    let total = handles.into_iter().map(|h| h.join().unwrap()).sum();
+    (total, Throughput::measure("RwLock read-mostly cache", threads * lookups, start))
+}
+
+/* ======= Atomics =========
+   ========================= */
+// For a single integer, a lock is overkill:
+// the CPU can do "read, add one, write back" as one indivisible (atomic) instruction.
+// `AtomicUsize` & co. expose these instructions, and they work through `&self`:
+// atomics are interior mutability (c11_heap) that is safe across threads.
+//
+// Each atomic operation takes an `Ordering`, which says what OTHER memory
+// operations are guaranteed to be seen in order around it:
+//      Relaxed     only this variable is atomic, no ordering with anything else.
+//                  Fine for a counter that is read after `join`.
+//      Acquire / Release   used in pairs to publish data: whatever was written before
+//                  a Release store is visible after the Acquire load that reads it.
+//      SeqCst      the strongest: all SeqCst operations appear in one global order.
+// When in doubt use SeqCst; then check with the book above whether you can relax.
+
+/// Which counter implementation to run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CounterKind {
+    Mutex,
+    AtomicRelaxed,
+    AtomicSeqCst,
+}
+
+pub fn counter(kind: CounterKind, threads: u64, increments: u64) -> (u64, Throughput) {
+    let start = Instant::now();
+    let total = match kind {
+        CounterKind::Mutex => {
+            let c = Arc::new(Mutex::new(0u64));
+            let handles: Vec<_> = (0..threads)
+                .map(|_| {
+                    let c = Arc::clone(&c);
+                    thread::spawn(move || for _ in 0..increments { *c.lock().unwrap() += 1; })
+                })
+                .collect();
+            handles.into_iter().for_each(|h| h.join().unwrap());
+            let total = *c.lock().unwrap();
+            total
+        }
+        CounterKind::AtomicRelaxed | CounterKind::AtomicSeqCst => {
+            let ordering = if kind == CounterKind::AtomicRelaxed { Ordering::Relaxed } else { Ordering::SeqCst };
+            let c = Arc::new(AtomicUsize::new(0));
+            let handles: Vec<_> = (0..threads)
+                .map(|_| {
+                    let c = Arc::clone(&c);
+                    thread::spawn(move || for _ in 0..increments { c.fetch_add(1, ordering); })
+                })
+                .collect();
+            handles.into_iter().for_each(|h| h.join().unwrap());
+            // `join` already synchronises with the threads, so even a Relaxed load is exact here
+            c.load(Ordering::Relaxed) as u64
+        }
+    };
+    let label = match kind {
+        CounterKind::Mutex => "Mutex<u64> counter",
+        CounterKind::AtomicRelaxed => "AtomicUsize counter (Relaxed)",
+        CounterKind::AtomicSeqCst => "AtomicUsize counter (SeqCst)",
+    };
+    (total, Throughput::measure(label, threads * increments, start))
+}
+
+/* ======== Barrier ========
+   ========================= */
+// A Barrier makes N threads wait for each other:
+// nobody gets past `wait()` until all N have called it.
+// This gives lock-step PHASES: every thread finishes phase k before anyone starts phase k+1.
+//
+// Our example is a parallel prefix sum (Hillis-Steele scan):
+// in phase k, every element i adds the element i - 2^k of the PREVIOUS phase.
+// After log2(n) phases, element i holds the sum of elements 0..=i.
+// Each thread owns a slice of the indices, and two buffers are swapped at each phase,
+// so a thread never reads a value that another thread is writing in the same phase.
+
+pub fn barrier_prefix_sum(input: &[u64], threads: usize) -> (Vec<u64>, Throughput) {
+    let n = input.len();
+    let threads = threads.max(1).min(n.max(1));
+    let buffers: Arc<[Vec<AtomicU64>; 2]> = Arc::new([
+        input.iter().map(|&x| AtomicU64::new(x)).collect(),
+        input.iter().map(|_| AtomicU64::new(0)).collect(),
+    ]);
+    let barrier = Arc::new(Barrier::new(threads));
+    let mut phases = 0;
+    while (1usize << phases) < n {
+        phases += 1;
+    }
+    let start = Instant::now();
+
+    let handles: Vec<_> = (0..threads)
+        .map(|t| {
+            let buffers = Arc::clone(&buffers);
+            let barrier = Arc::clone(&barrier);
+            // indices [lo, hi) belong to this thread
+            let lo = t * n / threads;
+            let hi = (t + 1) * n / threads;
+            thread::spawn(move || {
+                for k in 0..phases {
+                    let (from, to) = (&buffers[k % 2], &buffers[(k + 1) % 2]);
+                    let step = 1 << k;
+                    for i in lo..hi {
+                        let mut v = from[i].load(Ordering::Relaxed);
+                        if i >= step {
+                            v += from[i - step].load(Ordering::Relaxed);
+                        }
+                        to[i].store(v, Ordering::Relaxed);
+                    }
+                    // the barrier is also what makes the Relaxed stores above
+                    // visible to the other threads in the next phase
+                    barrier.wait();
+                }
+            })
+        })
+        .collect();
+    for h in handles {
+        h.join().unwrap();
+    }
+    let result = buffers[phases % 2].iter().map(|a| a.load(Ordering::Relaxed)).collect();
+    (result, Throughput::measure("Barrier prefix sum", (n * phases) as u64, start))
+}
+
+/* ===== Scoped threads ====
+   ========================= */
+// `thread::spawn` requires `'static` closures: the thread could outlive the caller,
+// so it cannot borrow the caller's local variables (hence all the Arc above).
+// `thread::scope` guarantees that every thread spawned in the scope is joined
+// before the scope returns, so threads CAN borrow from the stack.
+
+pub fn scoped_sum(data: &[u64], threads: usize) -> (u64, Throughput) {
+    let start = Instant::now();
+    let chunk = data.len().div_ceil(threads.max(1)).max(1);
+    let total = thread::scope(|s| {
+        // `data` is a plain borrowed slice: no Arc, no clone
+        let handles: Vec<_> = data
+            .chunks(chunk)
+            .map(|part| s.spawn(move || part.iter().sum::<u64>()))
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).sum()
+    });
+    (total, Throughput::measure("scoped threads sum", data.len() as u64, start))
+}
+
+pub fn gallery() {
+    let (sum, t) = producer_consumer(4, 4, 50_000, 64);
+    println!("{}   (sum {})", t, sum);
+    let (sum, t) = read_mostly_cache(4, 200_000, 1_000);
+    println!("{}   (sum {})", t, sum);
+    for kind in [CounterKind::Mutex, CounterKind::AtomicRelaxed, CounterKind::AtomicSeqCst] {
+        let (total, t) = counter(kind, 4, 250_000);
+        println!("{}   (total {})", t, total);
+    }
+    let input: Vec<u64> = (1..=100_000).collect();
+    let (prefix, t) = barrier_prefix_sum(&input, 4);
+    println!("{}   (last {})", t, prefix[prefix.len() - 1]);
+    // the array lives on the stack of this function
+    let local = [3u64; 100_000];
+    let (sum, t) = scoped_sum(&local, 4);
+    println!("{}   (sum {})", t, sum);
+    // QUIZ: which counter is the fastest? and why is the Mutex one slower?
+}
+
+/* ======= Poisoning =======
+   ========================= */
+// In `c11_heap::par::arcmutex` we wrote `counter.lock().unwrap()` without asking
+// why `lock` returns a Result at all.
+// QUIZ: what can go wrong when taking a lock?
+
+
+
+
+
+//
+// The previous owner of the lock may have PANICKED while holding it.
+// Its guard was dropped during unwinding, so the lock is free,
+// but the data may have been left half-updated.
+// Rust records this: the Mutex becomes POISONED, and every following `lock()`
+// returns `Err(PoisonError)` instead of silently handing out possibly broken data.
+// The `PoisonError` still contains the guard: we can decide that the data is fine
+// (or repair it) and get it back with `into_inner()`.
+pub mod poisoning {
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    /// Spawns a thread that panics halfway through an update of `data`,
+    /// while holding the lock. Returns whether the thread panicked.
+    pub fn poison(data: &Arc<Mutex<Vec<i32>>>) -> bool {
+        let data = Arc::clone(data);
+        let handle = thread::spawn(move || {
+            let mut guard = data.lock().unwrap();
+            guard.push(1);
+            // the invariant "the vector holds pairs" is now broken ...
+            panic!("panicking while holding the lock");
+            // ... and the second push never happens
+            #[allow(unreachable_code)]
+            guard.push(2);
+        });
+        handle.join().is_err()
+    }
+
+    /// Takes the lock even if it is poisoned, repairing the data in that case
+    pub fn lock_and_repair(data: &Mutex<Vec<i32>>) -> Vec<i32> {
+        let guard = match data.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                println!("the mutex is poisoned, repairing the data");
+                let mut guard = poisoned.into_inner();
+                // our repair: drop the unpaired element
+                if guard.len() % 2 == 1 {
+                    guard.pop();
+                }
+                guard
+            }
+        };
+        guard.clone()
+    }
+
+    pub fn poisonexample() {
+        let data = Arc::new(Mutex::new(vec![10, 20]));
+        println!("thread panicked: {}", poison(&data));
+        println!("is the mutex poisoned? {}", data.is_poisoned());
+        // QUIZ: what does this print?
+        match data.lock() {
+            Ok(v) => println!("got {:?}", *v),
+            Err(e) => println!("got an error, {}, with data {:?}", e, *e.get_ref()),
+        }
+        println!("after the repair: {:?}", lock_and_repair(&data));
+        // poisoning is a flag, not a state of the data: it stays until we clear it
+        println!("still poisoned? {}", data.is_poisoned());
+        data.clear_poison();
+        println!("after clear_poison? {}", data.is_poisoned());
+        // once we are the only owner, the whole Mutex can be consumed:
+        // `Mutex::into_inner` reports poisoning in the same way
+        let mutex = Arc::try_unwrap(data).unwrap();
+        println!("final data {:?}", mutex.into_inner().unwrap_or_else(|e| e.into_inner()));
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn panicking_inside_the_lock_poisons_it() {
+            let data = Arc::new(Mutex::new(vec![10, 20]));
+            assert!(poison(&data));
+            assert!(data.is_poisoned());
+            assert!(data.lock().is_err());
+            // the data is still there, half-updated
+            assert_eq!(*data.lock().unwrap_err().into_inner(), vec![10, 20, 1]);
+        }
+
+        #[test]
+        fn poisoned_data_can_be_recovered() {
+            let data = Arc::new(Mutex::new(vec![10, 20]));
+            poison(&data);
+            assert_eq!(lock_and_repair(&data), vec![10, 20]);
+            data.clear_poison();
+            assert!(data.lock().is_ok());
+        }
+
+        #[test]
+        fn healthy_mutex_is_not_touched() {
+            let data = Mutex::new(vec![1]);
+            assert_eq!(lock_and_repair(&data), vec![1]);
+        }
+    }
+}
+
+/* ======= Deadlocks =======
+   ========================= */
+// Rust prevents data races at compile time, but NOT deadlocks.
+// The classic deadlock needs two locks taken in opposite orders:
+//      thread 1:   lock A, then lock B
+//      thread 2:   lock B, then lock A
+// If each thread gets its first lock, each one waits forever for the other.
+//
+// `std::sync::Mutex` has no "lock with a timeout", but it has `try_lock`,
+// which never blocks: we build a timeout on top of it,
+// and a watchdog that collects which threads got stuck and on what.
+pub mod deadlock {
+    use std::sync::{Arc, Barrier, Mutex, MutexGuard, TryLockError};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    /// A Mutex with a name, so that reports can say which lock was involved
+    pub struct NamedMutex<T> {
+        pub name: &'static str,
+        inner: Mutex<T>,
+    }
+
+    impl<T> NamedMutex<T> {
+        pub fn new(name: &'static str, value: T) -> NamedMutex<T> {
+            NamedMutex { name, inner: Mutex::new(value) }
+        }
+    }
+
+    /// A thread that gave up waiting for a lock
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct StuckReport {
+        pub thread: String,
+        pub holding: Vec<&'static str>,
+        pub waiting_for: &'static str,
+        pub waited: Duration,
+    }
+
+    /// Hands out locks with a timeout, and remembers every timeout
+    pub struct Watchdog {
+        timeout: Duration,
+        reports: Mutex<Vec<StuckReport>>,
+    }
+
+    impl Watchdog {
+        pub fn new(timeout: Duration) -> Watchdog {
+            Watchdog { timeout, reports: Mutex::new(vec![]) }
+        }
+
+        /// Tries to lock `m` for at most the watchdog timeout.
+        /// `holding` lists the locks the calling thread already has, for the report.
+        pub fn lock<'a, T>(&self, m: &'a NamedMutex<T>, holding: &[&'static str]) -> Option<MutexGuard<'a, T>> {
+            let start = Instant::now();
+            loop {
+                match m.inner.try_lock() {
+                    Ok(guard) => return Some(guard),
+                    // in this demo nobody panics, but a poisoned lock is still a lock we got
+                    Err(TryLockError::Poisoned(p)) => return Some(p.into_inner()),
+                    Err(TryLockError::WouldBlock) => {}
+                }
+                if start.elapsed() >= self.timeout {
+                    let report = StuckReport {
+                        thread: thread::current().name().unwrap_or("<unnamed>").to_string(),
+                        holding: holding.to_vec(),
+                        waiting_for: m.name,
+                        waited: start.elapsed(),
+                    };
+                    self.reports.lock().unwrap().push(report);
+                    return None;
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+
+        pub fn reports(&self) -> Vec<StuckReport> {
+            let mut r = self.reports.lock().unwrap().clone();
+            r.sort_by(|a, b| a.thread.cmp(&b.thread));
+            r
+        }
+    }
+
+    // Each thread moves 1 from `from` to `to`, so it needs both locks.
+    // With `ordered`, it takes them in the global order (by name), otherwise `from` first.
+    // `meet` makes sure that both threads hold their first lock before going on:
+    // without it the deadlock would only happen SOMETIMES, which is what makes
+    // deadlocks so nasty in real programs, but would make our tests flaky.
+    fn transfer(name: &str, from: Arc<NamedMutex<i32>>, to: Arc<NamedMutex<i32>>, ordered: bool,
+                watchdog: Arc<Watchdog>, meet: Option<Arc<Barrier>>) -> thread::JoinHandle<bool> {
+        thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || {
+                let from_first = !ordered || from.name <= to.name;
+                let (first, second) = if from_first { (&from, &to) } else { (&to, &from) };
+                let mut g1 = watchdog.lock(first, &[]).expect("nobody holds a lock for longer than the timeout");
+                if let Some(meet) = &meet {
+                    meet.wait();
+                }
+                match watchdog.lock(second, &[first.name]) {
+                    Some(mut g2) => {
+                        let (f, t) = if from_first { (&mut *g1, &mut *g2) } else { (&mut *g2, &mut *g1) };
+                        *f -= 1;
+                        *t += 1;
+                        true
+                    }
+                    None => {
+                        // we still hold our first lock: wait until every thread gave up,
+                        // otherwise the first one to give up would let the other one through
+                        if let Some(meet) = &meet {
+                            meet.wait();
+                        }
+                        false
+                    }
+                }
+            })
+            .unwrap()
+    }
+
+    /// Runs two threads that lock A and B in opposite orders.
+    /// Returns the watchdog reports: one per stuck thread.
+    pub fn opposite_orders(timeout: Duration) -> Vec<StuckReport> {
+        let a = Arc::new(NamedMutex::new("A", 100));
+        let b = Arc::new(NamedMutex::new("B", 100));
+        let watchdog = Arc::new(Watchdog::new(timeout));
+        let meet = Arc::new(Barrier::new(2));
+        let t1 = transfer("thread-1", Arc::clone(&a), Arc::clone(&b), false, Arc::clone(&watchdog), Some(Arc::clone(&meet)));
+        let t2 = transfer("thread-2", Arc::clone(&b), Arc::clone(&a), false, Arc::clone(&watchdog), Some(meet));
+        t1.join().unwrap();
+        t2.join().unwrap();
+        watchdog.reports()
+    }
+
+    /// The fix: every thread takes the locks in the SAME global order (here, by name),
+    /// whatever order it needs them in.
+    pub fn same_order(timeout: Duration) -> (Vec<StuckReport>, i32, i32) {
+        let a = Arc::new(NamedMutex::new("A", 100));
+        let b = Arc::new(NamedMutex::new("B", 100));
+        let watchdog = Arc::new(Watchdog::new(timeout));
+        // no `meet` barrier here: with ordered locks, the second thread simply waits
+        // on its FIRST lock until the other one is done
+        let t1 = transfer("thread-1", Arc::clone(&a), Arc::clone(&b), true, Arc::clone(&watchdog), None);
+        let t2 = transfer("thread-2", Arc::clone(&b), Arc::clone(&a), true, Arc::clone(&watchdog), None);
+        t1.join().unwrap();
+        t2.join().unwrap();
+        let (va, vb) = (*a.inner.lock().unwrap(), *b.inner.lock().unwrap());
+        (watchdog.reports(), va, vb)
+    }
+
+    pub fn deadlockexample() {
+        for r in opposite_orders(Duration::from_millis(200)) {
+            println!("{} holds {:?} and gave up on {} after {:?}",
+                     r.thread, r.holding, r.waiting_for, r.waited);
+        }
+        let (reports, a, b) = same_order(Duration::from_millis(200));
+        println!("with ordered locking: {} stuck threads, A = {}, B = {}", reports.len(), a, b);
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn opposite_orders_are_reported_as_stuck() {
+            let reports = opposite_orders(Duration::from_millis(50));
+            assert_eq!(reports.len(), 2);
+            assert_eq!(reports[0].thread, "thread-1");
+            assert_eq!(reports[0].holding, vec!["A"]);
+            assert_eq!(reports[0].waiting_for, "B");
+            assert_eq!(reports[1].thread, "thread-2");
+            assert_eq!(reports[1].holding, vec!["B"]);
+            assert_eq!(reports[1].waiting_for, "A");
+            assert!(reports.iter().all(|r| r.waited >= Duration::from_millis(50)));
+        }
+
+        #[test]
+        fn same_order_never_gets_stuck() {
+            // a generous timeout: the test must not depend on scheduling
+            let (reports, a, b) = same_order(Duration::from_secs(5));
+            assert!(reports.is_empty());
+            // the first thread moves 1 from A to B, the second from B to A
+            assert_eq!((a, b), (100, 100));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounded_queue_delivers_every_item_once() {
+        let (sum, t) = producer_consumer(3, 2, 1000, 4);
+        let n = 3 * 1000;
+        assert_eq!(sum, n * (n - 1) / 2);
+        assert_eq!(t.ops, n);
+    }
+
+    #[test]
+    fn closed_queue_refuses_pushes_and_drains() {
+        let q = BoundedQueue::new(2);
+        q.push(1).unwrap();
+        q.close();
+        assert_eq!(q.push(2), Err(2));
+        assert_eq!(q.pop(), Some(1));
+        assert_eq!(q.pop(), None);
+    }
+
+    #[test]
+    fn cache_computes_each_key_once_stored() {
+        let (sum, _) = read_mostly_cache(4, 1000, 10);
+        let expected: u64 = (0..4u64)
+            .map(|t| (0..1000u64).map(|i| collatz_steps(1 + (i * 7 + t) % 10)).sum::<u64>())
+            .sum();
+        assert_eq!(sum, expected);
+        let cache = Cache::new(collatz_steps);
+        assert_eq!(cache.get(27), 111);
+        assert_eq!(cache.get(27), 111);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn every_counter_is_exact() {
+        for kind in [CounterKind::Mutex, CounterKind::AtomicRelaxed, CounterKind::AtomicSeqCst] {
+            assert_eq!(counter(kind, 4, 1000).0, 4000, "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn barrier_prefix_sum_matches_sequential() {
+        for n in [0usize, 1, 2, 7, 64, 1000] {
+            let input: Vec<u64> = (0..n as u64).map(|x| x * 3 + 1).collect();
+            let expected: Vec<u64> = input
+                .iter()
+                .scan(0, |acc, x| { *acc += x; Some(*acc) })
+                .collect();
+            for threads in [1, 3, 8] {
+                assert_eq!(barrier_prefix_sum(&input, threads).0, expected, "n={} threads={}", n, threads);
+            }
+        }
+    }
+
+    #[test]
+    fn scoped_threads_borrow_the_stack() {
+        let local = [2u64; 1001];
+        assert_eq!(scoped_sum(&local, 4).0, 2002);
+        assert_eq!(scoped_sum(&[], 4).0, 0);
+    }
+}
@@ -3,15 +3,37 @@
 // Below is a list of those files inside this directory that are externally visible
 pub mod c01_basic;
 pub mod c02_ownership;
+pub mod c03_cidr;
 pub mod c03_enums;
+pub mod c03_errors;
+pub mod c03_expr;
+pub mod c03_files;
+pub mod c03_optionresult;
+pub mod c03_textstats;
+pub mod c04_builders;
+pub mod c04_layout;
 pub mod c04_structs;
 pub mod c04_structshelper;
+pub mod c04_users;
 pub mod c05_modules;
 pub mod c06_testing;
 pub mod c07_lifetimes;
 pub mod c13_maps;
 pub mod c08_traits;
 pub mod c10_OOP;
+pub mod c10_averagers;
+pub mod c10_casts;
+pub mod c10_ecs;
+pub mod c10_plugins;
+pub mod c10_shapes;
+pub mod c10_show;
+pub mod c10_stats;
 pub mod c11_heap;
+pub mod c11_cells;
+pub mod c11_heapalloc;
+pub mod c11_threadpool;
 pub mod c12_fp;
+pub mod c12_bintree;
+pub mod c12_mllists;
+pub mod c14_concurrency;
 pub mod c99_QA;
\ No newline at end of file
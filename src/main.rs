@@ -13,15 +13,37 @@ use crate::classes as basedir;
 use basedir::c01_basic as c1;
 use basedir::c02_ownership as c2;
 // use basedir::c03_enums as c3;
+use basedir::c03_cidr as c3c;
+use basedir::c03_errors as c3e;
+use basedir::c03_expr as c3x;
+use basedir::c03_files as c3f;
+use basedir::c03_optionresult as c3o;
+use basedir::c03_textstats as c3t;
+use basedir::c04_builders as c4b;
+use basedir::c04_layout as c4l;
 use basedir::c04_structs as c4;
+use basedir::c04_users as c4u;
 use basedir::c05_modules as c5;
 use basedir::c06_testing as c6;
 use basedir::c07_lifetimes as c7;
 use basedir::c13_maps as cm;
 use basedir::c08_traits as c9;
 use basedir::c10_OOP as c10;
+use basedir::c10_averagers as c10a;
+use basedir::c10_casts as c10c;
+use basedir::c10_ecs as c10e;
+use basedir::c10_plugins as c10q;
+use basedir::c10_shapes as c10s;
+use basedir::c10_show as c10p;
+use basedir::c10_stats as c10t;
 use basedir::c11_heap as c11;
+use basedir::c11_cells as c11c;
+use basedir::c11_heapalloc as c11a;
+use basedir::c11_threadpool as c11t;
 use basedir::c12_fp as c12;
+use basedir::c12_bintree as c12b;
+use basedir::c12_mllists as c12l;
+use basedir::c14_concurrency as c14;
 use basedir::c99_QA as cqa;
 
 // Below is the main function.
@@ -31,6 +53,46 @@ use basedir::c99_QA as cqa;
 //      pub => public, callable by other functions that import this module
 //      if one writes no modifier => private, only callable from this file
 pub fn main() {
+    // Subcommands, e.g. `cargo run -- analyze foo.txt`:
+    // the first argument is the program itself, so we skip it.
+    // Without a known subcommand, we run the examples uncommented below.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(|s| s.as_str()) {
+        Some("analyze") => {
+            match c3f::analyzecommand(&args[1..]) {
+                Ok(stats) => print!("{}", stats),
+                Err(msg) => {
+                    eprintln!("{}", msg);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        // e.g. `seq 1 20 | cargo run -- average window:5 ewma:0.3`
+        Some("average") => {
+            match c10a::averagecommand(&args[1..]) {
+                Ok(report) => eprintln!("{}", report),
+                Err(msg) => {
+                    eprintln!("{}", msg);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        // e.g. `cargo run -- quack --list`, `cargo run --release -- quack --bench`
+        Some("quack") => {
+            match c10q::quackcommand(&args[1..]) {
+                Ok(out) => print!("{}", out),
+                Err(msg) => {
+                    eprintln!("{}", msg);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        _ => {}
+    }
+
     // // // from c01_basic
     // c1::var_ass_mut();
     // c1::vals_types();
@@ -50,18 +112,28 @@ pub fn main() {
     // c3::enum_usage();
     // c3::option();
     // c3::patternmatching();
+    // c3x::exprexample();
     // c3::errors();
+    // c3e::errorsexample();
+    // c3::ipaddr_usage();
+    // c3c::cidrexample();
+    // c3o::optionresult_usage();
     //
     // // from c04_structs
     // c4::struct_usage();
     // c4::struct_printing();
     // c4::struct_impl();
+    // c4l::layoutexample();
+    // c4u::usersexample().unwrap();
+    // c4b::buildersexample().unwrap();
 
     // c3::testqm();
     // let r = c3::readfilecontent();
     // if r.is_err(){
     //         println!("Error {:?}", r.unwrap_err());
     // }
+    // c3f::filesexample();
+    // c3t::textstatsexample();
     // c4::ownstructs();
 
     //
@@ -99,6 +171,13 @@ pub fn main() {
     // c10::example_oop1();
     // c10::example_animals_oop();
     // c10::example_multiple_traits();
+    // let _ = c10s::shapesexample();
+    // c10t::statsexample();
+    // c10a::averagersexample();
+    // c10p::showexample();
+    // c10q::pluginsexample();
+    // c10c::castsexample();
+    // c10e::ecsexample();
     //
     // // c11_heap
     // c11::example_box();
@@ -108,6 +187,10 @@ pub fn main() {
     // c11::example_drop();
     // c11::example_rc();
     // c11::implitictderef();
+    // c11::implicitderefmut();
+    // c11::mybox_conversions();
+    // c11::example_mybox_drop();
+    // c11::cowexample();
     c11::arc();
     // c11::refcell_usage();
     // c11::refcell_usage_2();
@@ -118,7 +201,15 @@ pub fn main() {
     // c11::graphexample();
     // c11::cellexamplee();
     // c11::rcwithcellexample();
+    // c11c::cellsexample();
     // c11::par::arcmutex();
+    // c11::par::arcmutex_pool();
+    // c11t::poolexample();
+    //
+    // // c11_heapalloc, run with `--features heapalloc`
+    // c11a::example_origin_vs_boxed();
+    // c11a::example_heap_examples();
+    // c11a::example_list_vs_vec();
     //
     // // c12_fp
     // c12::closures::closuresexample();
@@ -131,6 +222,13 @@ pub fn main() {
     // c12::iterators::examplefpiterators();
     // c12::iterators::calling_next_directly();
     // c12::iterators::using_other_iterator_trait_methods();
+    // c12b::bintreeexample();
+    // c12l::mllistsexample();
+
+    // // c14_concurrency, better with `cargo run --release`
+    // c14::gallery();
+    // c14::poisoning::poisonexample();
+    // c14::deadlock::deadlockexample();
 
     // cqa::traitqa::testit();
 }